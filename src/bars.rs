@@ -0,0 +1,277 @@
+use std::fmt;
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use ta::{Close, High, Low, Open, Volume};
+use crate::config::env_or;
+use crate::model::TradeData;
+
+/// How ticks are grouped into a bar.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum BarKind {
+    Time(i64),   // Seconds per bar
+    Tick(u64),   // Trades per bar
+    Volume(f64), // BTC per bar
+    Dollar(f64), // USDT per bar
+}
+
+impl BarKind {
+    /// Parses `1s`, `1m`, `5m`, `1h`, `tick:500`, `volume:10` or `dollar:1000000`.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim().to_lowercase();
+        if let Some((kind, size)) = raw.split_once(':') {
+            return match kind {
+                "tick" | "ticks" => size.parse().ok().filter(|&n| n > 0).map(BarKind::Tick),
+                "volume" | "vol" => size.parse().ok().filter(|&v| v > 0.0).map(BarKind::Volume),
+                "dollar" | "usd" => size.parse().ok().filter(|&v| v > 0.0).map(BarKind::Dollar),
+                _ => None,
+            };
+        }
+        let unit = match raw.chars().last()? {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            _ => return None,
+        };
+        let n: i64 = raw[..raw.len() - 1].parse().ok()?;
+        (n > 0).then_some(BarKind::Time(n * unit))
+    }
+}
+
+impl fmt::Display for BarKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BarKind::Time(secs) if secs % 3600 == 0 => write!(f, "{}h", secs / 3600),
+            BarKind::Time(secs) if secs % 60 == 0 => write!(f, "{}m", secs / 60),
+            BarKind::Time(secs) => write!(f, "{}s", secs),
+            BarKind::Tick(n) => write!(f, "tick:{}", n),
+            BarKind::Volume(v) => write!(f, "volume:{}", v),
+            BarKind::Dollar(v) => write!(f, "dollar:{}", v),
+        }
+    }
+}
+
+impl From<BarKind> for String {
+    fn from(kind: BarKind) -> Self {
+        kind.to_string()
+    }
+}
+
+impl TryFrom<String> for BarKind {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        BarKind::parse(&raw).ok_or_else(|| format!("bad bar kind {:?}", raw))
+    }
+}
+
+/// OHLCV of one bar with the volume split by aggressor side.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bar {
+    pub kind: BarKind,
+    pub open_time: DateTime<Utc>,  // Window start for time bars, first tick otherwise
+    pub close_time: DateTime<Utc>, // Window end for time bars, last tick otherwise
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub buy_volume: f64,  // Buyer was the taker
+    pub sell_volume: f64, // Seller was the taker
+    pub dollar_volume: f64,
+    pub trades: u64,
+}
+
+impl Bar {
+    fn start(kind: BarKind, open_time: DateTime<Utc>, tick: &TradeData) -> Self {
+        let mut bar = Self {
+            kind,
+            open_time,
+            close_time: tick.timestamp,
+            open: tick.price,
+            high: tick.price,
+            low: tick.price,
+            close: tick.price,
+            volume: 0.0,
+            buy_volume: 0.0,
+            sell_volume: 0.0,
+            dollar_volume: 0.0,
+            trades: 0,
+        };
+        bar.add(tick);
+        bar
+    }
+
+    fn add(&mut self, tick: &TradeData) {
+        self.high = self.high.max(tick.price);
+        self.low = self.low.min(tick.price);
+        self.close = tick.price;
+        self.volume += tick.quantity;
+        if tick.is_buyer_maker {
+            self.sell_volume += tick.quantity;
+        } else {
+            self.buy_volume += tick.quantity;
+        }
+        self.dollar_volume += tick.price * tick.quantity;
+        self.trades += 1;
+        if !matches!(self.kind, BarKind::Time(_)) {
+            self.close_time = tick.timestamp;
+        }
+    }
+}
+
+impl Open for Bar { fn open(&self) -> f64 { self.open } }
+impl High for Bar { fn high(&self) -> f64 { self.high } }
+impl Low for Bar { fn low(&self) -> f64 { self.low } }
+impl Close for Bar { fn close(&self) -> f64 { self.close } }
+impl Volume for Bar { fn volume(&self) -> f64 { self.volume } }
+
+/// Builds one kind of bar. Time bars close on the first tick after their window, so a
+/// quiet market leaves no empty bars. Volume and dollar bars take the whole tick that
+/// crosses the threshold rather than splitting it.
+pub struct BarBuilder {
+    pub kind: BarKind,
+    current: Option<Bar>,
+}
+
+impl BarBuilder {
+    pub fn new(kind: BarKind) -> Self {
+        Self { kind, current: None }
+    }
+
+    /// Adds a tick, returning the bar it completed.
+    pub fn update(&mut self, tick: &TradeData) -> Option<Bar> {
+        if let BarKind::Time(secs) = self.kind {
+            let start = tick.timestamp.timestamp().div_euclid(secs) * secs;
+            let open_time = DateTime::from_timestamp(start, 0).unwrap_or(tick.timestamp);
+            return match self.current.as_mut() {
+                Some(bar) if bar.open_time == open_time => {
+                    bar.add(tick);
+                    None
+                }
+                // Out-of-order tick from an older window; that bar is gone
+                Some(bar) if bar.open_time > open_time => None,
+                _ => {
+                    let mut bar = Bar::start(self.kind, open_time, tick);
+                    bar.close_time = open_time + Duration::seconds(secs);
+                    self.current.replace(bar)
+                }
+            };
+        }
+
+        let bar = match self.current.as_mut() {
+            Some(bar) => {
+                bar.add(tick);
+                bar
+            }
+            None => self.current.insert(Bar::start(self.kind, tick.timestamp, tick)),
+        };
+        let full = match self.kind {
+            BarKind::Tick(n) => bar.trades >= n,
+            BarKind::Volume(v) => bar.volume >= v,
+            BarKind::Dollar(v) => bar.dollar_volume >= v,
+            BarKind::Time(_) => false,
+        };
+        if full { self.current.take() } else { None }
+    }
+}
+
+/// Every bar kind the engine builds.
+pub struct BarSet {
+    builders: Vec<BarBuilder>,
+}
+
+impl BarSet {
+    /// Reads `BARS` (comma-separated, default `1s,1m,5m`).
+    pub fn from_env() -> Self {
+        let mut set = Self { builders: Vec::new() };
+        for raw in env_or("BARS", "1s,1m,5m".to_string()).split(',').filter(|s| !s.trim().is_empty()) {
+            match BarKind::parse(raw) {
+                Some(kind) => set.ensure(kind),
+                None => warn!("⚠️ UNKNOWN BAR KIND {:?} ignored", raw.trim()),
+            }
+        }
+        set
+    }
+
+    /// Adds a builder for `kind` unless one exists.
+    pub fn ensure(&mut self, kind: BarKind) {
+        if !self.builders.iter().any(|b| b.kind == kind) {
+            self.builders.push(BarBuilder::new(kind));
+        }
+    }
+
+    pub fn kinds(&self) -> Vec<BarKind> {
+        self.builders.iter().map(|b| b.kind).collect()
+    }
+
+    /// Adds a tick to every builder, returning the bars it completed.
+    pub fn update(&mut self, tick: &TradeData) -> Vec<Bar> {
+        self.builders.iter_mut().filter_map(|b| b.update(tick)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(secs: i64, price: f64, quantity: f64, is_buyer_maker: bool) -> TradeData {
+        let start: DateTime<Utc> = "2026-01-01T10:00:00Z".parse().unwrap();
+        TradeData { timestamp: start + Duration::milliseconds(secs * 1000 + 250), price, quantity, is_buyer_maker }
+    }
+
+    #[test]
+    fn time_bars_align_to_the_clock_and_skip_quiet_windows() {
+        let mut builder = BarBuilder::new(BarKind::Time(60));
+        let start: DateTime<Utc> = "2026-01-01T10:00:00Z".parse().unwrap();
+        assert!(builder.update(&tick(30, 100.0, 1.0, false)).is_none());
+        assert!(builder.update(&tick(59, 101.0, 1.0, false)).is_none());
+
+        // No ticks from 10:01 to 10:03; the 10:03 tick closes the 10:00 bar and nothing else
+        let bar = builder.update(&tick(185, 102.0, 1.0, false)).unwrap();
+        assert_eq!((bar.open_time, bar.close_time), (start, start + Duration::minutes(1)));
+        assert_eq!(bar.trades, 2);
+
+        let bar = builder.update(&tick(240, 103.0, 1.0, false)).unwrap();
+        assert_eq!(bar.open_time, start + Duration::minutes(3));
+        assert_eq!(bar.close, 102.0);
+        // A late tick from a closed window is dropped
+        assert!(builder.update(&tick(200, 99.0, 1.0, false)).is_none());
+    }
+
+    #[test]
+    fn aggregates_ohlcv_and_the_aggressor_split() {
+        let mut builder = BarBuilder::new(BarKind::Tick(4));
+        let ticks = [tick(0, 100.0, 1.0, false), tick(1, 105.0, 2.0, true), tick(2, 98.0, 0.5, false), tick(3, 101.0, 1.5, true)];
+        let bars: Vec<Bar> = ticks.iter().filter_map(|t| builder.update(t)).collect();
+        assert_eq!(bars.len(), 1);
+        let bar = &bars[0];
+        assert_eq!((bar.open, bar.high, bar.low, bar.close), (100.0, 105.0, 98.0, 101.0));
+        assert_eq!((bar.volume, bar.buy_volume, bar.sell_volume), (5.0, 1.5, 3.5));
+        assert_eq!(bar.dollar_volume, 100.0 + 210.0 + 49.0 + 151.5);
+        assert_eq!((bar.open_time, bar.close_time), (ticks[0].timestamp, ticks[3].timestamp));
+    }
+
+    #[test]
+    fn volume_and_dollar_bars_take_the_crossing_tick_whole() {
+        let mut volume = BarBuilder::new(BarKind::Volume(2.0));
+        assert!(volume.update(&tick(0, 100.0, 1.5, false)).is_none());
+        assert_eq!(volume.update(&tick(1, 100.0, 1.5, false)).map(|b| b.volume), Some(3.0));
+
+        let mut dollar = BarBuilder::new(BarKind::Dollar(250.0));
+        assert!(dollar.update(&tick(0, 100.0, 2.0, false)).is_none());
+        assert_eq!(dollar.update(&tick(1, 100.0, 1.0, false)).map(|b| b.trades), Some(2));
+    }
+
+    #[test]
+    fn parses_and_prints_bar_kinds() {
+        for raw in ["1s", "5m", "1h", "tick:500", "volume:10", "dollar:1000000"] {
+            assert_eq!(BarKind::parse(raw).unwrap().to_string(), raw);
+        }
+        assert_eq!(BarKind::parse("90s"), Some(BarKind::Time(90)));
+        assert_eq!(BarKind::parse("0m"), None);
+        assert_eq!(BarKind::parse("tick:0"), None);
+        assert_eq!(BarKind::parse("weekly"), None);
+    }
+}
@@ -0,0 +1,573 @@
+use std::time::Duration;
+use chrono::{TimeZone, Utc};
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use reqwest::{Client, Method, RequestBuilder};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use tokio_tungstenite::connect_async;
+use crate::config::env_or;
+use crate::executor::{trade_id_of, ExecError, ExecutionReport, Executor, OrderAck, OrderRequest, OrderState, Side};
+use crate::fills::Liquidity;
+use crate::pretrade::SymbolRules;
+use crate::simulator::SimMode;
+
+// Binance expires idle listen keys after 60 minutes
+const KEEPALIVE_SECS: u64 = 30 * 60;
+const RECONNECT_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Market {
+    Spot,
+    Futures, // USDⓈ-M perpetual
+}
+
+impl Market {
+    fn order_path(&self) -> &'static str {
+        match self {
+            Market::Spot => "/api/v3/order",
+            Market::Futures => "/fapi/v1/order",
+        }
+    }
+
+    fn exchange_info_path(&self) -> &'static str {
+        match self {
+            Market::Spot => "/api/v3/exchangeInfo",
+            Market::Futures => "/fapi/v1/exchangeInfo",
+        }
+    }
+
+    fn listen_key_path(&self) -> &'static str {
+        match self {
+            Market::Spot => "/api/v3/userDataStream",
+            Market::Futures => "/fapi/v1/listenKey",
+        }
+    }
+
+    /// (REST, WebSocket) base URLs.
+    fn default_urls(&self, testnet: bool) -> (&'static str, &'static str) {
+        match (self, testnet) {
+            (Market::Spot, true) => ("https://testnet.binance.vision", "wss://stream.testnet.binance.vision"),
+            (Market::Spot, false) => ("https://api.binance.com", "wss://stream.binance.com:9443"),
+            (Market::Futures, true) => ("https://testnet.binancefuture.com", "wss://stream.binancefuture.com"),
+            (Market::Futures, false) => ("https://fapi.binance.com", "wss://fstream.binance.com"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct BinanceConfig {
+    pub market: Market,
+    pub testnet: bool,
+    pub rest_url: String,
+    pub ws_url: String,
+    pub symbol: String,
+    api_key: String,
+    api_secret: String,
+    recv_window: u64,
+}
+
+impl BinanceConfig {
+    /// The market follows `SIM_MODE` (spot or perp; binary options have no exchange
+    /// equivalent). Reads `BINANCE_API_KEY`, `BINANCE_API_SECRET`, `BINANCE_TESTNET`
+    /// (default true), `BINANCE_SYMBOL` (BTCUSDT), `BINANCE_RECV_WINDOW` (5000ms) and
+    /// `BINANCE_REST_URL` / `BINANCE_WS_URL` to point at a mock exchange.
+    pub fn from_env(mode: SimMode) -> anyhow::Result<Self> {
+        let market = match mode {
+            SimMode::Spot => Market::Spot,
+            SimMode::Perpetual => Market::Futures,
+            SimMode::Binary => anyhow::bail!("SIM_MODE=binary cannot be executed on Binance, use spot or perp"),
+        };
+        let api_key = env_or("BINANCE_API_KEY", String::new());
+        let api_secret = env_or("BINANCE_API_SECRET", String::new());
+        if api_key.is_empty() || api_secret.is_empty() {
+            anyhow::bail!("BINANCE_API_KEY and BINANCE_API_SECRET are required");
+        }
+
+        let testnet = env_or("BINANCE_TESTNET", true);
+        let (rest, ws) = market.default_urls(testnet);
+        Ok(Self {
+            market,
+            testnet,
+            rest_url: env_or("BINANCE_REST_URL", rest.to_string()).trim_end_matches('/').to_string(),
+            ws_url: env_or("BINANCE_WS_URL", ws.to_string()).trim_end_matches('/').to_string(),
+            symbol: env_or("BINANCE_SYMBOL", "BTCUSDT".to_string()).to_uppercase(),
+            api_key,
+            api_secret,
+            recv_window: env_or("BINANCE_RECV_WINDOW", 5000),
+        })
+    }
+
+    /// Appends `timestamp`, `recvWindow` and the HMAC-SHA256 `signature` to a query string.
+    fn sign(&self, params: &[(&str, String)]) -> String {
+        self.sign_at(params, Utc::now().timestamp_millis())
+    }
+
+    fn sign_at(&self, params: &[(&str, String)], timestamp_ms: i64) -> String {
+        let mut query: Vec<String> = params.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        query.push(format!("recvWindow={}", self.recv_window));
+        query.push(format!("timestamp={}", timestamp_ms));
+        let query = query.join("&");
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.api_secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(query.as_bytes());
+        format!("{}&signature={}", query, hex::encode(mac.finalize().into_bytes()))
+    }
+}
+
+/// Order as returned by place, cancel and query. Spot and futures name a few fields differently.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OrderResponse {
+    order_id: i64,
+    client_order_id: String,
+    #[serde(default)]
+    orig_client_order_id: Option<String>, // Spot cancel responses
+    status: String,
+    #[serde(default)]
+    executed_qty: String,
+    #[serde(default)]
+    cummulative_quote_qty: Option<String>, // Spot (sic)
+    #[serde(default)]
+    avg_price: Option<String>,             // Futures
+}
+
+impl OrderResponse {
+    fn ack(self) -> OrderAck {
+        let filled_qty = num(&self.executed_qty);
+        let avg_price = match (&self.avg_price, &self.cummulative_quote_qty) {
+            (Some(avg), _) => num(avg),
+            (None, Some(quote)) if filled_qty > 0.0 => num(quote) / filled_qty,
+            _ => 0.0,
+        };
+        OrderAck {
+            client_order_id: self.orig_client_order_id.filter(|c| !c.is_empty()).unwrap_or(self.client_order_id),
+            exchange_order_id: Some(self.order_id),
+            state: OrderState::parse(&self.status),
+            filled_qty,
+            avg_price,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    code: i64,
+    msg: String,
+}
+
+/// What the user-data stream task hands the executor.
+enum StreamEvent {
+    Connected,
+    Report(ExecutionReport),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListenKey {
+    listen_key: String,
+}
+
+/// Binance spot or USDⓈ-M futures over signed REST, with fills from the user-data stream.
+pub struct BinanceExecutor {
+    client: Client,
+    config: BinanceConfig,
+    pub rules: SymbolRules,
+    events: mpsc::UnboundedReceiver<StreamEvent>,
+    reconnected: bool,
+}
+
+impl BinanceExecutor {
+    /// Loads the symbol's trading rules and spawns the user-data stream. Reports queue
+    /// up until `drain_reports`.
+    pub async fn connect(config: BinanceConfig) -> anyhow::Result<Self> {
+        let client = Client::new();
+        let rules = exchange_rules(&client, &config).await?;
+        info!("📏 {} RULES: tick {} | lot {} | min qty {} | min notional ${}",
+            rules.symbol, rules.tick_size, rules.step_size, rules.min_qty, rules.min_notional);
+        let (tx, events) = mpsc::unbounded_channel();
+        tokio::spawn(user_data_stream(client.clone(), config.clone(), tx));
+        Ok(Self { client, config, rules, events, reconnected: false })
+    }
+
+    fn signed(&self, method: Method, path: &str, params: &[(&str, String)]) -> RequestBuilder {
+        let url = format!("{}{}?{}", self.config.rest_url, path, self.config.sign(params));
+        self.client.request(method, url)
+            .header("X-MBX-APIKEY", &self.config.api_key)
+            .timeout(Duration::from_secs(10))
+    }
+
+    /// A market order for the pre-trade rounded quantity. Futures close an opposite
+    /// position and open the new one in the same order.
+    fn order_params(&self, order: &OrderRequest) -> Vec<(&'static str, String)> {
+        vec![
+            ("symbol", order.symbol.clone()),
+            ("side", order.side().label().to_string()),
+            ("type", "MARKET".to_string()),
+            ("quantity", format!("{:.*}", self.rules.qty_decimals(), order.quantity)),
+            ("newClientOrderId", order.client_order_id()),
+            ("newOrderRespType", "RESULT".to_string()),
+        ]
+    }
+}
+
+/// Lot size, tick size and minimum notional from `exchangeInfo`.
+async fn exchange_rules(client: &Client, config: &BinanceConfig) -> anyhow::Result<SymbolRules> {
+    let info: Value = client.get(format!("{}{}", config.rest_url, config.market.exchange_info_path()))
+        .query(&[("symbol", &config.symbol)])
+        .timeout(Duration::from_secs(10))
+        .send().await?
+        .error_for_status()?
+        .json().await?;
+    let symbol = info["symbols"].as_array()
+        .and_then(|all| all.iter().find(|s| s["symbol"] == config.symbol.as_str()))
+        .ok_or_else(|| anyhow::anyhow!("{} not listed in exchangeInfo", config.symbol))?;
+
+    let mut rules = SymbolRules {
+        symbol: config.symbol.clone(),
+        tick_size: 0.0,
+        step_size: 0.0,
+        min_qty: 0.0,
+        max_qty: f64::INFINITY,
+        min_notional: 0.0,
+    };
+    for filter in symbol["filters"].as_array().into_iter().flatten() {
+        match filter["filterType"].as_str().unwrap_or_default() {
+            "PRICE_FILTER" => rules.tick_size = num_field(&filter["tickSize"]),
+            "LOT_SIZE" => {
+                rules.step_size = num_field(&filter["stepSize"]);
+                rules.min_qty = num_field(&filter["minQty"]);
+                rules.max_qty = rules.max_qty.min(num_field(&filter["maxQty"]));
+            }
+            // Market orders can have a tighter cap than limit orders
+            "MARKET_LOT_SIZE" if num_field(&filter["maxQty"]) > 0.0 => {
+                rules.max_qty = rules.max_qty.min(num_field(&filter["maxQty"]));
+            }
+            // Spot calls it minNotional, futures notional
+            "NOTIONAL" | "MIN_NOTIONAL" => {
+                rules.min_notional = num_field(&filter["minNotional"]).max(num_field(&filter["notional"]));
+            }
+            _ => {}
+        }
+    }
+    Ok(rules)
+}
+
+impl Executor for BinanceExecutor {
+    fn name(&self) -> &'static str {
+        "binance"
+    }
+
+    /// Places a market order. A timeout leaves the outcome unknown; `status` resolves it.
+    async fn submit(&mut self, order: &OrderRequest) -> Result<OrderAck, ExecError> {
+        let req = self.signed(Method::POST, self.config.market.order_path(), &self.order_params(order));
+        send(req, true).await
+    }
+
+    async fn cancel(&mut self, client_order_id: &str) -> Result<OrderAck, ExecError> {
+        let params = [("symbol", self.config.symbol.clone()), ("origClientOrderId", client_order_id.to_string())];
+        send(self.signed(Method::DELETE, self.config.market.order_path(), &params), false).await
+    }
+
+    async fn status(&mut self, client_order_id: &str) -> Result<OrderAck, ExecError> {
+        let params = [("symbol", self.config.symbol.clone()), ("origClientOrderId", client_order_id.to_string())];
+        send(self.signed(Method::GET, self.config.market.order_path(), &params), false).await
+    }
+
+    fn drain_reports(&mut self) -> Vec<ExecutionReport> {
+        let mut out = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                StreamEvent::Connected => self.reconnected = true,
+                StreamEvent::Report(report) => out.push(report),
+            }
+        }
+        out
+    }
+
+    fn take_reconnected(&mut self) -> bool {
+        std::mem::take(&mut self.reconnected)
+    }
+}
+
+/// Sends a signed request and maps Binance's error conventions. `mutating` requests that
+/// fail in flight may still have executed, so those come back as `Unknown`.
+async fn send(req: RequestBuilder, mutating: bool) -> Result<OrderAck, ExecError> {
+    let in_flight = |e: String| if mutating { ExecError::Unknown(e) } else { ExecError::Transient(e) };
+    let resp = req.send().await.map_err(|e| {
+        if e.is_connect() { ExecError::Transient(e.to_string()) } else { in_flight(e.to_string()) }
+    })?;
+    let status = resp.status();
+    let retry_after = resp.headers().get("retry-after")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    let body = resp.text().await.map_err(|e| in_flight(e.to_string()))?;
+
+    if status.is_success() {
+        return serde_json::from_str::<OrderResponse>(&body)
+            .map(OrderResponse::ack)
+            .map_err(|e| in_flight(format!("unreadable response ({}): {}", e, body)));
+    }
+    match (status.as_u16(), serde_json::from_str::<ApiError>(&body)) {
+        // 418 is an IP ban after ignoring 429s
+        (429 | 418, _) => Err(ExecError::RateLimited(Duration::from_secs(retry_after.unwrap_or(1)))),
+        // -2011 cancel of an unknown order, -2013 query of an unknown order
+        (_, Ok(err)) if err.code == -2011 || err.code == -2013 => Err(ExecError::NotFound),
+        (code, _) if code >= 500 => Err(in_flight(format!("{}: {}", status, body))),
+        (_, Ok(err)) => Err(ExecError::Rejected { code: err.code, msg: err.msg }),
+        (_, Err(_)) => Err(ExecError::Rejected { code: status.as_u16() as i64, msg: body }),
+    }
+}
+
+/// Keeps a user-data stream open and forwards our symbol's order updates. Reconnects with
+/// a fresh listen key whenever the socket drops. Runs until the executor is dropped.
+async fn user_data_stream(client: Client, config: BinanceConfig, tx: mpsc::UnboundedSender<StreamEvent>) {
+    loop {
+        if let Err(e) = run_user_data_stream(&client, &config, &tx).await {
+            warn!("⚠️ USER DATA STREAM DROPPED: {}. Reconnecting in {}s", e, RECONNECT_SECS);
+        }
+        if tx.is_closed() { return; }
+        tokio::time::sleep(Duration::from_secs(RECONNECT_SECS)).await;
+    }
+}
+
+async fn run_user_data_stream(client: &Client, config: &BinanceConfig, tx: &mpsc::UnboundedSender<StreamEvent>) -> anyhow::Result<()> {
+    let key_url = format!("{}{}", config.rest_url, config.market.listen_key_path());
+    let key: ListenKey = client.post(&key_url)
+        .header("X-MBX-APIKEY", &config.api_key)
+        .timeout(Duration::from_secs(10))
+        .send().await?
+        .error_for_status()?
+        .json().await?;
+
+    let (mut ws, _) = connect_async(format!("{}/ws/{}", config.ws_url, key.listen_key)).await?;
+    info!("🔐 USER DATA STREAM CONNECTED ({:?}{})", config.market, if config.testnet { ", testnet" } else { "" });
+    if tx.send(StreamEvent::Connected).is_err() { return Ok(()); }
+
+    let mut keepalive = tokio::time::interval(Duration::from_secs(KEEPALIVE_SECS));
+    keepalive.tick().await;
+    loop {
+        tokio::select! {
+            msg = ws.next() => {
+                let text = match msg {
+                    Some(Ok(m)) if m.is_text() => m.into_text()?,
+                    Some(Ok(m)) if m.is_close() => anyhow::bail!("closed by server"),
+                    Some(Ok(_)) => continue, // Pings are answered by tungstenite
+                    Some(Err(e)) => return Err(e.into()),
+                    None => anyhow::bail!("stream ended"),
+                };
+                let Ok(event) = serde_json::from_str::<Value>(&text) else { continue };
+                if event["e"] == "listenKeyExpired" {
+                    anyhow::bail!("listen key expired");
+                }
+                if let Some(report) = parse_order_update(&event, &config.symbol) {
+                    if tx.send(StreamEvent::Report(report)).is_err() { return Ok(()); }
+                }
+            }
+            _ = keepalive.tick() => {
+                client.put(&key_url)
+                    .header("X-MBX-APIKEY", &config.api_key)
+                    .query(&[("listenKey", &key.listen_key)])
+                    .timeout(Duration::from_secs(10))
+                    .send().await?
+                    .error_for_status()?;
+            }
+        }
+    }
+}
+
+/// Spot `executionReport` and futures `ORDER_TRADE_UPDATE` share single-letter field names.
+fn parse_order_update(event: &Value, symbol: &str) -> Option<ExecutionReport> {
+    let o = match event["e"].as_str()? {
+        "executionReport" => event,
+        "ORDER_TRADE_UPDATE" => &event["o"],
+        _ => return None,
+    };
+    if o["s"].as_str()? != symbol { return None; }
+
+    let state = OrderState::parse(o["X"].as_str()?);
+    // Spot cancels carry the original id in "C"; "c" is the cancel request's own id
+    let client_order_id = match o["C"].as_str() {
+        Some(orig) if !orig.is_empty() && state == OrderState::Canceled => orig,
+        _ => o["c"].as_str()?,
+    }.to_string();
+    let cum_qty = num_field(&o["z"]);
+    let avg_price = match o["ap"].as_str() {
+        Some(ap) => num(ap),
+        None if cum_qty > 0.0 => num_field(&o["Z"]) / cum_qty,
+        None => 0.0,
+    };
+    let last_qty = num_field(&o["l"]);
+
+    Some(ExecutionReport {
+        trade_id: trade_id_of(&client_order_id),
+        client_order_id,
+        exchange_order_id: o["i"].as_i64(),
+        side: if o["S"] == "BUY" { Side::Buy } else { Side::Sell },
+        state,
+        last_qty,
+        last_price: num_field(&o["L"]),
+        cum_qty,
+        avg_price,
+        fee: num_field(&o["n"]),
+        fee_asset: o["N"].as_str().unwrap_or_default().to_string(),
+        liquidity: (last_qty > 0.0).then(|| if o["m"] == true { Liquidity::Maker } else { Liquidity::Taker }),
+        reason: o["r"].as_str().filter(|r| *r != "NONE").map(str::to_string),
+        time: Utc.timestamp_millis_opt(o["T"].as_i64().or(event["E"].as_i64())?).single()?,
+    })
+}
+
+fn num(s: &str) -> f64 {
+    s.parse().unwrap_or(0.0)
+}
+
+fn num_field(v: &Value) -> f64 {
+    v.as_str().map(num).or_else(|| v.as_f64()).unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::mock_http::{MockServer, Reply};
+
+    fn config(rest_url: &str, api_secret: &str) -> BinanceConfig {
+        BinanceConfig {
+            market: Market::Spot,
+            testnet: true,
+            rest_url: rest_url.to_string(),
+            ws_url: "ws://127.0.0.1:9".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            api_key: "KEY".to_string(),
+            api_secret: api_secret.to_string(),
+            recv_window: 5000,
+        }
+    }
+
+    async fn executor(server: &MockServer) -> BinanceExecutor {
+        let config = config(&server.url, "SECRET");
+        let client = Client::new();
+        let rules = exchange_rules(&client, &config).await.unwrap();
+        // No stream task: reports are fed by the tests that need them
+        let (_tx, events) = mpsc::unbounded_channel();
+        BinanceExecutor { client, config, rules, events, reconnected: false }
+    }
+
+    fn exchange_info() -> Reply {
+        Reply::new(200, &json!({"symbols": [{"symbol": "BTCUSDT", "filters": [
+            {"filterType": "PRICE_FILTER", "tickSize": "0.01"},
+            {"filterType": "LOT_SIZE", "stepSize": "0.00001", "minQty": "0.00001", "maxQty": "9000"},
+            {"filterType": "MARKET_LOT_SIZE", "maxQty": "120"},
+            {"filterType": "NOTIONAL", "minNotional": "5"}
+        ]}]}).to_string())
+    }
+
+    fn order() -> OrderRequest {
+        OrderRequest {
+            trade_id: 7,
+            symbol: "BTCUSDT".to_string(),
+            direction: "UP".to_string(),
+            price: 50_000.0,
+            stake_pct: 1.0,
+            notional: 100.0,
+            close_quantity: 0.0,
+            quantity: 0.002,
+        }
+    }
+
+    #[test]
+    fn signs_the_documented_example() {
+        // HMAC example from the Binance spot API documentation
+        let config = config("", "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j");
+        let params = [("symbol", "LTCBTC"), ("side", "BUY"), ("type", "LIMIT"), ("timeInForce", "GTC"), ("quantity", "1"), ("price", "0.1")]
+            .map(|(k, v)| (k, v.to_string()));
+        assert_eq!(config.sign_at(&params, 1499827319559),
+            "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559\
+             &signature=c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71");
+    }
+
+    #[tokio::test]
+    async fn loads_rules_and_submits_a_signed_market_order() {
+        let filled = json!({"symbol": "BTCUSDT", "orderId": 99, "clientOrderId": "qe-7", "status": "FILLED",
+            "executedQty": "0.00200000", "cummulativeQuoteQty": "100.02000000"});
+        let server = MockServer::replies(vec![exchange_info(), Reply::new(200, &filled.to_string())]).await;
+        let mut exec = executor(&server).await;
+        assert_eq!((exec.rules.tick_size, exec.rules.step_size, exec.rules.max_qty, exec.rules.min_notional), (0.01, 0.00001, 120.0, 5.0));
+
+        let ack = exec.submit(&order()).await.unwrap();
+        assert_eq!((ack.client_order_id.as_str(), ack.exchange_order_id, ack.state), ("qe-7", Some(99), OrderState::Filled));
+        assert!((ack.avg_price - 50_010.0).abs() < 1e-6);
+
+        let request = &server.requests()[1];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.header("x-mbx-apikey"), Some("KEY"));
+        assert!(request.path.starts_with("/api/v3/order?symbol=BTCUSDT&side=BUY&type=MARKET&quantity=0.00200&newClientOrderId=qe-7&"));
+        assert!(request.path.contains("&recvWindow=5000&timestamp="));
+        assert!(request.path.contains("&signature="));
+    }
+
+    #[tokio::test]
+    async fn maps_binance_errors() {
+        let server = MockServer::replies(vec![
+            exchange_info(),
+            Reply::new(429, r#"{"code":-1003,"msg":"Too many requests"}"#).header("Retry-After", "7"),
+            Reply::new(418, r#"{"code":-1003,"msg":"Way too many requests; IP banned"}"#),
+            Reply::new(400, r#"{"code":-2011,"msg":"Unknown order sent."}"#),
+            Reply::new(400, r#"{"code":-2013,"msg":"Order does not exist."}"#),
+            Reply::new(503, "Service Unavailable"),
+            Reply::new(502, "Bad Gateway"),
+            Reply::new(400, r#"{"code":-1013,"msg":"Filter failure: LOT_SIZE"}"#),
+        ]).await;
+        let mut exec = executor(&server).await;
+
+        assert!(matches!(exec.submit(&order()).await, Err(ExecError::RateLimited(d)) if d == Duration::from_secs(7)));
+        assert!(matches!(exec.submit(&order()).await, Err(ExecError::RateLimited(d)) if d == Duration::from_secs(1)));
+        assert!(matches!(exec.cancel("qe-7").await, Err(ExecError::NotFound)));
+        assert!(matches!(exec.status("qe-7").await, Err(ExecError::NotFound)));
+        // A 5xx on an order may still have executed; on a query it is just a retry
+        assert!(matches!(exec.submit(&order()).await, Err(ExecError::Unknown(_))));
+        assert!(matches!(exec.status("qe-7").await, Err(ExecError::Transient(_))));
+        assert!(matches!(exec.submit(&order()).await, Err(ExecError::Rejected { code: -1013, .. })));
+    }
+
+    #[tokio::test]
+    async fn unreachable_venue_is_safe_to_retry() {
+        let server = MockServer::replies(vec![exchange_info()]).await;
+        let mut exec = executor(&server).await;
+        exec.config.rest_url = "http://127.0.0.1:9".to_string(); // Nothing listens on the discard port
+        assert!(matches!(exec.submit(&order()).await, Err(ExecError::Transient(_))));
+    }
+
+    #[test]
+    fn parses_spot_and_futures_order_updates() {
+        let spot = json!({"e": "executionReport", "E": 1_700_000_000_100i64, "s": "BTCUSDT", "c": "qe-7", "C": "",
+            "S": "BUY", "X": "FILLED", "i": 99, "l": "0.002", "L": "50010.00", "z": "0.002", "Z": "100.02",
+            "n": "0.1", "N": "USDT", "m": false, "r": "NONE", "T": 1_700_000_000_000i64});
+        let report = parse_order_update(&spot, "BTCUSDT").unwrap();
+        assert_eq!((report.trade_id, report.state, report.side), (Some(7), OrderState::Filled, Side::Buy));
+        assert_eq!((report.last_qty, report.last_price, report.fee), (0.002, 50_010.0, 0.1));
+        assert!((report.avg_price - 50_010.0).abs() < 1e-6);
+        assert_eq!(report.liquidity, Some(Liquidity::Taker));
+        assert_eq!(report.reason, None);
+        assert_eq!(report.time.timestamp_millis(), 1_700_000_000_000);
+
+        // Spot cancels carry the original id in "C"
+        let cancel = json!({"e": "executionReport", "E": 1, "s": "BTCUSDT", "c": "web_1", "C": "qe-8",
+            "S": "SELL", "X": "CANCELED", "i": 100, "l": "0", "z": "0", "r": "NONE", "T": 2});
+        let report = parse_order_update(&cancel, "BTCUSDT").unwrap();
+        assert_eq!((report.client_order_id.as_str(), report.state, report.liquidity), ("qe-8", OrderState::Canceled, None));
+
+        let futures = json!({"e": "ORDER_TRADE_UPDATE", "E": 3, "T": 3, "o": {"s": "BTCUSDT", "c": "qe-9", "S": "SELL",
+            "X": "PARTIALLY_FILLED", "i": 5, "l": "0.001", "L": "49990", "z": "0.001", "ap": "49990", "n": "0.02",
+            "N": "USDT", "m": true, "T": 4}});
+        let report = parse_order_update(&futures, "BTCUSDT").unwrap();
+        assert_eq!((report.trade_id, report.state, report.avg_price), (Some(9), OrderState::PartiallyFilled, 49_990.0));
+        assert_eq!(report.liquidity, Some(Liquidity::Maker));
+
+        assert!(parse_order_update(&json!({"e": "executionReport", "s": "ETHUSDT"}), "BTCUSDT").is_none());
+        assert!(parse_order_update(&json!({"e": "outboundAccountPosition"}), "BTCUSDT").is_none());
+    }
+}
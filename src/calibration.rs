@@ -0,0 +1,349 @@
+use std::collections::VecDeque;
+use std::fmt;
+use chrono::{DateTime, Duration, Utc};
+use crate::config::env_or;
+use crate::model::TradeData;
+use crate::strategy::Evaluation;
+
+const BUCKET_WIDTH: f64 = 5.0; // Reliability buckets, % of forecast from 50 to 100
+const BANDS: [(f64, f64); 6] = [(50.0, 60.0), (60.0, 70.0), (70.0, 80.0), (80.0, 90.0), (90.0, 95.0), (95.0, 100.0)];
+
+/// How signals are sampled and when they are scored.
+#[derive(Debug, Clone)]
+pub struct CalibrationConfig {
+    pub horizon: Duration,    // Signal is scored on the price this long after it
+    pub sample_gap: Duration, // Min gap between recorded signals; the strategy evaluates every tick
+}
+
+impl CalibrationConfig {
+    /// Reads `CALIBRATION_HORIZON_SECS` (60, the binary expiry) and `CALIBRATION_SAMPLE_MS`
+    /// (0, every evaluation is recorded; raise it to thin out busy tapes).
+    pub fn from_env() -> Self {
+        Self {
+            horizon: Duration::seconds(env_or("CALIBRATION_HORIZON_SECS", 60i64).max(1)),
+            sample_gap: Duration::milliseconds(env_or("CALIBRATION_SAMPLE_MS", 0i64).max(0)),
+        }
+    }
+}
+
+/// An evaluated signal scored against the price at its horizon.
+#[derive(Debug, Clone)]
+pub struct SignalOutcome {
+    pub time: DateTime<Utc>,
+    pub direction: String, // UP / DOWN / NEUTRAL as the strategy called it
+    pub price: f64,
+    pub confidence: f64,
+    pub prob_up: f64,
+    pub whale_confirmed: bool,
+    pub feature_confirmed: bool,
+    pub passed: bool,      // Cleared every gate of the strategy
+    pub horizon_secs: i64,
+    pub exit_price: f64,   // Last trade at or before the horizon, like a binary expiry
+}
+
+impl SignalOutcome {
+    /// Probability given to the side prob_up leans to. Equals the confidence of UP and
+    /// DOWN signals; NEUTRAL ones are scored on their lean as well.
+    pub fn forecast(&self) -> f64 {
+        self.prob_up.max(1.0 - self.prob_up)
+    }
+
+    /// Whether the lean was right. None when the price did not move.
+    pub fn hit(&self) -> Option<bool> {
+        if self.exit_price == self.price { return None; }
+        Some((self.exit_price > self.price) == (self.prob_up >= 0.5))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tally {
+    pub signals: u64,
+    pub hits: u64,
+    forecast_sum: f64,
+}
+
+impl Tally {
+    fn add(&mut self, forecast: f64, hit: bool) {
+        self.signals += 1;
+        self.hits += hit as u64;
+        self.forecast_sum += forecast;
+    }
+
+    /// Realised hit rate, %.
+    pub fn hit_rate(&self) -> Option<f64> {
+        (self.signals > 0).then(|| self.hits as f64 / self.signals as f64 * 100.0)
+    }
+
+    /// Mean forecast, %.
+    pub fn mean_forecast(&self) -> Option<f64> {
+        (self.signals > 0).then(|| self.forecast_sum / self.signals as f64 * 100.0)
+    }
+}
+
+/// Records every signal the primary strategy evaluates, traded or not, and scores it
+/// once its horizon has passed. Only running tallies are kept in memory.
+pub struct CalibrationTracker {
+    pub config: CalibrationConfig,
+    pending: VecDeque<(DateTime<Utc>, SignalOutcome)>, // Horizon, outcome waiting for its exit price
+    last_sample: Option<DateTime<Utc>>,
+    last_price: Option<f64>,
+    flat: u64,
+    ups: u64,
+    brier_sum: f64,
+    buckets: [Tally; 10],
+    bands: [[Tally; 3]; BANDS.len()], // All, whale confirmed, passed
+}
+
+impl CalibrationTracker {
+    pub fn new(config: CalibrationConfig) -> Self {
+        Self {
+            config,
+            pending: VecDeque::new(),
+            last_sample: None,
+            last_price: None,
+            flat: 0,
+            ups: 0,
+            brier_sum: 0.0,
+            buckets: [Tally::default(); 10],
+            bands: [[Tally::default(); 3]; BANDS.len()],
+        }
+    }
+
+    /// Queues an evaluation unless one was recorded less than `sample_gap` ago.
+    pub fn record(&mut self, eval: Evaluation) {
+        if self.last_sample.is_some_and(|t| eval.time - t < self.config.sample_gap) { return; }
+        self.last_sample = Some(eval.time);
+        let s = eval.signal;
+        self.pending.push_back((eval.time + self.config.horizon, SignalOutcome {
+            time: eval.time,
+            direction: s.direction,
+            price: eval.price,
+            confidence: s.confidence,
+            prob_up: s.prob_up,
+            whale_confirmed: s.is_whale_confirmed,
+            feature_confirmed: s.is_feature_confirmed,
+            passed: eval.passed,
+            horizon_secs: self.config.horizon.num_seconds(),
+            exit_price: eval.price,
+        }));
+    }
+
+    /// Scores the signals whose horizon this tick has passed, returning them for storage.
+    pub fn update(&mut self, tick: &TradeData) -> Vec<SignalOutcome> {
+        let mut resolved = Vec::new();
+        while self.pending.front().is_some_and(|(horizon, _)| tick.timestamp > *horizon) {
+            let Some((_, mut outcome)) = self.pending.pop_front() else { break };
+            outcome.exit_price = self.last_price.unwrap_or(outcome.price);
+            self.add(&outcome);
+            resolved.push(outcome);
+        }
+        self.last_price = Some(tick.price);
+        resolved
+    }
+
+    /// Adds a scored signal to the tallies, e.g. one loaded from the store.
+    pub fn add(&mut self, outcome: &SignalOutcome) {
+        let Some(hit) = outcome.hit() else {
+            self.flat += 1;
+            return;
+        };
+        let forecast = outcome.forecast();
+        let up = outcome.exit_price > outcome.price;
+        self.ups += up as u64;
+        self.brier_sum += (outcome.prob_up - if up { 1.0 } else { 0.0 }).powi(2);
+
+        let pct = forecast * 100.0;
+        let bucket = (((pct - 50.0) / BUCKET_WIDTH) as usize).min(self.buckets.len() - 1);
+        self.buckets[bucket].add(forecast, hit);
+
+        let band = BANDS.iter().position(|&(_, hi)| pct < hi).unwrap_or(BANDS.len() - 1);
+        let tallies = &mut self.bands[band];
+        tallies[0].add(forecast, hit);
+        if outcome.whale_confirmed { tallies[1].add(forecast, hit); }
+        if outcome.passed { tallies[2].add(forecast, hit); }
+    }
+
+    pub fn report(&self) -> CalibrationReport {
+        let scored: u64 = self.buckets.iter().map(|b| b.signals).sum();
+        let brier = (scored > 0).then(|| self.brier_sum / scored as f64);
+        // Skill against always forecasting the base rate of up moves
+        let base = self.ups as f64 / scored.max(1) as f64;
+        let reference = base * (1.0 - base);
+        CalibrationReport {
+            horizon_secs: self.config.horizon.num_seconds(),
+            scored,
+            flat: self.flat,
+            pending: self.pending.len(),
+            brier,
+            skill: brier.filter(|_| reference > 1e-12).map(|b| 1.0 - b / reference),
+            up_rate: base * 100.0,
+            buckets: self.buckets.iter().enumerate()
+                .map(|(i, t)| (50.0 + i as f64 * BUCKET_WIDTH, 50.0 + (i + 1) as f64 * BUCKET_WIDTH, *t))
+                .collect(),
+            bands: BANDS.iter().zip(&self.bands).map(|(&(lo, hi), t)| (lo, hi, *t)).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CalibrationReport {
+    pub horizon_secs: i64,
+    pub scored: u64,             // Signals the price moved on
+    pub flat: u64,               // Price unchanged at the horizon, left out of the scores
+    pub pending: usize,
+    pub brier: Option<f64>,      // Mean squared error of prob_up, 0.25 is a coin flip
+    pub skill: Option<f64>,      // Brier skill against the base rate, above 0 beats it
+    pub up_rate: f64,            // %
+    pub buckets: Vec<(f64, f64, Tally)>,      // Reliability: forecast % range, tally
+    pub bands: Vec<(f64, f64, [Tally; 3])>,   // Hit rates: all, whale confirmed, passed
+}
+
+impl CalibrationReport {
+    pub fn one_line(&self) -> String {
+        let hits: u64 = self.buckets.iter().map(|b| b.2.hits).sum();
+        format!("Calibration {}s: {} scored | Hit {:.1}% | Brier {} | Skill {}", self.horizon_secs, self.scored,
+            hits as f64 / self.scored.max(1) as f64 * 100.0, opt(self.brier, 4), opt(self.skill, 3))
+    }
+}
+
+fn opt(v: Option<f64>, decimals: usize) -> String {
+    v.map_or("-".to_string(), |v| format!("{:.*}", decimals, v))
+}
+
+impl fmt::Display for CalibrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rate = |t: &Tally| t.hit_rate().map_or("-".to_string(), |r| format!("{:.1}% ({})", r, t.signals));
+        writeln!(f, "Horizon: {}s | Scored: {} | Flat: {} | Pending: {}", self.horizon_secs, self.scored, self.flat, self.pending)?;
+        writeln!(f, "Brier: {} (coin flip 0.2500) | Skill: {} | Up moves: {:.1}%", opt(self.brier, 4), opt(self.skill, 3), self.up_rate)?;
+        writeln!(f, "Reliability (forecast → realised):")?;
+        for (lo, hi, t) in self.buckets.iter().filter(|b| b.2.signals > 0) {
+            let (forecast, realised) = (t.mean_forecast().unwrap_or(0.0), t.hit_rate().unwrap_or(0.0));
+            writeln!(f, "  {:>3.0}-{:<3.0} {:>5.1}% → {:>5.1}% ({:+.1}) n={}", lo, hi, forecast, realised, realised - forecast, t.signals)?;
+        }
+        write!(f, "Hit rate by band (all | whale | passed):")?;
+        for (lo, hi, [all, whale, passed]) in self.bands.iter().filter(|b| b.2[0].signals > 0) {
+            write!(f, "\n  {:>3.0}-{:<3.0} {} | {} | {}", lo, hi, rate(all), rate(whale), rate(passed))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::QuantumSignal;
+
+    fn start() -> DateTime<Utc> {
+        "2026-01-01T00:00:00Z".parse().unwrap()
+    }
+
+    fn tracker() -> CalibrationTracker {
+        CalibrationTracker::new(CalibrationConfig { horizon: Duration::seconds(60), sample_gap: Duration::zero() })
+    }
+
+    fn outcome(prob_up: f64, price: f64, exit_price: f64) -> SignalOutcome {
+        SignalOutcome {
+            time: start(),
+            direction: "UP".to_string(),
+            price,
+            confidence: prob_up.max(1.0 - prob_up) * 100.0,
+            prob_up,
+            whale_confirmed: false,
+            feature_confirmed: false,
+            passed: false,
+            horizon_secs: 60,
+            exit_price,
+        }
+    }
+
+    fn tick(secs: i64, price: f64) -> TradeData {
+        TradeData { timestamp: start() + Duration::seconds(secs), price, quantity: 0.01, is_buyer_maker: false }
+    }
+
+    #[test]
+    fn an_unchanged_price_is_neither_hit_nor_miss() {
+        assert_eq!(outcome(0.8, 100.0, 100.0).hit(), None);
+        assert_eq!(outcome(0.8, 100.0, 101.0).hit(), Some(true));
+        assert_eq!(outcome(0.2, 100.0, 101.0).hit(), Some(false));
+        // An even forecast leans up
+        assert_eq!(outcome(0.5, 100.0, 99.0).hit(), Some(false));
+
+        let mut calibration = tracker();
+        calibration.add(&outcome(0.8, 100.0, 100.0));
+        let report = calibration.report();
+        assert_eq!((report.scored, report.flat), (0, 1));
+        assert_eq!((report.brier, report.skill), (None, None));
+    }
+
+    #[test]
+    fn brier_and_skill_match_hand_computed_values() {
+        let mut calibration = tracker();
+        let mut whale = outcome(0.8, 100.0, 101.0); // Up, hit: (0.8 - 1)² = 0.04
+        whale.whale_confirmed = true;
+        calibration.add(&whale);
+        calibration.add(&outcome(0.28, 100.0, 99.0)); // Down, hit: 0.28² = 0.0784
+        calibration.add(&outcome(0.62, 100.0, 99.0)); // Down, miss: 0.62² = 0.3844
+        let mut passed = outcome(1.0, 100.0, 101.0); // Up, hit: 0
+        passed.passed = true;
+        calibration.add(&passed);
+        calibration.add(&outcome(0.5, 100.0, 101.0)); // Up, hit: 0.25
+
+        let report = calibration.report();
+        assert_eq!(report.scored, 5);
+        let brier = report.brier.unwrap();
+        assert!((brier - 0.7528 / 5.0).abs() < 1e-12);
+        // 3 of 5 moves up: the base rate forecast scores 0.6 × 0.4 = 0.24
+        assert!((report.up_rate - 60.0).abs() < 1e-9);
+        assert!((report.skill.unwrap() - (1.0 - brier / 0.24)).abs() < 1e-12);
+        assert!((report.skill.unwrap() - 0.372_666_666_666_666_7).abs() < 1e-9);
+        // Whale confirmed and passed signals are also tallied on their own
+        assert_eq!((report.bands[3].2[1].signals, report.bands[5].2[2].signals), (1, 1));
+    }
+
+    #[test]
+    fn forecasts_on_an_edge_land_in_the_bucket_and_band_above() {
+        let mut calibration = tracker();
+        calibration.add(&outcome(0.5, 100.0, 101.0));
+        calibration.add(&outcome(1.0, 100.0, 101.0));
+        calibration.add(&outcome(0.8, 100.0, 99.0));
+        let report = calibration.report();
+
+        let signals: Vec<u64> = report.buckets.iter().map(|b| b.2.signals).collect();
+        assert_eq!(signals, vec![1, 0, 0, 0, 0, 0, 1, 0, 0, 1]); // 50-55, 80-85, 95-100 (1.0 is kept in the top bucket)
+        let bands: Vec<(f64, u64)> = report.bands.iter().map(|b| (b.0, b.2[0].signals)).collect();
+        assert_eq!(bands, vec![(50.0, 1), (60.0, 0), (70.0, 0), (80.0, 1), (90.0, 0), (95.0, 1)]);
+
+        let (_, _, top) = report.buckets[9];
+        assert_eq!((top.hit_rate(), top.mean_forecast()), (Some(100.0), Some(100.0)));
+        assert_eq!(report.buckets[6].2.hit_rate(), Some(0.0));
+    }
+
+    #[test]
+    fn scored_on_the_last_price_at_or_before_the_horizon() {
+        let mut calibration = tracker();
+        let signal = QuantumSignal {
+            confidence: 80.0,
+            direction: "UP".to_string(),
+            is_whale_confirmed: false,
+            prob_up: 0.8,
+            ofi: 0.0,
+            features: Default::default(),
+            is_feature_confirmed: false,
+        };
+        // The live loop updates with a tick before recording the evaluation taken on it
+        assert!(calibration.update(&tick(0, 100.0)).is_empty());
+        calibration.record(Evaluation { time: start(), price: 100.0, signal, passed: false });
+
+        assert!(calibration.update(&tick(30, 99.0)).is_empty());
+        assert!(calibration.update(&tick(60, 102.0)).is_empty()); // At the horizon: not past it yet
+        assert_eq!(calibration.report().pending, 1);
+
+        // The first tick past the horizon settles on the price before it
+        let resolved = calibration.update(&tick(61, 90.0));
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].exit_price, 102.0);
+        assert_eq!(resolved[0].hit(), Some(true));
+        let report = calibration.report();
+        assert_eq!((report.scored, report.pending), (1, 0));
+    }
+}
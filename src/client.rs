@@ -4,9 +4,14 @@ use log::{info, warn};
 use reqwest::Client;
 use serde_json::Value;
 use rand::Rng; // Import random number generator for safety net
+use chrono::Utc;
+use tokio::sync::watch;
+use crate::config::env_or;
+use crate::fills::OrderBook;
 use crate::model::TradeData;
+use crate::recorder::{TickRecorder, TickSource};
 
-pub async fn start_market_stream(tx: tokio::sync::mpsc::Sender<TradeData>) -> anyhow::Result<()> {
+pub async fn start_market_stream(tx: tokio::sync::mpsc::Sender<TradeData>, recorder: Option<TickRecorder>) -> anyhow::Result<()> {
     info!("🔌 STARTING HYBRID ENGINE (Cloud-Proof Mode)...");
     
     let client = Client::new();
@@ -51,15 +56,70 @@ pub async fn start_market_stream(tx: tokio::sync::mpsc::Sender<TradeData>) -> an
         last_price = current_price;
 
         let trade = TradeData {
+            timestamp: Utc::now(),
             price: current_price,
             quantity: 0.1,
             is_buyer_maker: is_maker,
         };
 
+        if let Some(rec) = &recorder {
+            let source = if price_fetched { TickSource::CoinCap } else { TickSource::Synthetic };
+            rec.record(source, &trade);
+        }
         if tx.send(trade).await.is_err() { break; }
 
         // Wait 1 second
         sleep(Duration::from_secs(1)).await;
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Polls the top of the L2 book once a second for `SLIPPAGE_MODEL=book`. Reads `DEPTH_URL`
+/// (Binance spot BTCUSDT, 20 levels). A failed poll publishes no book, so fills fall back to
+/// the fixed cost instead of walking a stale one.
+pub async fn start_depth_stream(tx: watch::Sender<Option<OrderBook>>) {
+    let client = Client::new();
+    let url = env_or("DEPTH_URL", "https://api.binance.com/api/v3/depth?symbol=BTCUSDT&limit=20".to_string());
+    info!("📚 L2 DEPTH: {}", url);
+    let mut live = true;
+
+    loop {
+        let book = match client.get(&url).timeout(Duration::from_secs(2)).send().await {
+            Ok(response) => response.json::<Value>().await.ok().and_then(|json| parse_depth(&json)),
+            Err(_) => None,
+        };
+        if book.is_some() != live {
+            live = book.is_some();
+            if live { info!("📚 L2 DEPTH LIVE"); } else { warn!("⚠️ L2 DEPTH UNAVAILABLE: fills use SLIPPAGE_BPS"); }
+        }
+        if tx.send(book).is_err() { break; }
+        sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Reads a Binance depth snapshot (`bids`/`asks` as `[price, qty]` strings, best first).
+fn parse_depth(json: &Value) -> Option<OrderBook> {
+    let side = |key: &str| -> Option<Vec<(f64, f64)>> {
+        json.get(key)?.as_array()?.iter()
+            .map(|level| Some((level.get(0)?.as_str()?.parse().ok()?, level.get(1)?.as_str()?.parse().ok()?)))
+            .collect()
+    };
+    let book = OrderBook { bids: side("bids")?, asks: side("asks")? };
+    (!book.bids.is_empty() && !book.asks.is_empty()).then_some(book)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_binance_depth_snapshot() {
+        let json: Value = serde_json::from_str(r#"{"lastUpdateId":1027024,
+            "bids":[["4.00000000","431.00000000"],["3.90000000","12.00000000"]],
+            "asks":[["4.00000200","12.00000000"]]}"#).unwrap();
+        let book = parse_depth(&json).unwrap();
+        assert_eq!(book.bids, vec![(4.0, 431.0), (3.9, 12.0)]);
+        assert_eq!(book.asks, vec![(4.000002, 12.0)]);
+        assert!(parse_depth(&serde_json::json!({"code": -1121, "msg": "Invalid symbol."})).is_none());
+    }
+}
@@ -0,0 +1,10 @@
+use std::env;
+use std::str::FromStr;
+
+/// Reads an env var and parses it, falling back to `default` when unset or malformed.
+pub fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse::<T>().ok())
+        .unwrap_or(default)
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use crate::fills::Liquidity;
+use crate::simulator::PaperWallet;
+
+const CLIENT_ID_PREFIX: &str = "qe-";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    /// UP buys, DOWN sells.
+    pub fn from_direction(direction: &str) -> Self {
+        if direction == "UP" { Side::Buy } else { Side::Sell }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Side::Buy => "BUY",
+            Side::Sell => "SELL",
+        }
+    }
+}
+
+/// What the engine wants executed. The paper wallet sizes from `stake_pct`, exchanges use `quantity`.
+#[derive(Debug, Clone)]
+pub struct OrderRequest {
+    pub trade_id: u64,
+    pub symbol: String,
+    pub direction: String,   // UP / DOWN
+    pub price: f64,          // Tick price at decision time
+    pub stake_pct: f64,
+    pub notional: f64,       // Stake in USDT
+    pub close_quantity: f64, // BTC of an opposite position to close first (0 when flat)
+    pub quantity: f64,       // BTC to trade, lot-rounded by the pre-trade checks
+}
+
+impl OrderRequest {
+    /// Client order id sent to the venue, so fills can be matched back to the trade.
+    pub fn client_order_id(&self) -> String {
+        format!("{}{}", CLIENT_ID_PREFIX, self.trade_id)
+    }
+
+    pub fn side(&self) -> Side {
+        Side::from_direction(&self.direction)
+    }
+}
+
+/// Trade id encoded in a client order id, if the order is ours.
+pub fn trade_id_of(client_order_id: &str) -> Option<u64> {
+    client_order_id.strip_prefix(CLIENT_ID_PREFIX)?.parse().ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderState {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+    Expired,
+}
+
+impl OrderState {
+    /// Parses a Binance order status.
+    pub fn parse(status: &str) -> Self {
+        match status {
+            "PARTIALLY_FILLED" => OrderState::PartiallyFilled,
+            "FILLED" => OrderState::Filled,
+            "CANCELED" | "PENDING_CANCEL" => OrderState::Canceled,
+            "REJECTED" => OrderState::Rejected,
+            "EXPIRED" | "EXPIRED_IN_MATCH" => OrderState::Expired,
+            _ => OrderState::New,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OrderState::New => "NEW",
+            OrderState::PartiallyFilled => "PARTIALLY FILLED",
+            OrderState::Filled => "FILLED",
+            OrderState::Canceled => "CANCELED",
+            OrderState::Rejected => "REJECTED",
+            OrderState::Expired => "EXPIRED",
+        }
+    }
+}
+
+/// Immediate answer to a place or cancel request.
+#[derive(Debug, Clone)]
+pub struct OrderAck {
+    pub client_order_id: String,
+    pub exchange_order_id: Option<i64>,
+    pub state: OrderState,
+    pub filled_qty: f64,
+    pub avg_price: f64,
+}
+
+/// Order update from the venue: a fill, a cancel or a reject.
+#[derive(Debug, Clone)]
+pub struct ExecutionReport {
+    pub trade_id: Option<u64>, // None for orders placed outside the bot
+    pub client_order_id: String,
+    pub exchange_order_id: Option<i64>,
+    pub side: Side,
+    pub state: OrderState,
+    pub last_qty: f64,  // This fill, 0 for non-fill updates
+    pub last_price: f64,
+    pub cum_qty: f64,   // Filled so far
+    pub avg_price: f64,
+    pub fee: f64,
+    pub fee_asset: String,
+    pub liquidity: Option<Liquidity>,
+    pub reason: Option<String>, // Reject reason, when the venue gives one
+    pub time: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub enum ExecError {
+    RateLimited(Duration),
+    Transient(String),                   // Not sent, safe to retry
+    Unknown(String),                     // Sent, outcome unknown until a status query
+    Rejected { code: i64, msg: String }, // Venue said no
+    NotFound,
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::RateLimited(wait) => write!(f, "rate limited for {:.1}s", wait.as_secs_f64()),
+            ExecError::Transient(e) => write!(f, "transient: {}", e),
+            ExecError::Unknown(e) => write!(f, "outcome unknown: {}", e),
+            ExecError::Rejected { code, msg } => write!(f, "rejected ({}): {}", code, msg),
+            ExecError::NotFound => write!(f, "order not found"),
+        }
+    }
+}
+
+/// Somewhere orders can be sent: the paper wallet or a real exchange.
+pub trait Executor: Send {
+    fn name(&self) -> &'static str;
+
+    fn submit(&mut self, order: &OrderRequest) -> impl Future<Output = Result<OrderAck, ExecError>> + Send;
+
+    fn cancel(&mut self, client_order_id: &str) -> impl Future<Output = Result<OrderAck, ExecError>> + Send;
+
+    fn status(&mut self, client_order_id: &str) -> impl Future<Output = Result<OrderAck, ExecError>> + Send;
+
+    /// Execution reports received since the last call.
+    fn drain_reports(&mut self) -> Vec<ExecutionReport>;
+
+    /// True once after the update stream (re)connects, when reports may have been missed.
+    fn take_reconnected(&mut self) -> bool {
+        false
+    }
+}
+
+/// The paper wallet fills against ticks. Its fills are read with `drain_fills`,
+/// so no reports come out of here.
+impl Executor for PaperWallet {
+    fn name(&self) -> &'static str {
+        "paper"
+    }
+
+    async fn submit(&mut self, order: &OrderRequest) -> Result<OrderAck, ExecError> {
+        let (fills, pending) = (self.fills.len(), self.pending_orders.len());
+        self.open_trade(order.trade_id, order.direction.clone(), order.price, order.stake_pct);
+
+        let (state, filled_qty, avg_price) = if let Some(fill) = self.fills.get(fills) {
+            (OrderState::Filled, fill.quantity, fill.price)
+        } else if self.pending_orders.len() > pending {
+            (OrderState::New, 0.0, 0.0) // Waiting out the simulated latency
+        } else {
+            return Err(ExecError::Rejected { code: 0, msg: "nothing to sell".to_string() });
+        };
+        Ok(OrderAck { client_order_id: order.client_order_id(), exchange_order_id: None, state, filled_qty, avg_price })
+    }
+
+    async fn cancel(&mut self, client_order_id: &str) -> Result<OrderAck, ExecError> {
+        let trade_id = trade_id_of(client_order_id).ok_or(ExecError::NotFound)?;
+        let idx = self.pending_orders.iter().position(|o| o.trade_id == trade_id).ok_or(ExecError::NotFound)?;
+        self.pending_orders.remove(idx);
+        Ok(OrderAck { client_order_id: client_order_id.to_string(), exchange_order_id: None, state: OrderState::Canceled, filled_qty: 0.0, avg_price: 0.0 })
+    }
+
+    /// Paper orders are either waiting on latency or already filled.
+    async fn status(&mut self, client_order_id: &str) -> Result<OrderAck, ExecError> {
+        let trade_id = trade_id_of(client_order_id).ok_or(ExecError::NotFound)?;
+        let state = if self.pending_orders.iter().any(|o| o.trade_id == trade_id) { OrderState::New } else { OrderState::Filled };
+        Ok(OrderAck { client_order_id: client_order_id.to_string(), exchange_order_id: None, state, filled_qty: 0.0, avg_price: 0.0 })
+    }
+
+    fn drain_reports(&mut self) -> Vec<ExecutionReport> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,354 @@
+use std::fmt;
+use chrono::NaiveDate;
+use log::warn;
+use ta::indicators::{
+    AverageTrueRange, BollingerBands, ExponentialMovingAverage, MovingAverageConvergenceDivergence,
+    RelativeStrengthIndex,
+};
+use ta::Next;
+use crate::bars::{Bar, BarKind};
+use crate::config::env_or;
+use crate::model::TradeData;
+
+/// Indicator periods, the bars they run on and the confirmation filters.
+#[derive(Debug, Clone)]
+pub struct FeatureConfig {
+    pub bar: BarKind,
+    pub ema_fast: usize,
+    pub ema_slow: usize,
+    pub macd_signal: usize,
+    pub rsi_period: usize,
+    pub rsi_overbought: f64,
+    pub rsi_oversold: f64,
+    pub bb_period: usize,
+    pub bb_stddev: f64,
+    pub atr_period: usize,
+    pub filters: Vec<Filter>,
+}
+
+impl FeatureConfig {
+    /// Reads `FEATURE_BAR` (1m, any `BARS` kind), `EMA_FAST` (12), `EMA_SLOW` (26), `MACD_SIGNAL` (9),
+    /// `RSI_PERIOD` (14), `RSI_OVERBOUGHT` (70), `RSI_OVERSOLD` (30), `BB_PERIOD` (20),
+    /// `BB_STDDEV` (2.0), `ATR_PERIOD` (14) and `FEATURE_FILTERS` (comma-separated, default none).
+    pub fn from_env() -> Self {
+        Self {
+            bar: BarKind::parse(&env_or("FEATURE_BAR", "1m".to_string())).unwrap_or(BarKind::Time(60)),
+            ema_fast: env_or("EMA_FAST", 12usize).max(1),
+            ema_slow: env_or("EMA_SLOW", 26usize).max(1),
+            macd_signal: env_or("MACD_SIGNAL", 9usize).max(1),
+            rsi_period: env_or("RSI_PERIOD", 14usize).max(1),
+            rsi_overbought: env_or("RSI_OVERBOUGHT", 70.0),
+            rsi_oversold: env_or("RSI_OVERSOLD", 30.0),
+            bb_period: env_or("BB_PERIOD", 20usize).max(1),
+            bb_stddev: env_or("BB_STDDEV", 2.0),
+            atr_period: env_or("ATR_PERIOD", 14usize).max(1),
+            filters: env_or("FEATURE_FILTERS", String::new())
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .filter_map(|name| Filter::parse(name).or_else(|| {
+                    warn!("⚠️ UNKNOWN FEATURE FILTER {:?} ignored", name);
+                    None
+                }))
+                .collect(),
+        }
+    }
+}
+
+/// An indicator check a signal has to pass on top of the OFI whale confirmation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Ema,       // Fast EMA on the signal's side of the slow EMA
+    Rsi,       // No buying overbought, no selling oversold
+    Macd,      // Histogram agrees with the direction
+    Bollinger, // Price still inside the band it would run into
+    Vwap,      // Price on the signal's side of VWAP
+}
+
+impl Filter {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "ema" => Some(Filter::Ema),
+            "rsi" => Some(Filter::Rsi),
+            "macd" => Some(Filter::Macd),
+            "bb" | "bollinger" => Some(Filter::Bollinger),
+            "vwap" => Some(Filter::Vwap),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Filter::Ema => "ema",
+            Filter::Rsi => "rsi",
+            Filter::Macd => "macd",
+            Filter::Bollinger => "bb",
+            Filter::Vwap => "vwap",
+        }
+    }
+}
+
+/// Indicator values as of the last closed bar (VWAP as of the last tick).
+/// A value stays None until its indicator has seen a full period.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FeatureSnapshot {
+    pub ema_fast: Option<f64>,
+    pub ema_slow: Option<f64>,
+    pub rsi: Option<f64>,
+    pub macd: Option<f64>,
+    pub macd_signal: Option<f64>,
+    pub macd_hist: Option<f64>,
+    pub bb_upper: Option<f64>,
+    pub bb_middle: Option<f64>,
+    pub bb_lower: Option<f64>,
+    pub atr: Option<f64>,
+    pub vwap: Option<f64>,
+}
+
+impl fmt::Display for FeatureSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = |x: Option<f64>| x.map_or("-".to_string(), |x| format!("{:.2}", x));
+        write!(f, "EMA {}/{} | RSI {} | MACD {}/{}/{} | BB {}/{}/{} | ATR {} | VWAP {}",
+            v(self.ema_fast), v(self.ema_slow), v(self.rsi), v(self.macd), v(self.macd_signal), v(self.macd_hist),
+            v(self.bb_lower), v(self.bb_middle), v(self.bb_upper), v(self.atr), v(self.vwap))
+    }
+}
+
+/// Keeps EMA, RSI, MACD, Bollinger Bands and ATR on closed bars of the configured kind,
+/// plus a session VWAP from the ticks that resets at 00:00 UTC.
+pub struct FeaturePipeline {
+    pub config: FeatureConfig,
+    ema_fast: ExponentialMovingAverage,
+    ema_slow: ExponentialMovingAverage,
+    rsi: RelativeStrengthIndex,
+    macd: MovingAverageConvergenceDivergence,
+    bb: BollingerBands,
+    atr: AverageTrueRange,
+    bars: usize, // Closed bars fed to the indicators
+    vwap_day: Option<NaiveDate>,
+    vwap_pv: f64,
+    vwap_volume: f64,
+    latest: FeatureSnapshot,
+}
+
+impl FeaturePipeline {
+    pub fn new(config: FeatureConfig) -> Self {
+        // Periods are clamped to at least 1 above, which is all ta rejects
+        Self {
+            ema_fast: ExponentialMovingAverage::new(config.ema_fast).unwrap(),
+            ema_slow: ExponentialMovingAverage::new(config.ema_slow).unwrap(),
+            rsi: RelativeStrengthIndex::new(config.rsi_period).unwrap(),
+            macd: MovingAverageConvergenceDivergence::new(config.ema_fast, config.ema_slow, config.macd_signal).unwrap(),
+            bb: BollingerBands::new(config.bb_period, config.bb_stddev).unwrap(),
+            atr: AverageTrueRange::new(config.atr_period).unwrap(),
+            bars: 0,
+            vwap_day: None,
+            vwap_pv: 0.0,
+            vwap_volume: 0.0,
+            latest: FeatureSnapshot::default(),
+            config,
+        }
+    }
+
+    pub fn update(&mut self, tick: &TradeData) {
+        self.update_vwap(tick);
+    }
+
+    /// Feeds a closed bar; bars of other kinds are ignored.
+    pub fn on_bar(&mut self, bar: &Bar) {
+        if bar.kind == self.config.bar {
+            self.close_bar(bar);
+        }
+    }
+
+    pub fn snapshot(&self) -> FeatureSnapshot {
+        self.latest
+    }
+
+    /// Checks every configured filter. A filter whose indicator is still warming up passes.
+    pub fn confirms(&self, direction: &str, price: f64) -> bool {
+        self.config.filters.iter().all(|&f| self.passes(f, direction, price))
+    }
+
+    fn passes(&self, filter: Filter, direction: &str, price: f64) -> bool {
+        let s = &self.latest;
+        let up = match direction {
+            "UP" => true,
+            "DOWN" => false,
+            _ => return false,
+        };
+        match filter {
+            Filter::Ema => match (s.ema_fast, s.ema_slow) {
+                (Some(fast), Some(slow)) => if up { fast > slow } else { fast < slow },
+                _ => true,
+            },
+            Filter::Rsi => s.rsi.is_none_or(|rsi| if up { rsi < self.config.rsi_overbought } else { rsi > self.config.rsi_oversold }),
+            Filter::Macd => s.macd_hist.is_none_or(|h| if up { h > 0.0 } else { h < 0.0 }),
+            Filter::Bollinger => if up { s.bb_upper.is_none_or(|u| price < u) } else { s.bb_lower.is_none_or(|l| price > l) },
+            Filter::Vwap => s.vwap.is_none_or(|v| if up { price > v } else { price < v }),
+        }
+    }
+
+    fn close_bar(&mut self, bar: &Bar) {
+        self.bars += 1;
+        let cfg = &self.config;
+        let ready = |period: usize| self.bars >= period;
+
+        let ema_fast = self.ema_fast.next(bar);
+        let ema_slow = self.ema_slow.next(bar);
+        let rsi = self.rsi.next(bar);
+        let macd = self.macd.next(bar);
+        let bb = self.bb.next(bar);
+        let atr = self.atr.next(bar);
+
+        let macd_ready = ready(cfg.ema_slow + cfg.macd_signal);
+        self.latest = FeatureSnapshot {
+            ema_fast: ready(cfg.ema_fast).then_some(ema_fast),
+            ema_slow: ready(cfg.ema_slow).then_some(ema_slow),
+            rsi: ready(cfg.rsi_period + 1).then_some(rsi),
+            macd: macd_ready.then_some(macd.macd),
+            macd_signal: macd_ready.then_some(macd.signal),
+            macd_hist: macd_ready.then_some(macd.histogram),
+            bb_upper: ready(cfg.bb_period).then_some(bb.upper),
+            bb_middle: ready(cfg.bb_period).then_some(bb.average),
+            bb_lower: ready(cfg.bb_period).then_some(bb.lower),
+            atr: ready(cfg.atr_period).then_some(atr),
+            vwap: self.latest.vwap,
+        };
+    }
+
+    fn update_vwap(&mut self, tick: &TradeData) {
+        let day = tick.timestamp.date_naive();
+        if self.vwap_day != Some(day) {
+            self.vwap_day = Some(day);
+            self.vwap_pv = 0.0;
+            self.vwap_volume = 0.0;
+        }
+        self.vwap_pv += tick.price * tick.quantity;
+        self.vwap_volume += tick.quantity;
+        if self.vwap_volume > 0.0 {
+            self.latest.vwap = Some(self.vwap_pv / self.vwap_volume);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration, Utc};
+
+    fn config(filters: Vec<Filter>) -> FeatureConfig {
+        FeatureConfig {
+            bar: BarKind::Time(60),
+            ema_fast: 2,
+            ema_slow: 3,
+            macd_signal: 2,
+            rsi_period: 2,
+            rsi_overbought: 70.0,
+            rsi_oversold: 30.0,
+            bb_period: 3,
+            bb_stddev: 2.0,
+            atr_period: 2,
+            filters,
+        }
+    }
+
+    fn bar(i: i64, close: f64) -> Bar {
+        let open_time = "2026-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::minutes(i);
+        Bar {
+            kind: BarKind::Time(60),
+            open_time,
+            close_time: open_time + Duration::minutes(1),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1.0,
+            buy_volume: 1.0,
+            sell_volume: 0.0,
+            dollar_volume: close,
+            trades: 1,
+        }
+    }
+
+    fn tick(at: &str, price: f64, quantity: f64) -> TradeData {
+        TradeData { timestamp: at.parse().unwrap(), price, quantity, is_buyer_maker: false }
+    }
+
+    #[test]
+    fn ema_and_rsi_warm_up_then_match_a_hand_computed_series() {
+        let mut features = FeaturePipeline::new(config(Vec::new()));
+        features.on_bar(&bar(0, 10.0));
+        let s = features.snapshot();
+        assert!(s.ema_fast.is_none() && s.ema_slow.is_none() && s.rsi.is_none());
+
+        features.on_bar(&bar(1, 11.0));
+        let s = features.snapshot();
+        // EMA(2): k = 2/3, seeded with the first close
+        assert!((s.ema_fast.unwrap() - 32.0 / 3.0).abs() < 1e-9);
+        assert!(s.ema_slow.is_none() && s.rsi.is_none());
+
+        features.on_bar(&bar(2, 10.5));
+        let s = features.snapshot();
+        assert!((s.ema_fast.unwrap() - 95.0 / 9.0).abs() < 1e-9);
+        assert!((s.ema_slow.unwrap() - 10.5).abs() < 1e-9); // EMA(3): 10, 10.5, 10.5
+        // Gains and losses smoothed by EMA(2) from a 0.1/0.1 seed: 21/90 up, 31/90 down
+        assert!((s.rsi.unwrap() - 100.0 * 21.0 / 52.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bars_of_other_kinds_are_ignored() {
+        let mut features = FeaturePipeline::new(config(Vec::new()));
+        let mut other = bar(0, 10.0);
+        other.kind = BarKind::Time(1);
+        for _ in 0..5 { features.on_bar(&other); }
+        assert!(features.snapshot().ema_fast.is_none());
+    }
+
+    #[test]
+    fn vwap_weights_by_size_and_resets_each_utc_day() {
+        let mut features = FeaturePipeline::new(config(Vec::new()));
+        features.update(&tick("2026-01-01T23:59:00Z", 100.0, 1.0));
+        features.update(&tick("2026-01-01T23:59:30Z", 110.0, 3.0));
+        assert_eq!(features.snapshot().vwap, Some(107.5));
+        features.update(&tick("2026-01-02T00:00:01Z", 90.0, 1.0));
+        assert_eq!(features.snapshot().vwap, Some(90.0));
+    }
+
+    #[test]
+    fn each_filter_confirms_only_its_side() {
+        let snapshot = FeatureSnapshot {
+            ema_fast: Some(101.0),
+            ema_slow: Some(100.0),
+            rsi: Some(75.0),
+            macd_hist: Some(0.5),
+            bb_upper: Some(110.0),
+            bb_lower: Some(90.0),
+            vwap: Some(100.0),
+            ..FeatureSnapshot::default()
+        };
+        // (filter, price, UP passes, DOWN passes)
+        let cases = [
+            (Filter::Ema, 105.0, true, false),
+            (Filter::Rsi, 105.0, false, true), // Overbought: no buying
+            (Filter::Macd, 105.0, true, false),
+            (Filter::Bollinger, 111.0, false, true), // Above the upper band
+            (Filter::Bollinger, 89.0, true, false),  // Below the lower band
+            (Filter::Vwap, 105.0, true, false),
+        ];
+        for (filter, price, up, down) in cases {
+            let mut features = FeaturePipeline::new(config(vec![filter]));
+            features.latest = snapshot;
+            assert_eq!(features.confirms("UP", price), up, "{} UP at {}", filter.label(), price);
+            assert_eq!(features.confirms("DOWN", price), down, "{} DOWN at {}", filter.label(), price);
+            assert!(!features.confirms("FLAT", price));
+
+            features.latest = FeatureSnapshot::default(); // Still warming up
+            assert!(features.confirms("UP", price) && features.confirms("DOWN", price));
+        }
+
+        let mut oversold = FeaturePipeline::new(config(vec![Filter::Rsi]));
+        oversold.latest.rsi = Some(25.0);
+        assert!(oversold.confirms("UP", 100.0) && !oversold.confirms("DOWN", 100.0));
+    }
+}
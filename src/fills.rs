@@ -0,0 +1,220 @@
+use std::time::Duration;
+use log::warn;
+use crate::config::env_or;
+
+/// Which side of the book an order took.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Liquidity {
+    Maker, // Resting limit (take-profit)
+    Taker, // Market order (entries, stops, time exits)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FeeSchedule {
+    pub maker_rate: f64,
+    pub taker_rate: f64,
+}
+
+impl FeeSchedule {
+    pub fn rate(&self, liquidity: Liquidity) -> f64 {
+        match liquidity {
+            Liquidity::Maker => self.maker_rate,
+            Liquidity::Taker => self.taker_rate,
+        }
+    }
+}
+
+/// Extra cost a market order pays beyond half the spread.
+#[derive(Debug, Clone, Copy)]
+pub enum SlippageModel {
+    FixedBps(f64),
+    /// Square-root impact: base + impact * sqrt(order qty / recent avg trade qty)
+    VolumeDependent { base_bps: f64, impact_bps: f64 },
+    /// Walks L2 depth when a book is available, otherwise falls back to a fixed cost
+    OrderBookWalk { fallback_bps: f64 },
+}
+
+/// L2 snapshot as (price, quantity) levels, best level first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBook {
+    pub bids: Vec<(f64, f64)>,
+    pub asks: Vec<(f64, f64)>,
+}
+
+impl OrderBook {
+    /// Midpoint of the best bid and ask, if both sides have a level.
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.bids.first()?.0 + self.asks.first()?.0) / 2.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fill {
+    pub price: f64,
+    pub quantity: f64,
+    pub fee: f64,
+    pub liquidity: Liquidity,
+}
+
+pub struct FillModel {
+    pub fees: FeeSchedule,
+    pub half_spread_bps: f64,
+    pub slippage: SlippageModel,
+    pub latency: Duration, // Decision-to-fill delay
+}
+
+impl FillModel {
+    /// Reads `MAKER_FEE_RATE`, `TAKER_FEE_RATE`, `SPREAD_BPS`, `SLIPPAGE_MODEL` (fixed | volume | book),
+    /// `SLIPPAGE_BPS`, `IMPACT_BPS` and `FILL_LATENCY_MS` (0, fills on the deciding tick). The older
+    /// single `FEE_RATE` still works as the default for both sides.
+    pub fn from_env(default_maker: f64, default_taker: f64) -> Self {
+        let slippage_bps = env_or("SLIPPAGE_BPS", 1.0);
+        let slippage = match std::env::var("SLIPPAGE_MODEL").unwrap_or_default().to_lowercase().as_str() {
+            "volume" => SlippageModel::VolumeDependent {
+                base_bps: slippage_bps,
+                impact_bps: env_or("IMPACT_BPS", 5.0),
+            },
+            "book" => SlippageModel::OrderBookWalk { fallback_bps: slippage_bps },
+            _ => SlippageModel::FixedBps(slippage_bps),
+        };
+
+        let legacy_fee = std::env::var("FEE_RATE").ok().and_then(|v| v.parse::<f64>().ok());
+        if legacy_fee.is_some() {
+            warn!("⚠️ FEE_RATE is deprecated, use MAKER_FEE_RATE and TAKER_FEE_RATE (applied to both for now)");
+        }
+
+        Self {
+            fees: FeeSchedule {
+                maker_rate: env_or("MAKER_FEE_RATE", legacy_fee.unwrap_or(default_maker)),
+                taker_rate: env_or("TAKER_FEE_RATE", legacy_fee.unwrap_or(default_taker)),
+            },
+            half_spread_bps: env_or("SPREAD_BPS", 1.0) / 2.0,
+            slippage,
+            latency: Duration::from_millis(env_or("FILL_LATENCY_MS", 0)),
+        }
+    }
+
+    /// Fills a market order. `side` is +1 for a buy, -1 for a sell; `reference` is the last trade price.
+    /// `book` is the latest L2 snapshot, if the feed has one.
+    pub fn market(&self, side: f64, quantity: f64, reference: f64, avg_trade_qty: f64, book: Option<&OrderBook>) -> Fill {
+        let price = match self.slippage {
+            SlippageModel::FixedBps(bps) => apply_bps(reference, side, self.half_spread_bps + bps),
+            SlippageModel::VolumeDependent { base_bps, impact_bps } => {
+                let participation = if avg_trade_qty > 0.0 { quantity / avg_trade_qty } else { 1.0 };
+                apply_bps(reference, side, self.half_spread_bps + base_bps + impact_bps * participation.sqrt())
+            }
+            SlippageModel::OrderBookWalk { fallback_bps } => match book {
+                Some(book) => walk_book(book, side, quantity, reference, fallback_bps),
+                None => apply_bps(reference, side, self.half_spread_bps + fallback_bps),
+            },
+        };
+
+        Fill {
+            price,
+            quantity,
+            fee: price * quantity * self.fees.rate(Liquidity::Taker),
+            liquidity: Liquidity::Taker,
+        }
+    }
+
+    /// Fills a resting limit order exactly at its price, paying the maker fee.
+    pub fn limit(&self, quantity: f64, price: f64) -> Fill {
+        Fill {
+            price,
+            quantity,
+            fee: price * quantity * self.fees.rate(Liquidity::Maker),
+            liquidity: Liquidity::Maker,
+        }
+    }
+}
+
+fn apply_bps(price: f64, side: f64, bps: f64) -> f64 {
+    price * (1.0 + side * bps / 10_000.0)
+}
+
+/// Volume-weighted price of sweeping the opposite side of the book.
+/// Anything beyond the visible depth is priced `fallback_bps` past the last level.
+fn walk_book(book: &OrderBook, side: f64, quantity: f64, reference: f64, fallback_bps: f64) -> f64 {
+    if quantity <= 0.0 { return reference; }
+    let levels = if side > 0.0 { &book.asks } else { &book.bids };
+    let mut remaining = quantity;
+    let mut cost = 0.0;
+    let mut last = reference;
+
+    for &(price, size) in levels {
+        let take = remaining.min(size);
+        cost += take * price;
+        remaining -= take;
+        last = price;
+        if remaining <= 0.0 { break; }
+    }
+
+    if remaining > 0.0 {
+        cost += remaining * apply_bps(last, side, fallback_bps);
+    }
+
+    cost / quantity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(slippage: SlippageModel) -> FillModel {
+        FillModel {
+            fees: FeeSchedule { maker_rate: 0.0002, taker_rate: 0.0004 },
+            half_spread_bps: 1.0,
+            slippage,
+            latency: Duration::ZERO,
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn fixed_slippage_pays_half_spread_plus_bps_and_the_taker_fee() {
+        let m = model(SlippageModel::FixedBps(2.0));
+        let buy = m.market(1.0, 0.5, 10_000.0, 0.0, None);
+        assert!(close(buy.price, 10_003.0));
+        assert!(close(buy.fee, 10_003.0 * 0.5 * 0.0004));
+        assert_eq!((buy.quantity, buy.liquidity), (0.5, Liquidity::Taker));
+        assert!(close(m.market(-1.0, 0.5, 10_000.0, 0.0, None).price, 9_997.0));
+    }
+
+    #[test]
+    fn volume_slippage_grows_with_the_square_root_of_participation() {
+        let m = model(SlippageModel::VolumeDependent { base_bps: 1.0, impact_bps: 5.0 });
+        // 4x the average trade: 1 + 1 + 5 * 2 = 12 bps
+        assert!(close(m.market(1.0, 0.4, 10_000.0, 0.1, None).price, 10_012.0));
+        // No volume history yet counts as participation 1: 7 bps
+        assert!(close(m.market(-1.0, 0.4, 10_000.0, 0.0, None).price, 9_993.0));
+    }
+
+    #[test]
+    fn book_walk_fills_level_by_level_and_prices_the_rest_past_the_depth() {
+        let m = model(SlippageModel::OrderBookWalk { fallback_bps: 10.0 });
+        let book = OrderBook { bids: vec![(99.0, 1.0), (98.0, 1.0)], asks: vec![(101.0, 1.0), (102.0, 1.0)] };
+
+        // Inside the best level
+        assert!(close(m.market(1.0, 0.5, 100.0, 0.0, Some(&book)).price, 101.0));
+        // Part of the order fills at the second level: (1 * 101 + 0.5 * 102) / 1.5
+        assert!(close(m.market(1.0, 1.5, 100.0, 0.0, Some(&book)).price, 152.0 / 1.5));
+        // Beyond the visible bids the last unit is priced 10 bps under 98
+        assert!(close(m.market(-1.0, 3.0, 100.0, 0.0, Some(&book)).price, (99.0 + 98.0 + 97.902) / 3.0));
+    }
+
+    #[test]
+    fn book_walk_without_a_book_uses_the_fixed_fallback() {
+        let m = model(SlippageModel::OrderBookWalk { fallback_bps: 2.0 });
+        assert!(close(m.market(1.0, 1.0, 10_000.0, 0.0, None).price, 10_003.0));
+    }
+
+    #[test]
+    fn limit_fills_at_its_price_as_maker() {
+        let fill = model(SlippageModel::FixedBps(2.0)).limit(0.5, 10_000.0);
+        assert_eq!((fill.price, fill.liquidity), (10_000.0, Liquidity::Maker));
+        assert!(close(fill.fee, 10_000.0 * 0.5 * 0.0002));
+    }
+}
@@ -0,0 +1,682 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use log::error;
+use serde::Serialize;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use crate::config::env_or;
+use crate::executor::ExecutionReport;
+use crate::fills::Liquidity;
+use crate::model::QuantumSignal;
+use crate::simulator::{ClosedTrade, FillRecord};
+
+const SYMBOL: &str = "BTCUSDT";
+
+const CSV_HEADER: &str = "ts_utc,event,trade_id,symbol,direction,price,reference_price,quantity,stake,stake_pct,fee,pnl,\
+confidence,prob_up,ofi,volatility,whale_confirmed,liquidity,reason,price_source,opened_utc,\
+feature_confirmed,ema_fast,ema_slow,rsi,macd_hist,bb_upper,bb_lower,atr,vwap";
+
+/// Lifecycle stage a journal row describes.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum JournalEvent {
+    Signal,
+    Order,
+    Rejected,
+    Fill,
+    Settlement,
+    Execution, // Order update from a live venue
+}
+
+impl JournalEvent {
+    fn label(&self) -> &'static str {
+        match self {
+            JournalEvent::Signal => "SIGNAL",
+            JournalEvent::Order => "ORDER",
+            JournalEvent::Rejected => "REJECTED",
+            JournalEvent::Fill => "FILL",
+            JournalEvent::Settlement => "SETTLEMENT",
+            JournalEvent::Execution => "EXECUTION",
+        }
+    }
+}
+
+/// One journal row. Fields that do not apply to an event stay empty.
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalRecord {
+    pub ts_utc: DateTime<Utc>,
+    pub event: JournalEvent,
+    pub trade_id: u64,
+    pub symbol: &'static str,
+    pub direction: String,
+    pub price: f64,
+    pub reference_price: Option<f64>,
+    pub quantity: Option<f64>,
+    pub stake: Option<f64>,
+    pub stake_pct: Option<f64>,
+    pub fee: Option<f64>,
+    pub pnl: Option<f64>,
+    pub confidence: Option<f64>,
+    pub prob_up: Option<f64>,
+    pub ofi: Option<f64>,
+    pub volatility: Option<f64>,
+    pub whale_confirmed: Option<bool>,
+    pub liquidity: Option<&'static str>,
+    pub reason: Option<String>,
+    pub price_source: Option<String>,
+    pub opened_utc: Option<DateTime<Utc>>,
+    pub feature_confirmed: Option<bool>,
+    pub ema_fast: Option<f64>,
+    pub ema_slow: Option<f64>,
+    pub rsi: Option<f64>,
+    pub macd_hist: Option<f64>,
+    pub bb_upper: Option<f64>,
+    pub bb_lower: Option<f64>,
+    pub atr: Option<f64>,
+    pub vwap: Option<f64>,
+}
+
+impl JournalRecord {
+    fn new(event: JournalEvent, trade_id: u64, ts_utc: DateTime<Utc>, direction: &str, price: f64) -> Self {
+        Self {
+            ts_utc,
+            event,
+            trade_id,
+            symbol: SYMBOL,
+            direction: direction.to_string(),
+            price,
+            reference_price: None,
+            quantity: None,
+            stake: None,
+            stake_pct: None,
+            fee: None,
+            pnl: None,
+            confidence: None,
+            prob_up: None,
+            ofi: None,
+            volatility: None,
+            whale_confirmed: None,
+            liquidity: None,
+            reason: None,
+            price_source: None,
+            opened_utc: None,
+            feature_confirmed: None,
+            ema_fast: None,
+            ema_slow: None,
+            rsi: None,
+            macd_hist: None,
+            bb_upper: None,
+            bb_lower: None,
+            atr: None,
+            vwap: None,
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let num = |v: Option<f64>| v.map(|x| format!("{:.8}", x)).unwrap_or_default();
+        let time = |t: DateTime<Utc>| t.to_rfc3339_opts(SecondsFormat::Millis, true);
+        [
+            time(self.ts_utc),
+            self.event.label().to_string(),
+            self.trade_id.to_string(),
+            self.symbol.to_string(),
+            csv_field(&self.direction),
+            format!("{:.8}", self.price),
+            num(self.reference_price),
+            num(self.quantity),
+            num(self.stake),
+            num(self.stake_pct),
+            num(self.fee),
+            num(self.pnl),
+            num(self.confidence),
+            num(self.prob_up),
+            num(self.ofi),
+            num(self.volatility),
+            self.whale_confirmed.map(|w| w.to_string()).unwrap_or_default(),
+            self.liquidity.unwrap_or_default().to_string(),
+            csv_field(self.reason.as_deref().unwrap_or_default()),
+            csv_field(self.price_source.as_deref().unwrap_or_default()),
+            self.opened_utc.map(time).unwrap_or_default(),
+            self.feature_confirmed.map(|c| c.to_string()).unwrap_or_default(),
+            num(self.ema_fast),
+            num(self.ema_slow),
+            num(self.rsi),
+            num(self.macd_hist),
+            num(self.bb_upper),
+            num(self.bb_lower),
+            num(self.atr),
+            num(self.vwap),
+        ]
+        .join(",")
+    }
+}
+
+fn liquidity_label(liquidity: Liquidity) -> &'static str {
+    match liquidity {
+        Liquidity::Maker => "MAKER",
+        Liquidity::Taker => "TAKER",
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// When the writer forces journal data to disk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FsyncPolicy {
+    Always,            // After every flush
+    EveryN(usize),     // After this many records
+    Interval(Duration),
+    Never,             // Leave it to the OS
+}
+
+impl FsyncPolicy {
+    /// Reads `JOURNAL_FSYNC` (always | never | every:<records> | interval:<secs>). Defaults to interval:5.
+    pub fn from_env() -> Self {
+        let raw = std::env::var("JOURNAL_FSYNC").unwrap_or_default().to_lowercase();
+        let (kind, arg) = raw.split_once(':').unwrap_or((raw.as_str(), ""));
+        match (kind, arg.parse::<u64>()) {
+            ("always", _) => FsyncPolicy::Always,
+            ("never", _) => FsyncPolicy::Never,
+            ("every", Ok(n)) if n > 0 => FsyncPolicy::EveryN(n as usize),
+            ("interval", Ok(secs)) => FsyncPolicy::Interval(Duration::from_secs(secs)),
+            _ => FsyncPolicy::Interval(Duration::from_secs(5)),
+        }
+    }
+}
+
+/// Problems the writer reports instead of silently losing records.
+#[derive(Debug, Clone)]
+pub enum JournalHealth {
+    Backpressure { capacity: usize }, // Queue full, the logic loop waited
+    WriteFailed { error: String, pending: usize }, // Records kept and retried on the next flush
+    Dropped { count: usize },         // Retry buffer overflowed; oldest records lost
+    Rotated { path: String },
+}
+
+/// Which files the journal writes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JournalFormat {
+    Csv,
+    JsonLines,
+    Both,
+}
+
+impl JournalFormat {
+    /// Reads `JOURNAL_FORMAT` (csv | jsonl | both). Defaults to both.
+    pub fn from_env() -> Self {
+        match std::env::var("JOURNAL_FORMAT").unwrap_or_default().to_lowercase().as_str() {
+            "csv" => JournalFormat::Csv,
+            "jsonl" | "json" => JournalFormat::JsonLines,
+            _ => JournalFormat::Both,
+        }
+    }
+}
+
+pub struct JournalConfig {
+    pub dir: String,
+    pub format: JournalFormat,
+    pub fsync: FsyncPolicy,
+    pub max_bytes: u64,      // Size rotation threshold per file
+    pub queue_capacity: usize,
+}
+
+impl JournalConfig {
+    /// Reads `JOURNAL_DIR`, `JOURNAL_FORMAT`, `JOURNAL_FSYNC`, `JOURNAL_MAX_MB` and `JOURNAL_QUEUE`.
+    pub fn from_env() -> Self {
+        Self {
+            dir: env_or("JOURNAL_DIR", "journal".to_string()),
+            format: JournalFormat::from_env(),
+            fsync: FsyncPolicy::from_env(),
+            max_bytes: env_or("JOURNAL_MAX_MB", 50u64) * 1024 * 1024,
+            queue_capacity: env_or("JOURNAL_QUEUE", 1024),
+        }
+    }
+}
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const FLUSH_BATCH: usize = 256;
+const MAX_RETRY_BUFFER: usize = 100_000;
+
+/// Trade lifecycle journal: signal -> order -> fill -> settlement, joined by trade id.
+/// Records go over a channel to a dedicated writer task; nothing touches disk on the logic loop.
+pub struct TradeJournal {
+    tx: mpsc::Sender<JournalRecord>,
+    health: mpsc::UnboundedSender<JournalHealth>,
+    capacity: usize,
+    next_id: u64,
+    writer: tokio::task::JoinHandle<()>,
+}
+
+impl TradeJournal {
+    /// Starts the writer task. Health events arrive on the returned receiver.
+    pub fn spawn(config: JournalConfig) -> (Self, mpsc::UnboundedReceiver<JournalHealth>) {
+        let (tx, rx) = mpsc::channel(config.queue_capacity);
+        let (health_tx, health_rx) = mpsc::unbounded_channel();
+        let capacity = config.queue_capacity;
+
+        let writer = JournalWriter::new(config, health_tx.clone());
+        let writer = tokio::spawn(writer.run(rx));
+
+        let journal = Self {
+            tx,
+            health: health_tx,
+            capacity,
+            // Millisecond seed keeps ids unique across restarts
+            next_id: Utc::now().timestamp_millis() as u64,
+            writer,
+        };
+        (journal, health_rx)
+    }
+
+    /// Closes the queue and waits for the writer's final flush and sync.
+    pub async fn close(self) {
+        let Self { tx, writer, .. } = self;
+        drop(tx);
+        if let Err(e) = writer.await {
+            error!("JOURNAL WRITER FAILED ON SHUTDOWN: {}", e);
+        }
+    }
+
+    pub fn next_trade_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// `ts` is the tick the signal was taken on, so replays and backtests journal in tick time.
+    pub async fn signal(&self, trade_id: u64, ts: DateTime<Utc>, signal: &QuantumSignal, price: f64, volatility: f64) {
+        let mut rec = JournalRecord::new(JournalEvent::Signal, trade_id, ts, &signal.direction, price);
+        rec.confidence = Some(signal.confidence);
+        rec.prob_up = Some(signal.prob_up);
+        rec.ofi = Some(signal.ofi);
+        rec.volatility = Some(volatility);
+        rec.whale_confirmed = Some(signal.is_whale_confirmed);
+        let f = &signal.features;
+        rec.feature_confirmed = Some(signal.is_feature_confirmed);
+        (rec.ema_fast, rec.ema_slow, rec.rsi, rec.macd_hist) = (f.ema_fast, f.ema_slow, f.rsi, f.macd_hist);
+        (rec.bb_upper, rec.bb_lower, rec.atr, rec.vwap) = (f.bb_upper, f.bb_lower, f.atr, f.vwap);
+        self.write(rec).await;
+    }
+
+    pub async fn order(&self, trade_id: u64, ts: DateTime<Utc>, direction: &str, price: f64, stake_pct: f64, stake: f64) {
+        let mut rec = JournalRecord::new(JournalEvent::Order, trade_id, ts, direction, price);
+        rec.stake_pct = Some(stake_pct);
+        rec.stake = Some(stake);
+        self.write(rec).await;
+    }
+
+    pub async fn rejected(&self, trade_id: u64, ts: DateTime<Utc>, direction: &str, price: f64, reason: &str) {
+        let mut rec = JournalRecord::new(JournalEvent::Rejected, trade_id, ts, direction, price);
+        rec.reason = Some(reason.to_string());
+        self.write(rec).await;
+    }
+
+    pub async fn fill(&self, fill: &FillRecord) {
+        let mut rec = JournalRecord::new(JournalEvent::Fill, fill.trade_id, fill.time, &fill.side, fill.price);
+        rec.reference_price = Some(fill.reference_price);
+        rec.quantity = Some(fill.quantity);
+        rec.stake = Some(fill.stake);
+        rec.fee = Some(fill.fee);
+        rec.liquidity = Some(liquidity_label(fill.liquidity));
+        self.write(rec).await;
+    }
+
+    /// Live venue update. `price` is this fill's price, `reference_price` the running average.
+    pub async fn execution(&self, venue: &str, report: &ExecutionReport) {
+        let mut rec = JournalRecord::new(JournalEvent::Execution, report.trade_id.unwrap_or(0), report.time,
+            report.side.label(), report.last_price);
+        rec.reference_price = Some(report.avg_price);
+        rec.quantity = Some(report.last_qty);
+        rec.fee = Some(report.fee);
+        rec.liquidity = report.liquidity.map(liquidity_label);
+        rec.reason = Some(match &report.reason {
+            Some(r) => format!("{} {} {}: {}", venue, report.client_order_id, report.state.label(), r),
+            None => format!("{} {} {}", venue, report.client_order_id, report.state.label()),
+        });
+        self.write(rec).await;
+    }
+
+    pub async fn settlement(&self, trade: &ClosedTrade) {
+        let mut rec = JournalRecord::new(JournalEvent::Settlement, trade.trade_id, trade.close_time, &trade.direction, trade.exit_price);
+        rec.reference_price = Some(trade.entry_price);
+        rec.pnl = Some(trade.pnl);
+        rec.reason = Some(trade.reason.label().to_string());
+        rec.price_source = Some(trade.price_source.label());
+        rec.opened_utc = Some(trade.open_time);
+        self.write(rec).await;
+    }
+
+    /// Enqueues without blocking; when the queue is full, reports backpressure and waits.
+    async fn write(&self, rec: JournalRecord) {
+        let rec = match self.tx.try_send(rec) {
+            Ok(()) => return,
+            Err(TrySendError::Full(rec)) => {
+                let _ = self.health.send(JournalHealth::Backpressure { capacity: self.capacity });
+                rec
+            }
+            Err(TrySendError::Closed(_)) => {
+                error!("JOURNAL WRITER STOPPED: Record lost");
+                return;
+            }
+        };
+        if self.tx.send(rec).await.is_err() {
+            error!("JOURNAL WRITER STOPPED: Record lost");
+        }
+    }
+}
+
+/// An open, size-tracked journal file.
+struct Sink {
+    file: BufWriter<File>,
+    path: PathBuf,
+    bytes: u64,
+}
+
+impl Sink {
+    /// Closes the file without flushing what is still buffered, so a retry does not duplicate it.
+    fn discard(self) {
+        let _ = self.file.into_parts();
+    }
+}
+
+/// One output format and how far through the pending records its files are.
+/// CSV and JSONL are tracked apart so a failure in one never rewrites lines into the other.
+struct Track {
+    format: JournalFormat, // Csv or JsonLines
+    sink: Option<Sink>,
+    day: Option<NaiveDate>,
+    part: u32,   // Size-rotation index within the day
+    done: usize, // Leading pending records already flushed to this format
+}
+
+impl Track {
+    fn new(format: JournalFormat) -> Self {
+        Self { format, sink: None, day: None, part: 0, done: 0 }
+    }
+
+    fn ext(&self) -> &'static str {
+        if self.format == JournalFormat::Csv { "csv" } else { "jsonl" }
+    }
+
+    /// Writes the records this format has not flushed yet and flushes them. `done` only
+    /// advances past records that reached the file; on error the sink is discarded.
+    fn flush(&mut self, records: &[JournalRecord], config: &JournalConfig, health: &mpsc::UnboundedSender<JournalHealth>) -> std::io::Result<()> {
+        let result = self.write_from(records, config, health);
+        if result.is_err() {
+            // Reopen on the next attempt in case the handle went bad
+            if let Some(sink) = self.sink.take() { sink.discard(); }
+        }
+        result
+    }
+
+    fn write_from(&mut self, records: &[JournalRecord], config: &JournalConfig, health: &mpsc::UnboundedSender<JournalHealth>) -> std::io::Result<()> {
+        for (i, rec) in records.iter().enumerate().skip(self.done) {
+            if self.rotate_if_needed(rec.ts_utc.date_naive(), config, health)? {
+                self.done = i; // The previous file was flushed before the switch
+            }
+            let line = match self.format {
+                JournalFormat::Csv => rec.to_csv(),
+                _ => serde_json::to_string(rec)?,
+            } + "\n";
+            if let Some(sink) = self.sink.as_mut() {
+                sink.file.write_all(line.as_bytes())?;
+                sink.bytes += line.len() as u64;
+            }
+        }
+        if let Some(sink) = self.sink.as_mut() {
+            sink.file.flush()?;
+        }
+        self.done = records.len();
+        Ok(())
+    }
+
+    /// Starts a new file on a UTC day change or when the file passes `max_bytes`.
+    /// Returns true when it flushed and closed a file.
+    fn rotate_if_needed(&mut self, day: NaiveDate, config: &JournalConfig, health: &mpsc::UnboundedSender<JournalHealth>) -> std::io::Result<bool> {
+        let oversized = self.sink.as_ref().is_some_and(|s| s.bytes >= config.max_bytes);
+        let new_day = self.day != Some(day);
+        if !(oversized || new_day || self.sink.is_none()) { return Ok(false); }
+
+        if new_day {
+            self.day = Some(day);
+            self.part = 0;
+        } else if oversized {
+            self.part += 1;
+        }
+
+        // Flush what we are leaving behind before switching
+        let closed = match self.sink.as_mut() {
+            Some(sink) => {
+                sink.file.flush()?;
+                true
+            }
+            None => false,
+        };
+
+        fs::create_dir_all(&config.dir)?;
+        let stem = if self.part == 0 {
+            format!("trades-{}", day.format("%Y-%m-%d"))
+        } else {
+            format!("trades-{}.{}", day.format("%Y-%m-%d"), self.part)
+        };
+        let mut sink = open_sink(PathBuf::from(&config.dir).join(format!("{}.{}", stem, self.ext())))?;
+        if self.format == JournalFormat::Csv && sink.bytes == 0 {
+            sink.file.write_all(CSV_HEADER.as_bytes())?;
+            sink.file.write_all(b"\n")?;
+            sink.bytes += CSV_HEADER.len() as u64 + 1;
+        }
+        if closed {
+            let _ = health.send(JournalHealth::Rotated { path: sink.path.display().to_string() });
+        }
+        self.sink = Some(sink);
+        Ok(closed)
+    }
+}
+
+struct JournalWriter {
+    config: JournalConfig,
+    health: mpsc::UnboundedSender<JournalHealth>,
+    pending: Vec<JournalRecord>, // Kept until every format has flushed them
+    tracks: Vec<Track>,
+    unsynced: usize,
+    last_sync: Instant,
+}
+
+impl JournalWriter {
+    fn new(config: JournalConfig, health: mpsc::UnboundedSender<JournalHealth>) -> Self {
+        let tracks = match config.format {
+            JournalFormat::Csv => vec![Track::new(JournalFormat::Csv)],
+            JournalFormat::JsonLines => vec![Track::new(JournalFormat::JsonLines)],
+            JournalFormat::Both => vec![Track::new(JournalFormat::Csv), Track::new(JournalFormat::JsonLines)],
+        };
+        Self {
+            config,
+            health,
+            pending: Vec::new(),
+            tracks,
+            unsynced: 0,
+            last_sync: Instant::now(),
+        }
+    }
+
+    /// File I/O runs on the blocking pool, so the writer works on any runtime flavour.
+    async fn run(mut self, mut rx: mpsc::Receiver<JournalRecord>) {
+        let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            let last = tokio::select! {
+                rec = rx.recv() => match rec {
+                    Some(rec) => {
+                        self.pending.push(rec);
+                        if self.pending.len() < FLUSH_BATCH { continue; }
+                        false
+                    }
+                    // All senders gone: final flush and sync
+                    None => true,
+                },
+                _ = ticker.tick() => false,
+            };
+            let task = tokio::task::spawn_blocking(move || {
+                self.flush();
+                if last { self.sync(); }
+                self
+            });
+            self = match task.await {
+                Ok(writer) => writer,
+                Err(e) => {
+                    error!("JOURNAL WRITER FAILED: {}", e);
+                    return;
+                }
+            };
+            if last { return; }
+        }
+    }
+
+    /// Writes everything pending to each format. Records leave the queue only once every
+    /// format has flushed them; on failure they stay for the next attempt.
+    fn flush(&mut self) {
+        if self.pending.is_empty() { return; }
+
+        for track in &mut self.tracks {
+            if let Err(e) = track.flush(&self.pending, &self.config, &self.health) {
+                let _ = self.health.send(JournalHealth::WriteFailed {
+                    error: format!("{}: {}", track.ext(), e),
+                    pending: self.pending.len() - track.done,
+                });
+            }
+        }
+
+        let written = self.tracks.iter().map(|t| t.done).min().unwrap_or(0);
+        self.pending.drain(..written);
+        for track in &mut self.tracks { track.done -= written; }
+
+        if self.pending.len() > MAX_RETRY_BUFFER {
+            let excess = self.pending.len() - MAX_RETRY_BUFFER;
+            self.pending.drain(..excess);
+            for track in &mut self.tracks { track.done = track.done.saturating_sub(excess); }
+            let _ = self.health.send(JournalHealth::Dropped { count: excess });
+        }
+
+        self.unsynced += written;
+        let due = match self.config.fsync {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::EveryN(n) => self.unsynced >= n,
+            FsyncPolicy::Interval(every) => self.last_sync.elapsed() >= every,
+            FsyncPolicy::Never => false,
+        };
+        if due && self.unsynced > 0 {
+            self.sync();
+        }
+    }
+
+    fn sync(&mut self) {
+        for sink in self.tracks.iter().filter_map(|t| t.sink.as_ref()) {
+            if let Err(e) = sink.file.get_ref().sync_data() {
+                let _ = self.health.send(JournalHealth::WriteFailed { error: e.to_string(), pending: self.pending.len() });
+            }
+        }
+        self.unsynced = 0;
+        self.last_sync = Instant::now();
+    }
+}
+
+fn open_sink(path: PathBuf) -> std::io::Result<Sink> {
+    // Open file in Append mode
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let bytes = file.metadata()?.len();
+    Ok(Sink { file: BufWriter::new(file), path, bytes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn writer(name: &str) -> (JournalWriter, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("journal-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = JournalConfig {
+            dir: dir.display().to_string(),
+            format: JournalFormat::Both,
+            fsync: FsyncPolicy::Never,
+            max_bytes: u64::MAX,
+            queue_capacity: 16,
+        };
+        (JournalWriter::new(config, mpsc::unbounded_channel().0), dir)
+    }
+
+    fn record(trade_id: u64) -> JournalRecord {
+        let ts = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        JournalRecord::new(JournalEvent::Signal, trade_id, ts, "UP", 100.0)
+    }
+
+    fn lines(path: PathBuf) -> usize {
+        fs::read_to_string(path).unwrap().lines().count()
+    }
+
+    #[test]
+    fn failed_format_retries_without_duplicating_the_other() {
+        let (mut w, dir) = writer("split");
+        // A directory where the JSONL file should be makes only that format fail
+        let jsonl = dir.join("trades-2026-01-02.jsonl");
+        fs::create_dir_all(&jsonl).unwrap();
+
+        w.pending.extend([record(1), record(2)]);
+        w.flush();
+        assert_eq!(w.pending.len(), 2);
+        assert_eq!(lines(dir.join("trades-2026-01-02.csv")), 3); // Header + 2
+
+        fs::remove_dir(&jsonl).unwrap();
+        w.pending.push(record(3));
+        w.flush();
+        assert!(w.pending.is_empty());
+        assert_eq!(lines(dir.join("trades-2026-01-02.csv")), 4);
+        assert_eq!(lines(jsonl), 3);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn records_stay_queued_until_the_buffer_flushes() {
+        let (mut w, dir) = writer("full");
+        w.tracks.truncate(1);
+        let track = &mut w.tracks[0];
+        track.day = Some(record(1).ts_utc.date_naive());
+        // Writes land in the BufWriter; only the flush hits the full device
+        let file = OpenOptions::new().append(true).open("/dev/full").unwrap();
+        track.sink = Some(Sink { file: BufWriter::new(file), path: "/dev/full".into(), bytes: 0 });
+
+        w.pending.push(record(1));
+        w.flush();
+        assert_eq!(w.pending.len(), 1);
+        assert!(w.tracks[0].sink.is_none());
+
+        w.flush(); // Reopens the real file
+        assert!(w.pending.is_empty());
+        assert_eq!(lines(dir.join("trades-2026-01-02.csv")), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test] // Current-thread runtime
+    async fn writes_in_tick_time_on_a_single_threaded_runtime() {
+        let (w, dir) = writer("tick");
+        let (mut journal, _health) = TradeJournal::spawn(w.config);
+        let ts = record(1).ts_utc;
+        let id = journal.next_trade_id();
+        journal.order(id, ts, "UP", 100.0, 1.0, 10.0).await;
+        journal.rejected(id + 1, ts, "DOWN", 100.0, "paused").await;
+        journal.close().await;
+
+        // Named by the tick's day, not the wall clock's
+        let csv = fs::read_to_string(dir.join("trades-2026-01-02.csv")).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.lines().skip(1).all(|l| l.contains("2026-01-02T03:04:05")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -3,104 +3,537 @@ use log::{info, error, warn};
 use std::env;
 use dotenv::dotenv;
 use std::time::{Duration, Instant};
+use chrono::Utc;
+use persistence::{WalletEvent, WalletStore};
+use notify::EventKind;
+use executor::Executor;
 
 // --- IMPORTS ---
 mod client;
 mod model;
 mod telegram;
 mod news_filter;
-mod logger;
+mod journal;
 mod simulator; // <--- CRITICAL: This imports the simulator file
+mod config;
+mod fills;
+mod persistence;
+mod performance;
+mod risk;
+mod storage;
+mod recorder;
+mod notify;
+mod message;
+mod notifiers;
+mod reports;
+mod executor;
+mod binance;
+mod oms;
+mod pretrade;
+mod strategy;
+mod features;
+mod bars;
+mod whale;
+mod backtest;
+mod optimizer;
+mod calibration;
+#[cfg(test)]
+mod mock_http;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenv().ok();
-    if env::var("RUST_LOG").is_err() { env::set_var("RUST_LOG", "info"); }
+    // OPTIMIZE_FROM=<recording|sqlite> runs the walk-forward optimiser instead of the bot
+    let optimize = optimizer::OptimizerConfig::from_env();
+    if env::var("RUST_LOG").is_err() {
+        // Thousands of backtests would drown the report in per-trade wallet logs
+        env::set_var("RUST_LOG", if optimize.is_some() { "info,algo_trading_bot::simulator=warn" } else { "info" });
+    }
     env_logger::init();
+    if let Some(cfg) = optimize {
+        return optimizer::run(cfg);
+    }
     
-    let token = env::var("TELEGRAM_TOKEN").expect("TELEGRAM_TOKEN not set");
-    let chat_id = env::var("CHAT_ID").expect("CHAT_ID not set");
+    // Telegram is optional: without it, alerts go to the other configured backends
+    let token = config::env_or("TELEGRAM_TOKEN", String::new());
+    let chat_id = config::env_or("CHAT_ID", String::new());
+    let telegram = match (token.is_empty(), chat_id.is_empty()) {
+        (false, false) => Some(telegram::TelegramBot::new(token, chat_id)),
+        _ => {
+            warn!("⚠️ TELEGRAM_TOKEN/CHAT_ID not set: Telegram alerts and commands disabled");
+            None
+        }
+    };
     
     info!("🚀 QUANTUM ENGINE v3.0 (SCOREBOARD ACTIVE)");
     // Add this near the top of main(), around line 26:
     
     
     let (tx_data, mut rx_data) = mpsc::channel::<model::TradeData>(100);
-    let bot = telegram::TelegramBot::new(token, chat_id);
-    bot.send_signal("🚀 SYSTEM ONLINE: Connected to Binance Global. Scanning for Whales...").await;
-    let bot_clone = bot.clone();
+    let alerts = notify::Alerts::from_env(&config::env_or("STATE_DIR", "state".to_string()), telegram.clone());
+    alerts.send(EventKind::System, message::MessageBuilder::new()
+        .title("🚀", "SYSTEM ONLINE")
+        .line("Connected to Binance Global. Scanning for Whales...")
+        .build());
+
+    // SPAWN COMMAND POLLER (TELEGRAM_COMMANDS=false disables)
+    let (tx_cmd, mut rx_cmd) = mpsc::channel::<telegram::CommandRequest>(16);
+    if let Some(bot) = telegram.filter(|_| config::env_or("TELEGRAM_COMMANDS", true)) {
+        tokio::spawn(bot.poll_commands(tx_cmd));
+    }
     
-    // SPAWN MARKET STREAM
-    tokio::spawn(async move {
-        // We assume binance_client is already fixed and working
-        if let Err(e) = client::start_market_stream(tx_data).await {
-            error!("CRITICAL: Market stream died: {}", e);
+    // SPAWN MARKET STREAM (REPLAY_FROM=<file|dir> swaps in a recorded session)
+    let replay_from = config::env_or("REPLAY_FROM", String::new());
+    let bar_recorder: Option<recorder::TickRecorder>;
+    let mut recorder_thread: Option<recorder::RecorderThread> = None;
+    let (tx_book, rx_book) = tokio::sync::watch::channel::<Option<fills::OrderBook>>(None);
+    if !replay_from.is_empty() {
+        warn!("⏪ REPLAY MODE: {} (point STATE_DIR at a fresh dir; {})", replay_from, match model::SignalParams::from_env().seed {
+            Some(seed) => format!("Monte Carlo seeded with MC_SEED={}", seed),
+            None => "set MC_SEED to repeat the Monte Carlo draws".to_string(),
+        });
+        let speed = config::env_or("REPLAY_SPEED", 1.0);
+        tokio::spawn(async move {
+            if let Err(e) = recorder::start_replay_stream(replay_from, speed, tx_data).await {
+                error!("CRITICAL: Replay failed: {}", e);
+            }
+        });
+        bar_recorder = None;
+    } else {
+        let tick_recorder = recorder::RecorderConfig::from_env().and_then(|cfg| match recorder::TickRecorder::spawn(cfg) {
+            Ok((r, thread)) => {
+                recorder_thread = Some(thread);
+                Some(r)
+            }
+            Err(e) => {
+                error!("TICK RECORDER DISABLED: {}", e);
+                None
+            }
+        });
+        bar_recorder = tick_recorder.clone();
+        // Recordings have no L2, so replays always use the SLIPPAGE_BPS fallback
+        if config::env_or("SLIPPAGE_MODEL", String::new()).eq_ignore_ascii_case("book") {
+            tokio::spawn(client::start_depth_stream(tx_book));
         }
-    });
+        tokio::spawn(async move {
+            // We assume binance_client is already fixed and working
+            if let Err(e) = client::start_market_stream(tx_data, tick_recorder).await {
+                error!("CRITICAL: Market stream died: {}", e);
+            }
+        });
+    }
 
     // SPAWN LOGIC ENGINE
     let logic_handle = tokio::spawn(async move {
         let mut microstructure = model::MarketMicrostructure::new();
         let oracle = news_filter::NewsOracle::new();
+        let (mut journal, mut journal_health) = journal::TradeJournal::spawn(journal::JournalConfig::from_env());
         
         // --- INITIALIZE WALLET ---
-        let mut wallet = simulator::PaperWallet::new(); 
-        info!("💰 VIRTUAL WALLET INITIALIZED: $10,000");
+        let sim_mode = simulator::SimMode::from_env();
+        let mut wallet = simulator::PaperWallet::new(sim_mode);
+
+        // --- RESTORE SAVED STATE ---
+        let state_dir = config::env_or("STATE_DIR", "state".to_string());
+
+        // --- SQLITE STORE (SQLITE_PATH="" disables) ---
+        let db_path = config::env_or("SQLITE_PATH", format!("{}/trading.db", state_dir));
+        let mut history = Vec::new();
+        let storage = if db_path.is_empty() {
+            None
+        } else {
+            match storage::load_trades(&db_path).and_then(|h| Ok((h, storage::Storage::spawn(&db_path)?))) {
+                Ok((h, s)) => {
+                    history = h;
+                    Some(s)
+                }
+                Err(e) => {
+                    error!("SQLITE STORE DISABLED: {}", e);
+                    None
+                }
+            }
+        };
+
+        let mut store = match WalletStore::open(&state_dir) {
+            Ok(s) => Some(s),
+            Err(e) => {
+                error!("WALLET PERSISTENCE DISABLED: {}", e);
+                None
+            }
+        };
+        let mut recovered = Vec::new();
+        if let Some(store) = store.as_mut() {
+            if let Err(e) = store.restore(&mut wallet) {
+                // Never let the fresh snapshot below overwrite state that may still be recoverable
+                match store.set_aside() {
+                    Ok(suffix) => warn!("⚠️ COULD NOT RESTORE WALLET, STARTING FRESH (old state kept as *.{}): {}", suffix, e),
+                    Err(move_err) => {
+                        error!("COULD NOT RESTORE WALLET ({}) OR MOVE IT ASIDE ({}), REFUSING TO START", e, move_err);
+                        std::process::exit(1);
+                    }
+                }
+                wallet = simulator::PaperWallet::new(sim_mode);
+            }
+            // Replayed fills/settlements were journaled before the restart
+            wallet.drain_fills();
+            wallet.drain_closed();
+
+            // Binary trades that expired while we were down
+            wallet.settle_overdue(Utc::now());
+            for closed in wallet.drain_closed() {
+                journal.settlement(&closed).await;
+                if let Some(db) = &storage { db.trade(&closed); }
+                recovered.push(closed);
+            }
+            store.snapshot(&wallet);
+        }
+        info!("💰 VIRTUAL WALLET INITIALIZED: ${:.2} ({} mode)", wallet.balance, wallet.mode.label());
+
+        // Lifetime analytics: replay stored history, then continue live
+        let historic_pnl: f64 = history.iter().map(|t| t.pnl).sum::<f64>() + recovered.iter().map(|t| t.pnl).sum::<f64>();
+        let started_at = history.first().map_or(Utc::now(), |t| t.close_time);
+        let mut performance = performance::PerformanceTracker::new(wallet.equity() - historic_pnl, started_at);
+        for t in &history {
+            performance.push(t.close_time, t.pnl);
+        }
+        for closed in &recovered {
+            performance.record(closed);
+        }
+
+        // --- RISK MANAGER (kill switch only clears with RISK_RESET=true or /reset) ---
+        let mut risk = risk::RiskManager::new(risk::RiskLimits::from_env(), &wallet, &state_dir);
+        if config::env_or("RISK_RESET", false) {
+            risk.reset(&wallet);
+        }
+
+        // --- LIVE EXECUTION (EXECUTION_MODE=binance: the exchange position follows the paper position) ---
+        let mut rules = pretrade::SymbolRules::from_env("BTCUSDT");
+        let mut live = None;
+        if config::env_or("EXECUTION_MODE", "paper".to_string()).eq_ignore_ascii_case("binance") {
+            match binance::BinanceConfig::from_env(sim_mode) {
+                Ok(cfg) => {
+                    warn!("🏦 LIVE EXECUTION: Binance {:?} {} ({})", cfg.market, cfg.symbol,
+                        if cfg.testnet { "TESTNET" } else { "REAL MONEY" });
+                    match binance::BinanceExecutor::connect(cfg).await {
+                        Ok(exchange) => {
+                            rules = exchange.rules.clone();
+                            let mut oms = oms::OrderManager::new(exchange, oms::OmsConfig::from_env());
+                            // A restored paper position was mirrored by the previous run
+                            oms.position = paper_position(&wallet);
+                            if oms.position != 0.0 {
+                                warn!("🏦 LIVE POSITION ASSUMED FROM SAVED STATE: {:+.5} BTC", oms.position);
+                            }
+                            live = Some(oms);
+                        }
+                        Err(e) => error!("LIVE EXECUTION DISABLED: {}", e),
+                    }
+                }
+                Err(e) => error!("LIVE EXECUTION DISABLED: {}", e),
+            }
+        }
+        let mut pretrade = pretrade::PreTradeChecks::new(pretrade::PreTradeLimits::from_env(), rules, sim_mode);
+
+        // --- STRATEGIES (STRATEGIES=whale_mc,...; the first trades the main wallet) ---
+        let mut strategies = strategy::from_env();
+        let mut primary = strategies.remove(0);
+        let mut shadows: Vec<strategy::ShadowBook> = strategies.into_iter()
+            .map(|s| strategy::ShadowBook::new(s, sim_mode,
+                pretrade::PreTradeChecks::new(pretrade.limits.clone(), pretrade.rules.clone(), sim_mode), Utc::now()))
+            .collect();
+        let mut queued_intents: Vec<strategy::Intent> = Vec::new(); // From timers and fills, run on the next tick
+
+        // --- SIGNAL CALIBRATION (every evaluation of the primary, scored at its horizon) ---
+        let mut calibration = calibration::CalibrationTracker::new(calibration::CalibrationConfig::from_env());
+        let horizon_secs = calibration.config.horizon.num_seconds();
+        if storage.is_some() {
+            match storage::load_signal_outcomes(&db_path, horizon_secs) {
+                Ok(outcomes) => {
+                    for outcome in &outcomes { calibration.add(outcome); }
+                    info!("🎯 CALIBRATION: {}s horizon | {} stored signal outcomes", horizon_secs, outcomes.len());
+                }
+                Err(e) => warn!("⚠️ COULD NOT LOAD SIGNAL OUTCOMES: {}", e),
+            }
+        }
+        info!("🧠 STRATEGY: {} (+{} shadow)", primary.name(), shadows.len());
+        let feature_cfg = &microstructure.features.config;
+        let filters: Vec<&str> = feature_cfg.filters.iter().map(|f| f.label()).collect();
+        let bar_kinds: Vec<String> = microstructure.bars.kinds().iter().map(|k| k.to_string()).collect();
+        info!("📊 BARS: {}", bar_kinds.join(","));
+        info!("🧮 FEATURES: {} bars | Filters: {}", feature_cfg.bar,
+            if filters.is_empty() { "none".to_string() } else { filters.join(",") });
 
         let mut last_news_check = Instant::now();
+        let mut last_snapshot = Instant::now();
+        let mut last_checkpoint = Instant::now();
         let mut trades_processed = 0;
+        let mut paused = false;
+        let mut live_retry_at: Option<Instant> = None; // Backoff after a failed live order
+        let mut report_schedule = reports::ReportSchedule::from_env(Utc::now());
+        let mut report_timer = tokio::time::interval(Duration::from_secs(30));
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("SIGTERM handler");
 
         loop {
             tokio::select! {
                 Some(trade) = rx_data.recv() => {
-                    microstructure.update(&trade);
+                    let closed_bars = microstructure.update(&trade);
                     trades_processed += 1;
+                    for whale in microstructure.whales.drain_events() {
+                        info!("🐋 WHALE {} {}: {:.4} BTC (${:.0}) @ {:.2} | {} fill(s){}", whale.side.label(), whale.kind.label(),
+                            whale.quantity, whale.notional, whale.price, whale.fills,
+                            whale.zscore.map(|z| format!(" | z {:.1}", z)).unwrap_or_default());
+                    }
+                    if let Some(rec) = &bar_recorder {
+                        for bar in &closed_bars { rec.record_bar(bar); }
+                    }
                     
                     // --- UPDATE WALLET (Check for wins/losses) ---
-                    wallet.update(trade.price);
+                    if let Some(store) = store.as_mut() { store.record(WalletEvent::Tick(trade)); }
+                    wallet.book = rx_book.borrow().clone();
+                    wallet.update(&trade);
+                    if let Some(db) = &storage { db.tick(&trade); }
+                    for outcome in calibration.update(&trade) {
+                        if let Some(db) = &storage { db.signal_outcome(&outcome); }
+                    }
 
-                    // Speed Hack: Start showing dashboard after just 20 ticks
-                    if microstructure.prices.len() >= 20 {
-                        let current_price = trade.price;
-                        
-                        // Calculate Volatility
-                        let min = microstructure.prices.iter().fold(f64::INFINITY, |a, &b| a.min(b));
-                        let max = microstructure.prices.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
-                        let volatility = (max - min) / 2.0;
-
-                        // Dynamic Threshold Logic
-                        let mut required_conf = 90.0;
-                        if volatility > 100.0 { required_conf = 94.0; } 
-                        else if volatility < 20.0 { required_conf = 88.0; }
-
-                        // Analyze Signal
-                        let signal = model::QuantumSignal::analyze(&microstructure, current_price, volatility);
-
-                        if signal.confidence > required_conf {
-                            if signal.is_whale_confirmed {
-                                // 1. Calculate Bet Size
-                                let stake_str = signal.calculate_stake(); 
-                                let stake_val = stake_str.trim_end_matches('%').parse::<f64>().unwrap_or(1.0);
-
-                                // 2. EXECUTE TRADE IN SIMULATOR
-                                wallet.open_trade(signal.direction.clone(), current_price, stake_val);
-
-                                // 3. Log & Alert
-                                logger::TradeLogger::log_signal(&signal.direction, signal.confidence, current_price, true);
-                                
-                                let msg = format!(
-                                    "🔥 *ELITE SIGNAL*\nPair: BTC/USDT\nAction: *{}*\nConf: {:.1}%\n💰 Stake: *{}*\nPrice: {:.2}", 
-                                    signal.direction, signal.confidence, stake_str, current_price
-                                );
-                                bot_clone.send_signal(&msg).await;
-                                
-                                info!("🚀 SIGNAL FIRED: {} | Stake: {} | Balance: ${:.2}", signal.direction, stake_str, wallet.balance);
-                                
-                                // Cool down to prevent spam
-                                tokio::time::sleep(Duration::from_secs(60)).await;
+                    if let Some(reason) = risk.observe(&wallet) {
+                        flatten(&mut wallet, store.as_mut());
+                        alerts.critical(EventKind::Halt, message::halt(&reason));
+                    }
+
+                    // --- STRATEGIES (the primary trades the main wallet, the rest their own shadow books) ---
+                    let mut intents = std::mem::take(&mut queued_intents);
+                    intents.extend(primary.on_tick(&trade, &microstructure));
+                    for bar in &closed_bars {
+                        intents.extend(primary.on_bar(bar, &microstructure));
+                    }
+                    if let Some(eval) = primary.take_evaluation() {
+                        calibration.record(eval);
+                    }
+                    for shadow in shadows.iter_mut() {
+                        shadow.wallet.book = wallet.book.clone();
+                        shadow.on_tick(&trade, &closed_bars, &microstructure, &risk);
+                    }
+
+                    for intent in intents {
+                        let signal = &intent.signal;
+                        let (current_price, volatility, stake_val) = (intent.price, intent.volatility, intent.stake_pct);
+                        let trade_id = journal.next_trade_id();
+                        journal.signal(trade_id, trade.timestamp, signal, current_price, volatility).await;
+                        if let Some(db) = &storage { db.signal(trade_id, trade.timestamp, signal, current_price, volatility); }
+
+                        // 1. Bet Size comes from the strategy
+                        let mut order = executor::OrderRequest {
+                            trade_id,
+                            symbol: pretrade.rules.symbol.clone(),
+                            direction: signal.direction.clone(),
+                            price: current_price,
+                            stake_pct: stake_val,
+                            notional: wallet.balance * stake_val / 100.0,
+                            close_quantity: wallet.position.as_ref()
+                                .filter(|p| p.direction != signal.direction)
+                                .map_or(0.0, |p| p.quantity),
+                            quantity: 0.0,
+                        };
+
+                        // 2. RISK GATE (operator pause first, then risk limits, then pre-trade checks)
+                        let gate = if paused {
+                            Err("paused by operator".to_string())
+                        } else if let Err(r) = risk.check(&wallet, stake_val) {
+                            Err(r.to_string())
+                        } else if let Err(r) = pretrade.check(&mut order, wallet.book.as_ref().and_then(|b| b.mid()).unwrap_or(trade.price), trade.timestamp) {
+                            alerts.send(EventKind::Rejection, message::rejection(&order, &r.to_string()));
+                            Err(format!("pre-trade: {}", r))
+                        } else {
+                            Ok(())
+                        };
+                        match gate {
+                            Err(rejection) => {
+                                warn!("🛑 SIGNAL BLOCKED: {} | {}", signal.direction, rejection);
+                                journal.rejected(trade_id, trade.timestamp, &signal.direction, current_price, &rejection).await;
+                            }
+                            Ok(()) => {
+                                // 3. EXECUTE TRADE IN SIMULATOR (the exchange follows its fills below)
+                                if let Err(e) = wallet.submit(&order).await {
+                                    info!("🚫 PAPER ORDER NOT PLACED: {}", e);
+                                    journal.rejected(trade_id, trade.timestamp, &signal.direction, order.price, &e.to_string()).await;
+                                    continue;
+                                }
+                                // The rounded price the wallet used, so a replay opens the same trade
+                                journal.order(trade_id, trade.timestamp, &signal.direction, order.price, stake_val, order.notional).await;
+                                if let Some(store) = store.as_mut() {
+                                    store.record(WalletEvent::Order {
+                                        trade_id,
+                                        direction: signal.direction.clone(),
+                                        price: order.price,
+                                        stake_pct: stake_val,
+                                    });
+                                }
+
+                                // 4. Alert
+                                alerts.send(EventKind::Signal, message::signal(&signal.direction, signal.confidence, stake_val, current_price));
+                                info!("🚀 SIGNAL FIRED: {} | Stake: {:.1}% | Balance: ${:.2}", signal.direction, stake_val, wallet.balance);
+                                info!("🧮 FEATURES: {}", signal.features);
                             }
                         }
                     }
+
+                    // --- FILLS & SETTLEMENTS (Expiry / SL / TP / Trailing / Time) ---
+                    for fill in wallet.drain_fills() {
+                        journal.fill(&fill).await;
+                        queued_intents.extend(primary.on_fill(&fill));
+                    }
+                    if let Some(live) = live.as_mut() {
+                        // Entries, exits, flips and kill-switch closes all reach the exchange as the
+                        // difference between the paper and the venue position
+                        let gap = live.gap_to(paper_position(&wallet)).filter(|_| live_retry_at.is_none_or(|t| Instant::now() >= t));
+                        let qty = gap.map_or(0.0, |g| pretrade.rules.round_qty(g.abs()));
+                        if qty >= pretrade.rules.min_qty && qty * trade.price >= pretrade.rules.min_notional {
+                            let direction = if gap.unwrap_or_default() > 0.0 { "UP" } else { "DOWN" };
+                            let order = executor::OrderRequest {
+                                trade_id: journal.next_trade_id(),
+                                symbol: pretrade.rules.symbol.clone(),
+                                direction: direction.to_string(),
+                                price: trade.price,
+                                stake_pct: 0.0,
+                                notional: qty * trade.price,
+                                close_quantity: 0.0,
+                                quantity: qty,
+                            };
+                            match live.submit(order.clone()).await {
+                                Ok(ack) => {
+                                    live_retry_at = None;
+                                    info!("🏦 LIVE ORDER {} | {} {:.5} | {} | #{} | Filled {:.5} @ {:.2}", ack.client_order_id, order.side().label(),
+                                        qty, ack.state.label(), ack.exchange_order_id.unwrap_or_default(), ack.filled_qty, ack.avg_price);
+                                }
+                                Err(e) => {
+                                    live_retry_at = Some(Instant::now() + Duration::from_secs(30));
+                                    error!("LIVE ORDER FAILED ({}): {}", order.client_order_id(), e);
+                                    journal.rejected(order.trade_id, trade.timestamp, direction, trade.price, &format!("{}: {}", live.venue(), e)).await;
+                                    alerts.critical(EventKind::System, message::MessageBuilder::new()
+                                        .title("⚠️", "LIVE ORDER FAILED")
+                                        .field("Order", order.client_order_id())
+                                        .field("Error", &e)
+                                        .line("Retrying in 30s.")
+                                        .build());
+                                }
+                            }
+                        }
+                        for report in live.poll(Utc::now()).await {
+                            info!("🏦 {} {} #{} | {} {:.5} @ {:.2} | Filled {:.5} (avg {:.2}) | Fee {:.8} {}", live.venue(),
+                                report.client_order_id, report.exchange_order_id.unwrap_or_default(), report.state.label(),
+                                report.last_qty, report.last_price, report.cum_qty, report.avg_price, report.fee, report.fee_asset);
+                            journal.execution(live.venue(), &report).await;
+                            if matches!(report.state, executor::OrderState::Rejected | executor::OrderState::Canceled | executor::OrderState::Expired) {
+                                alerts.send(EventKind::System, message::MessageBuilder::new()
+                                    .title("⚠️", &format!("LIVE ORDER {}", report.state.label()))
+                                    .field("Order", &report.client_order_id)
+                                    .field("Filled", format!("{:.5} @ {:.2}", report.cum_qty, report.avg_price))
+                                    .field("Reason", report.reason.as_deref().unwrap_or("-"))
+                                    .build());
+                            }
+                        }
+                    }
+                    for closed in wallet.drain_closed() {
+                        journal.settlement(&closed).await;
+                        if let Some(db) = &storage { db.trade(&closed); }
+                        performance.record(&closed);
+                        if let Some(reason) = risk.on_close(&closed) {
+                            flatten(&mut wallet, store.as_mut());
+                            alerts.critical(EventKind::Halt, message::halt(&reason));
+                        }
+                        info!("📊 SESSION PERFORMANCE\n{}", performance.report());
+                        alerts.send(EventKind::Settlement, message::settlement(&closed));
+                    }
+
+                    // --- PERIODIC SNAPSHOT (compacts the wallet journal; syncs buffered ticks in between) ---
+                    if last_snapshot.elapsed() > Duration::from_secs(60) {
+                        if let Some(store) = store.as_mut() { store.snapshot(&wallet); }
+                        if let Some(db) = &storage { db.wallet_snapshot(&wallet); }
+                        last_snapshot = Instant::now();
+                        last_checkpoint = last_snapshot;
+                    } else if last_checkpoint.elapsed() > Duration::from_secs(5) {
+                        if let Some(store) = store.as_mut() { store.checkpoint(); }
+                        last_checkpoint = Instant::now();
+                    }
+                }
+
+                // --- SCHEDULED REPORTS & STRATEGY TIMERS ---
+                _ = report_timer.tick() => {
+                    let now = Utc::now();
+                    queued_intents.extend(primary.on_timer(now));
+                    for shadow in shadows.iter_mut() {
+                        shadow.on_timer(now, &risk);
+                    }
+                    for period in report_schedule.due(now) {
+                        info!("🗓️ SENDING {:?} REPORT", period);
+                        alerts.send(EventKind::Report, report_schedule.build(period, &performance, now));
+                        let report = calibration.report();
+                        if report.scored > 0 {
+                            alerts.send(EventKind::Report, message::calibration(&report));
+                        }
+                    }
+                },
+
+                // --- OPERATOR COMMANDS ---
+                Some(req) = rx_cmd.recv() => {
+                    let reply = match req.command {
+                        telegram::Command::Pause => {
+                            paused = true;
+                            warn!("⏸️ TRADING PAUSED by {}", req.user);
+                            message::MessageBuilder::new().title("⏸️", "Trading paused.").line("Open positions are still managed.").build()
+                        }
+                        telegram::Command::Resume => {
+                            paused = false;
+                            info!("▶️ TRADING RESUMED by {}", req.user);
+                            message::MessageBuilder::new().title("▶️", "Trading resumed.").build()
+                        }
+                        telegram::Command::Halt => {
+                            let reason = format!("manual halt by {}", req.user);
+                            if risk.halt(reason.clone()) {
+                                flatten(&mut wallet, store.as_mut());
+                                message::halt(&reason)
+                            } else {
+                                message::MessageBuilder::new().line("🛑 Kill switch is already latched.").build()
+                            }
+                        }
+                        telegram::Command::Reset => {
+                            risk.reset(&wallet);
+                            message::MessageBuilder::new().title("✅", "Kill switch reset.").build()
+                        }
+                        telegram::Command::Calibration => message::calibration(&calibration.report()),
+                        cmd => command_report(cmd, &microstructure, &wallet, &performance, &risk, live.as_ref(), paused,
+                            storage.as_ref().map(|db| db.dropped())),
+                    };
+                    alerts.reply(telegram::BACKEND, &req.chat_id, reply);
+                },
+
+                // --- JOURNAL WRITER HEALTH ---
+                Some(health) = journal_health.recv() => match health {
+                    journal::JournalHealth::Backpressure { capacity } => {
+                        warn!("⚠️ JOURNAL BACKPRESSURE: Queue full ({} records), logic loop waited", capacity);
+                    }
+                    journal::JournalHealth::Rotated { path } => info!("🗂️ JOURNAL ROTATED: {}", path),
+                    journal::JournalHealth::WriteFailed { error, pending } => {
+                        error!("JOURNAL WRITE FAILED ({} records held for retry): {}", pending, error);
+                    }
+                    journal::JournalHealth::Dropped { count } => {
+                        error!("JOURNAL RETRY BUFFER OVERFLOW: {} records dropped", count);
+                        alerts.critical(EventKind::System, message::MessageBuilder::new()
+                            .title("⚠️", "JOURNAL DATA LOSS")
+                            .line(format!("{} records dropped after repeated write failures.", count))
+                            .build());
+                    }
+                },
+
+                // --- SHUTDOWN ---
+                _ = tokio::signal::ctrl_c() => {
+                    warn!("🛑 SHUTDOWN: Ctrl-C received");
+                    break;
+                }
+                _ = sigterm.recv() => {
+                    warn!("🛑 SHUTDOWN: SIGTERM received");
+                    break;
                 }
 
                 // --- HEARTBEAT DASHBOARD ---
@@ -112,7 +545,7 @@ async fn main() -> anyhow::Result<()> {
                      } else {
                         // DASHBOARD WITH WALLET BALANCE
                         let ofi = microstructure.calculate_ofi();
-                        let status = if ofi > 0.2 { "🐂" } else if ofi < -0.2 { "🐻" } else { "🦀" };
+                        let status = if !microstructure.ofi_trusted() { "🦀 (thin)" } else if ofi > 0.2 { "🐂" } else if ofi < -0.2 { "🐻" } else { "🦀" };
                         let price = microstructure.prices.back().unwrap_or(&0.0);
                         
                         // Win Rate Calc
@@ -121,8 +554,28 @@ async fn main() -> anyhow::Result<()> {
                             (wallet.wins as f64 / total_trades as f64 * 100.0) as u64
                         } else { 0 };
 
-                        info!("⚡ BTC: {:.2} | 💰 Bal: ${:.0} (WR: {}%) | OFI: {:.3} | {}", 
-                            price, wallet.balance, win_rate, ofi, status);
+                        info!("⚡ BTC: {:.2} | 💰 Bal: ${:.0} (WR: {}%) | OFI: {:.3} | {} | 🐋 ${:+.0}",
+                            price, wallet.balance, win_rate, ofi, status, microstructure.whales.net_flow());
+
+                        if !performance.trades.is_empty() {
+                            info!("📈 {}", performance.report().one_line());
+                        }
+                        let calibration_report = calibration.report();
+                        if calibration_report.scored > 0 {
+                            info!("🎯 {}", calibration_report.one_line());
+                        }
+                        for shadow in &shadows {
+                            info!("🧪 [{}] Bal: ${:.0} | Eq: ${:.0} | {} trades", shadow.strategy.name(), shadow.wallet.balance,
+                                shadow.wallet.equity(), shadow.performance.trades.len());
+                        }
+
+                        // Linear modes: mark-to-market view
+                        if let Some(pos) = &wallet.position {
+                            info!("📊 {} {} {:.5} BTC @ {:.2} | uPnL: ${:+.2} | Eq: ${:.0} | rPnL: ${:+.2} | Fees: ${:.2} | Slip: ${:.2} | Funding: ${:+.2}",
+                                wallet.mode.label(), pos.direction, pos.quantity, pos.avg_entry,
+                                pos.unrealized_pnl(*price), wallet.equity(),
+                                wallet.realized_pnl, wallet.fees_paid, wallet.slippage_paid, -wallet.funding_paid);
+                        }
                      }
                 }
             }
@@ -137,8 +590,106 @@ async fn main() -> anyhow::Result<()> {
                 last_news_check = Instant::now();
             }
         }
+
+        // Commit what is still queued before the process exits
+        if let Some(store) = store.as_mut() { store.snapshot(&wallet); }
+        journal.close().await;
+        if let Some(db) = storage { db.close(); }
+        if let Some(rec) = recorder_thread { rec.close(); }
     });
 
     logic_handle.await?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Signed paper position in BTC (+ long / - short), the target the live book follows.
+fn paper_position(wallet: &simulator::PaperWallet) -> f64 {
+    wallet.position.as_ref().map_or(0.0, |p| if p.direction == "UP" { p.quantity } else { -p.quantity })
+}
+
+/// Kill switch: closes the paper book (and so the live one), journaled for replay.
+fn flatten(wallet: &mut simulator::PaperWallet, store: Option<&mut WalletStore>) {
+    if let Some(store) = store { store.record(WalletEvent::Flatten); }
+    wallet.flatten();
+}
+
+/// Read-only command replies.
+#[allow(clippy::too_many_arguments)]
+fn command_report(
+    command: telegram::Command,
+    market: &model::MarketMicrostructure,
+    wallet: &simulator::PaperWallet,
+    performance: &performance::PerformanceTracker,
+    risk: &risk::RiskManager,
+    live: Option<&oms::OrderManager<binance::BinanceExecutor>>,
+    paused: bool,
+    db_dropped: Option<u64>, // Rows the SQLite queue had to drop
+) -> message::Message {
+    match command {
+        telegram::Command::Status => {
+            let total = wallet.wins + wallet.losses;
+            let win_rate = if total > 0 { wallet.wins as f64 / total as f64 * 100.0 } else { 0.0 };
+            let state = match (&risk.halted, paused) {
+                (Some(reason), _) => format!("🛑 HALTED ({})", reason),
+                (None, true) => "⏸️ PAUSED".to_string(),
+                (None, false) => "▶️ TRADING".to_string(),
+            };
+            let mut msg = message::MessageBuilder::new()
+                .title("⚡", "STATUS")
+                .field("BTC", format!("{:.2}", market.prices.back().copied().unwrap_or(0.0)))
+                .field("OFI", format!("{:.3}", market.calculate_ofi()))
+                .field("Balance", format!("${:.2}", wallet.balance))
+                .field("Equity", format!("${:.2}", wallet.equity()))
+                .field("Win Rate", format!("{:.1}% ({}W / {}L)", win_rate, wallet.wins, wallet.losses));
+            if let Some(dropped) = db_dropped {
+                msg = msg.field("DB Dropped", format!("{} rows", dropped));
+            }
+            msg.strong_field("State", state).build()
+        }
+        telegram::Command::Positions => {
+            let mut msg = message::MessageBuilder::new().title("📂", &format!("POSITIONS ({} mode)", wallet.mode.label()));
+            for t in &wallet.active_trades {
+                msg = msg.line(format!("{} ${:.2} @ {:.2}, expires {}", t.direction, t.stake, t.entry_price, t.expiry.format("%H:%M:%S")));
+            }
+            if let Some(pos) = &wallet.position {
+                msg = msg.line(format!("{} {:.5} BTC @ {:.2} | uPnL: ${:+.2}",
+                    pos.direction, pos.quantity, pos.avg_entry, pos.unrealized_pnl(wallet.mark_price)));
+            }
+            for o in &wallet.pending_orders {
+                msg = msg.line(format!("Pending {} {:.1}% (decided {})", o.direction, o.stake_pct, o.decided_at.format("%H:%M:%S")));
+            }
+            if wallet.open_positions() == 0 && wallet.pending_orders.is_empty() {
+                msg = msg.line("No open positions.");
+            }
+            if let Some(live) = live {
+                msg = msg.strong_field(&format!("{} position", live.venue()), format!("{:+.5} BTC", live.position));
+                for o in live.open_orders() {
+                    msg = msg.line(format!("{} {} {} | filled {:.5} @ {:.2} (sent {})", o.request.client_order_id(), o.side().label(),
+                        o.state.label(), o.filled_qty, o.avg_price, o.created.format("%H:%M:%S")));
+                }
+            }
+            msg.build()
+        }
+        telegram::Command::Pnl => {
+            if performance.trades.is_empty() {
+                message::MessageBuilder::new().title("📊", "PNL").line("No closed trades yet.").build()
+            } else {
+                message::report("PNL", &performance.report())
+            }
+        }
+        telegram::Command::Config => {
+            let limits = &risk.limits;
+            let exits = &wallet.exit_rules;
+            let fm = &wallet.fill_model;
+            message::MessageBuilder::new()
+                .title("⚙️", "CONFIG")
+                .pre(format!("mode        {}\npaused      {}\nmax pos     {}\nmax expo    {:.1}%\ndaily loss  {:.1}%\nmax dd      {:.1}%\nloss streak {}\nstop loss   {:.2}%\ntake profit {:.2}%\ntrailing    {:.2}%\nmax hold    {}s\nfees        {:.4}/{:.4}\nhalf spread {:.2}bps\nlatency     {}ms",
+                    wallet.mode.label(), paused, limits.max_positions, limits.max_exposure_pct, limits.daily_loss_pct,
+                    limits.max_drawdown_pct, limits.max_consecutive_losses, exits.stop_loss_pct, exits.take_profit_pct,
+                    exits.trailing_pct, exits.max_hold_secs, fm.fees.maker_rate, fm.fees.taker_rate, fm.half_spread_bps,
+                    fm.latency.as_millis()))
+                .build()
+        }
+        _ => message::help(),
+    }
+}
@@ -0,0 +1,336 @@
+use std::fmt::Display;
+use serde::{Deserialize, Serialize};
+use crate::calibration::CalibrationReport;
+use crate::executor::OrderRequest;
+use crate::performance::PerformanceReport;
+use crate::simulator::ClosedTrade;
+
+/// Markup dialect a backend renders messages in.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Format {
+    Plain,
+    MarkdownV2, // Telegram
+    Html,       // Telegram
+    Discord,    // Discord markdown
+    Slack,      // Slack mrkdwn
+}
+
+impl Format {
+    /// Reads `TELEGRAM_PARSE_MODE` (html | markdownv2 | plain). Defaults to html.
+    /// Legacy "Markdown" has no escape for every special character, so it is not offered.
+    pub fn telegram_from_env() -> Self {
+        match std::env::var("TELEGRAM_PARSE_MODE").unwrap_or_default().to_lowercase().as_str() {
+            "markdownv2" | "markdown" | "md" => Format::MarkdownV2,
+            "plain" | "none" => Format::Plain,
+            _ => Format::Html,
+        }
+    }
+
+    /// Value for the Telegram Bot API `parse_mode` field.
+    pub fn telegram_parse_mode(&self) -> Option<&'static str> {
+        match self {
+            Format::MarkdownV2 => Some("MarkdownV2"),
+            Format::Html => Some("HTML"),
+            _ => None,
+        }
+    }
+
+    /// Escapes dynamic text so it shows literally.
+    pub fn escape(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        for c in text.chars() {
+            match (self, c) {
+                (Format::Html | Format::Slack, '&') => out.push_str("&amp;"),
+                (Format::Html | Format::Slack, '<') => out.push_str("&lt;"),
+                (Format::Html | Format::Slack, '>') => out.push_str("&gt;"),
+                (Format::MarkdownV2, '_' | '*' | '[' | ']' | '(' | ')' | '~' | '`' | '>' | '#' | '+' | '-'
+                    | '=' | '|' | '{' | '}' | '.' | '!' | '\\')
+                | (Format::Discord, '_' | '*' | '~' | '`' | '|' | '>' | '\\') => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                _ => out.push(c),
+            }
+        }
+        out
+    }
+
+    fn bold(&self, text: &str) -> String {
+        match self {
+            Format::Plain => text.to_string(),
+            Format::MarkdownV2 | Format::Slack => format!("*{}*", self.escape(text)),
+            Format::Discord => format!("**{}**", self.escape(text)),
+            Format::Html => format!("<b>{}</b>", self.escape(text)),
+        }
+    }
+
+    fn pre(&self, text: &str) -> String {
+        match self {
+            Format::Plain => text.to_string(),
+            // Inside pre blocks only ` and \ are special
+            Format::MarkdownV2 => format!("```\n{}\n```", text.replace('\\', "\\\\").replace('`', "\\`")),
+            Format::Html => format!("<pre>{}</pre>", self.escape(text)),
+            Format::Discord => format!("```\n{}\n```", text.replace('`', "'")),
+            Format::Slack => format!("```{}```", self.escape(text)),
+        }
+    }
+}
+
+/// One piece of a message. Kept unformatted so each backend can render it in its own markup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Block {
+    Title { icon: String, text: String },
+    Field { label: String, value: String, strong: bool },
+    Line(String),
+    Pre(String),
+    Break, // Blank line between coalesced messages
+}
+
+/// A notification body, rendered per backend with `render`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub blocks: Vec<Block>,
+    /// Optional PNG (e.g. an equity chart). Backends without image support send the text only.
+    #[serde(skip)]
+    pub image: Option<Vec<u8>>,
+}
+
+impl Message {
+    pub fn render(&self, format: Format) -> String {
+        self.blocks.iter().map(|b| match b {
+            Block::Title { icon, text } => format!("{} {}", format.escape(icon), format.bold(text)),
+            Block::Field { label, value, strong: true } => format!("{}: {}", format.escape(label), format.bold(value)),
+            Block::Field { label, value, strong: false } => format!("{}: {}", format.escape(label), format.escape(value)),
+            Block::Line(text) => format.escape(text),
+            Block::Pre(text) => format.pre(text),
+            Block::Break => String::new(),
+        }).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Text of the first title, e.g. for an email subject.
+    pub fn title(&self) -> Option<&str> {
+        self.blocks.iter().find_map(|b| match b {
+            Block::Title { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Appends another message after a blank line.
+    pub fn append(&mut self, other: Message) {
+        self.blocks.push(Block::Break);
+        self.blocks.extend(other.blocks);
+    }
+}
+
+/// Builds a message block by block. Escaping happens at render time, so any value is safe.
+#[derive(Default)]
+pub struct MessageBuilder {
+    blocks: Vec<Block>,
+    image: Option<Vec<u8>>,
+}
+
+impl MessageBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// "🔥 **TITLE**"
+    pub fn title(mut self, icon: &str, title: &str) -> Self {
+        self.blocks.push(Block::Title { icon: icon.to_string(), text: title.to_string() });
+        self
+    }
+
+    /// "Label: value"
+    pub fn field(mut self, label: &str, value: impl Display) -> Self {
+        self.blocks.push(Block::Field { label: label.to_string(), value: value.to_string(), strong: false });
+        self
+    }
+
+    /// "Label: **value**"
+    pub fn strong_field(mut self, label: &str, value: impl Display) -> Self {
+        self.blocks.push(Block::Field { label: label.to_string(), value: value.to_string(), strong: true });
+        self
+    }
+
+    pub fn line(mut self, text: impl Display) -> Self {
+        self.blocks.push(Block::Line(text.to_string()));
+        self
+    }
+
+    /// Monospaced block, for tables and multi-line reports.
+    pub fn pre(mut self, text: impl Display) -> Self {
+        self.blocks.push(Block::Pre(text.to_string()));
+        self
+    }
+
+    /// Attaches a PNG image.
+    pub fn image(mut self, png: Vec<u8>) -> Self {
+        self.image = Some(png);
+        self
+    }
+
+    pub fn build(self) -> Message {
+        Message { blocks: self.blocks, image: self.image }
+    }
+}
+
+// --- TEMPLATES ---
+
+pub fn signal(direction: &str, confidence: f64, stake_pct: f64, price: f64) -> Message {
+    MessageBuilder::new()
+        .title("🔥", "ELITE SIGNAL")
+        .field("Pair", "BTC/USDT")
+        .strong_field("Action", direction)
+        .field("Conf", format!("{:.1}%", confidence))
+        .strong_field("💰 Stake", format!("{:.1}%", stake_pct))
+        .field("Price", format!("{:.2}", price))
+        .build()
+}
+
+pub fn settlement(closed: &ClosedTrade) -> Message {
+    let outcome = if closed.pnl > 0.0 { "🏆" } else if closed.pnl == 0.0 { "↩️" } else { "💀" };
+    MessageBuilder::new()
+        .title(outcome, "TRADE CLOSED")
+        .field("Pair", "BTC/USDT")
+        .strong_field("Side", &closed.direction)
+        .strong_field("Exit", closed.reason.label())
+        .field("Entry", format!("{:.2}", closed.entry_price))
+        .field("Exit Price", format!("{:.2}", closed.exit_price))
+        .strong_field("PnL", format!("${:+.2}", closed.pnl))
+        .field("Held", format!("{}s", (closed.close_time - closed.open_time).num_seconds()))
+        .field("Settle", closed.price_source.label())
+        .build()
+}
+
+pub fn halt(reason: &str) -> Message {
+    MessageBuilder::new()
+        .title("🛑", "KILL SWITCH TRIPPED")
+        .field("Reason", reason)
+        .line("Trading halted until /reset.")
+        .build()
+}
+
+pub fn rejection(order: &OrderRequest, reason: &str) -> Message {
+    MessageBuilder::new()
+        .title("🚫", "ORDER REJECTED")
+        .field("Order", order.client_order_id())
+        .strong_field("Side", order.side().label())
+        .field("Notional", format!("${:.2}", order.notional))
+        .field("Price", format!("{:.2}", order.price))
+        .strong_field("Check", reason)
+        .build()
+}
+
+/// Performance summary, used for /pnl and the periodic reports.
+pub fn report(title: &str, report: &PerformanceReport) -> Message {
+    MessageBuilder::new()
+        .title("📊", title)
+        .pre(report)
+        .build()
+}
+
+pub fn calibration(report: &CalibrationReport) -> Message {
+    MessageBuilder::new()
+        .title("🎯", "SIGNAL CALIBRATION")
+        .pre(report)
+        .build()
+}
+
+/// Scheduled daily/weekly summary.
+pub fn period_report(title: &str, report: &PerformanceReport, chart: Option<Vec<u8>>) -> Message {
+    let mut msg = MessageBuilder::new()
+        .title("🗓️", title)
+        .strong_field("PnL", format!("${:+.2} ({:+.2}%)", report.net_pnl, report.total_return))
+        .field("Trades", format!("{} ({}W / {}L)", report.trades, report.wins, report.losses))
+        .field("Win Rate", format!("{:.1}%", report.win_rate))
+        .field("Max DD", format!("{:.2}%", report.max_drawdown))
+        .field("Best / Worst", format!("${:+.2} / ${:+.2}", report.best_trade, report.worst_trade))
+        .field("Sharpe / PF", format!("{:.2} / {:.2}", report.sharpe, report.profit_factor))
+        .field("Equity", format!("${:.2}", report.final_equity));
+    if let Some(png) = chart {
+        msg = msg.image(png);
+    }
+    msg.build()
+}
+
+pub fn help() -> Message {
+    MessageBuilder::new()
+        .title("🤖", "COMMANDS")
+        .line("/status - price, OFI, balance, win rate")
+        .line("/positions - open trades")
+        .line("/pnl - performance report")
+        .line("/config - active settings")
+        .line("/calibration - signal confidence vs outcomes")
+        .line("/pause - stop opening trades")
+        .line("/resume - start again (allow-listed users)")
+        .line("/halt - trip the kill switch (allow-listed users)")
+        .line("/reset - clear the kill switch (allow-listed users)")
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_v2_escapes_every_reserved_character() {
+        for c in "_*[]()~`>#+-=|{}.!\\".chars() {
+            assert_eq!(Format::MarkdownV2.escape(&c.to_string()), format!("\\{}", c));
+        }
+        assert_eq!(Format::MarkdownV2.escape("1.5% (max) -> done!"), "1\\.5% \\(max\\) \\-\\> done\\!");
+        assert_eq!(Format::MarkdownV2.escape("<b>&"), "<b\\>&");
+    }
+
+    #[test]
+    fn html_escapes_markup_only() {
+        assert_eq!(Format::Html.escape("<b>P&L</b> *1.5* _x_"), "&lt;b&gt;P&amp;L&lt;/b&gt; *1.5* _x_");
+    }
+
+    #[test]
+    fn discord_escapes_markdown() {
+        assert_eq!(Format::Discord.escape("a_b *c* ~d~ `e` |f| > g \\"), "a\\_b \\*c\\* \\~d\\~ \\`e\\` \\|f\\| \\> g \\\\");
+        assert_eq!(Format::Discord.escape("1.5 (x) #1 <y"), "1.5 (x) #1 <y");
+    }
+
+    #[test]
+    fn slack_escapes_control_characters() {
+        assert_eq!(Format::Slack.escape("<!here> & *bold*"), "&lt;!here&gt; &amp; *bold*");
+    }
+
+    #[test]
+    fn plain_leaves_text_alone() {
+        let text = "a_b *c* [x](y) <b>&</b> \\";
+        assert_eq!(Format::Plain.escape(text), text);
+    }
+
+    #[test]
+    fn pre_blocks_only_escape_what_breaks_them() {
+        assert_eq!(Format::MarkdownV2.pre("a.b `c` \\"), "```\na.b \\`c\\` \\\\\n```");
+        assert_eq!(Format::Html.pre("x < y & z"), "<pre>x &lt; y &amp; z</pre>");
+        assert_eq!(Format::Discord.pre("a ``` b"), "```\na ''' b\n```");
+        assert_eq!(Format::Slack.pre("a & <b>"), "```a &amp; &lt;b&gt;```");
+        assert_eq!(Format::Plain.pre("a `b`"), "a `b`");
+    }
+
+    #[test]
+    fn render_escapes_values_in_every_format() {
+        let msg = MessageBuilder::new()
+            .title("🔥", "P&L <1>")
+            .field("Side", "UP_1.5")
+            .strong_field("Stake", "2.0%")
+            .line("done!")
+            .build();
+        assert_eq!(msg.render(Format::Html), "🔥 <b>P&amp;L &lt;1&gt;</b>\nSide: UP_1.5\nStake: <b>2.0%</b>\ndone!");
+        assert_eq!(msg.render(Format::MarkdownV2), "🔥 *P&L <1\\>*\nSide: UP\\_1\\.5\nStake: *2\\.0%*\ndone\\!");
+        assert_eq!(msg.render(Format::Discord), "🔥 **P&L <1\\>**\nSide: UP\\_1.5\nStake: **2.0%**\ndone!");
+        assert_eq!(msg.render(Format::Slack), "🔥 *P&amp;L &lt;1&gt;*\nSide: UP_1.5\nStake: *2.0%*\ndone!");
+        assert_eq!(msg.render(Format::Plain), "🔥 P&L <1>\nSide: UP_1.5\nStake: 2.0%\ndone!");
+    }
+
+    #[test]
+    fn append_separates_messages_with_a_blank_line() {
+        let mut msg = MessageBuilder::new().line("one").build();
+        msg.append(MessageBuilder::new().line("two").build());
+        assert_eq!(msg.render(Format::Plain), "one\n\ntwo");
+    }
+}
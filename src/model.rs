@@ -1,9 +1,17 @@
 use rayon::prelude::*;
 use rand::prelude::*;
 use std::collections::VecDeque;
+use chrono::{DateTime, Utc};
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::config::env_or;
+use crate::bars::{Bar, BarSet};
+use crate::features::{FeatureConfig, FeaturePipeline, FeatureSnapshot};
+use crate::whale::{WhaleConfig, WhaleDetector};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct TradeData {
+    pub timestamp: DateTime<Utc>,
     pub price: f64,
     pub quantity: f64,
     pub is_buyer_maker: bool,
@@ -14,24 +22,103 @@ pub struct QuantumSignal {
     pub confidence: f64,
     pub direction: String,
     pub is_whale_confirmed: bool,
+    pub prob_up: f64, // Monte Carlo share of paths finishing above the current price
+    pub ofi: f64,
+    pub features: FeatureSnapshot,   // Indicators when the signal was taken
+    pub is_feature_confirmed: bool, // Passed every FEATURE_FILTERS check
 }
 
+/// Thresholds of the Monte Carlo whale engine. The defaults are the original hand-picked
+/// values; `OPTIMIZE_FROM` searches them walk-forward.
+#[derive(Debug, Clone, Copy)]
+pub struct SignalParams {
+    pub prob_cutoff: f64,   // prob_up above this is UP, below 1 - this is DOWN
+    pub ofi_threshold: f64, // |OFI| that agrees with the direction
+    pub conf: f64,          // Confidence needed at normal volatility, %
+    pub conf_low_vol: f64,  // ...below vol_low
+    pub conf_high_vol: f64, // ...above vol_high
+    pub vol_low: f64,
+    pub vol_high: f64,
+    pub warmup_ticks: usize,
+    pub mc_paths: usize,
+    pub seed: Option<u64>, // Monte Carlo seed; None draws from the OS, so runs differ
+}
+
+impl SignalParams {
+    /// Reads `SIGNAL_PROB_CUTOFF` (0.70), `SIGNAL_OFI` (0.2), `SIGNAL_CONF` (90),
+    /// `SIGNAL_CONF_LOW_VOL` (88), `SIGNAL_CONF_HIGH_VOL` (94), `SIGNAL_VOL_LOW` (20),
+    /// `SIGNAL_VOL_HIGH` (100), `SIGNAL_WARMUP_TICKS` (20, at most the 100-tick window), `MC_PATHS` (10000)
+    /// and `MC_SEED` (unset).
+    pub fn from_env() -> Self {
+        Self {
+            prob_cutoff: env_or("SIGNAL_PROB_CUTOFF", 0.70),
+            ofi_threshold: env_or("SIGNAL_OFI", 0.2),
+            conf: env_or("SIGNAL_CONF", 90.0),
+            conf_low_vol: env_or("SIGNAL_CONF_LOW_VOL", 88.0),
+            conf_high_vol: env_or("SIGNAL_CONF_HIGH_VOL", 94.0),
+            vol_low: env_or("SIGNAL_VOL_LOW", 20.0),
+            vol_high: env_or("SIGNAL_VOL_HIGH", 100.0),
+            warmup_ticks: env_or("SIGNAL_WARMUP_TICKS", 20usize).clamp(2, PRICE_WINDOW),
+            mc_paths: env_or("MC_PATHS", 10_000usize).max(100),
+            seed: std::env::var("MC_SEED").ok().and_then(|s| s.trim().parse().ok()),
+        }
+    }
+
+    /// The generator the strategy draws its Monte Carlo paths from.
+    pub fn rng(&self) -> StdRng {
+        self.seed.map_or_else(StdRng::from_os_rng, StdRng::seed_from_u64)
+    }
+
+    /// Dynamic threshold: demand more in wild markets, less in quiet ones.
+    pub fn required_confidence(&self, volatility: f64) -> f64 {
+        if volatility > self.vol_high {
+            self.conf_high_vol
+        } else if volatility < self.vol_low {
+            self.conf_low_vol
+        } else {
+            self.conf
+        }
+    }
+}
+
+impl fmt::Display for SignalParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "prob {:.2} | ofi {:.2} | conf {:.0}/{:.0}/{:.0} | warmup {}",
+            self.prob_cutoff, self.ofi_threshold, self.conf_low_vol, self.conf, self.conf_high_vol, self.warmup_ticks)
+    }
+}
+
+/// Ticks kept for OFI and volatility.
+const PRICE_WINDOW: usize = 100;
+/// Monte Carlo paths per seeded chunk; fixed so the result does not depend on the thread count.
+const MC_CHUNK: usize = 256;
+
 pub struct MarketMicrostructure {
     pub bids: VecDeque<f64>,
     pub asks: VecDeque<f64>,
     pub prices: VecDeque<f64>,
+    pub bars: BarSet,
+    pub features: FeaturePipeline,
+    pub whales: WhaleDetector,
 }
 
 impl MarketMicrostructure {
     pub fn new() -> Self {
+        let features = FeaturePipeline::new(FeatureConfig::from_env());
+        let mut bars = BarSet::from_env();
+        bars.ensure(features.config.bar);
         Self {
             bids: VecDeque::new(),
             asks: VecDeque::new(),
             prices: VecDeque::new(),
+            bars,
+            features,
+            whales: WhaleDetector::new(WhaleConfig::from_env()),
         }
     }
 
-    pub fn update(&mut self, trade: &TradeData) {
+    /// Adds a tick and returns the bars it closed, already fed to the indicators.
+    pub fn update(&mut self, trade: &TradeData) -> Vec<Bar> {
         if trade.is_buyer_maker {
             self.asks.push_back(trade.quantity);
             self.bids.push_back(0.0);
@@ -40,12 +127,30 @@ impl MarketMicrostructure {
             self.asks.push_back(0.0);
         }
         self.prices.push_back(trade.price);
+        self.features.update(trade);
+        self.whales.update(trade);
 
-        if self.bids.len() > 100 {
+        if self.bids.len() > PRICE_WINDOW {
             self.bids.pop_front();
             self.asks.pop_front();
             self.prices.pop_front();
         }
+
+        let bars = self.bars.update(trade);
+        for bar in &bars {
+            self.features.on_bar(bar);
+        }
+        bars
+    }
+
+    /// BTC traded in the OFI window.
+    pub fn ofi_volume(&self) -> f64 {
+        self.bids.iter().sum::<f64>() + self.asks.iter().sum::<f64>()
+    }
+
+    /// OFI over a window this thin is one or two trades, not flow.
+    pub fn ofi_trusted(&self) -> bool {
+        self.ofi_volume() >= self.whales.config.ofi_min_volume
     }
 
     pub fn calculate_ofi(&self) -> f64 {
@@ -58,67 +163,78 @@ impl MarketMicrostructure {
 }
 
 impl QuantumSignal {
-    pub fn analyze(market: &MarketMicrostructure, current_price: f64, volatility: f64) -> Self {
+    /// Runs the Monte Carlo on paths drawn from `rng`: the same seed and ticks give the same signal.
+    pub fn analyze(market: &MarketMicrostructure, current_price: f64, volatility: f64, params: &SignalParams, rng: &mut StdRng) -> Self {
         let ofi = market.calculate_ofi();
         
-        // Monte Carlo
-        let paths = 10_000;
-        let results: Vec<f64> = (0..paths)
+        // Monte Carlo: one draw seeds every chunk, so threads cannot reorder the randomness
+        let paths = params.mc_paths;
+        let base: u64 = rng.random();
+        let up_moves: usize = (0..paths.div_ceil(MC_CHUNK))
             .into_par_iter()
-            .map(|_| {
-                let mut rng = rand::rng();
-                let mut price = current_price;
-                for _ in 0..60 {
-                    price += volatility * rng.random_range(-1.0..1.0); 
-                }
-                price
+            .map(|chunk| {
+                let mut rng = StdRng::seed_from_u64(base.wrapping_add(chunk as u64));
+                let len = MC_CHUNK.min(paths - chunk * MC_CHUNK);
+                (0..len)
+                    .filter(|_| {
+                        let mut price = current_price;
+                        for _ in 0..60 {
+                            price += volatility * rng.random_range(-1.0..1.0);
+                        }
+                        price > current_price
+                    })
+                    .count()
             })
-            .collect();
-
-        let up_moves = results.iter().filter(|&&p| p > current_price).count();
+            .sum();
         let prob_up = up_moves as f64 / paths as f64;
         
-        let (direction, raw_confidence) = if prob_up > 0.70 {
+        let (direction, raw_confidence) = if prob_up > params.prob_cutoff {
             ("UP", prob_up * 100.0)
-        } else if prob_up < 0.30 {
+        } else if prob_up < 1.0 - params.prob_cutoff {
             ("DOWN", (1.0 - prob_up) * 100.0)
         } else {
             ("NEUTRAL", 50.0)
         };
 
-        // Whale Confirmation
-        let is_whale_confirmed = match direction {
-            "UP" => ofi > 0.2,   
-            "DOWN" => ofi < -0.2, 
+        // Whale Confirmation: OFI over enough volume, backed by large trades on the same side
+        // (OFI alone on a feed without trade sizes, see `WhaleDetector::has_sizes`)
+        let ofi_agrees = match direction {
+            "UP" => ofi > params.ofi_threshold,
+            "DOWN" => ofi < -params.ofi_threshold,
             _ => false,
         };
+        let whales_agree = !market.whales.has_sizes() || market.whales.confirms(direction);
+        let is_whale_confirmed = ofi_agrees && market.ofi_trusted() && whales_agree;
+        let is_feature_confirmed = market.features.confirms(direction, current_price);
 
         Self {
             confidence: raw_confidence,
             direction: direction.to_string(),
             is_whale_confirmed,
+            prob_up,
+            ofi,
+            features: market.features.snapshot(),
+            is_feature_confirmed,
         }
     }
 
-    // --- NEW: PHASE 5 RISK ENGINE ---
-    #[allow(dead_code)]
-    pub fn calculate_stake(&self) -> String {
+    /// Stake as % of balance: tenth Kelly on an 85% binary payout, capped at 1-5%,
+    /// and a flat 1% under 80% confidence.
+    pub fn calculate_stake(&self) -> f64 {
+        if self.confidence < 80.0 {
+            return 1.0; // Flat risk for lower confidence
+        }
+
         // Standard Binary Options Payout (85%)
-        let b = 0.85; 
+        let b = 0.85;
         let p = self.confidence / 100.0;
         let q = 1.0 - p;
-        
+
         // Kelly Formula: (bp - q) / b
         let raw_kelly = ((b * p) - q) / b;
-        
+
         // Safety: Use "Tenth Kelly" to minimize Drawdown
         // Max Bet Cap: 5% of account
-        let safe_stake = (raw_kelly * 0.10).max(0.01).min(0.05);
-        
-        if self.confidence < 80.0 {
-            return "1.0% (Min)".to_string(); // Flat risk for lower confidence
-        }
-
-        format!("{:.1}%", safe_stake * 100.0)
+        (raw_kelly * 0.10).clamp(0.01, 0.05) * 100.0
     }
 } 
\ No newline at end of file
@@ -0,0 +1,248 @@
+use std::time::Duration;
+use chrono::Utc;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use log::info;
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+use crate::config::env_or;
+use crate::message::Format;
+use crate::notify::{DeliveryError, Notification, Notifier};
+
+/// Sends a prepared request and maps the response onto retry semantics.
+async fn send(req: RequestBuilder) -> Result<(), DeliveryError> {
+    let resp = req.timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| DeliveryError::Transient(e.to_string()))?;
+    let status = resp.status();
+    if status.is_success() { return Ok(()); }
+
+    let header_wait = resp.headers().get("retry-after")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<f64>().ok());
+    let body = resp.text().await.unwrap_or_default();
+    match status.as_u16() {
+        429 => {
+            // Discord puts a float `retry_after` (seconds) in the body; others use the header
+            let body_wait = serde_json::from_str::<Value>(&body).ok()
+                .and_then(|v| v.get("retry_after").and_then(Value::as_f64));
+            let secs = body_wait.or(header_wait).unwrap_or(1.0).max(0.1);
+            Err(DeliveryError::RateLimited(Duration::from_secs_f64(secs)))
+        }
+        code if code >= 500 => Err(DeliveryError::Transient(format!("{}: {}", status, body))),
+        _ => Err(DeliveryError::Rejected(format!("{}: {}", status, body))),
+    }
+}
+
+/// Discord channel webhook (`DISCORD_WEBHOOK_URL`).
+pub struct DiscordNotifier {
+    client: Client,
+    url: String,
+}
+
+impl DiscordNotifier {
+    pub fn from_env() -> Option<Self> {
+        let url = env_or("DISCORD_WEBHOOK_URL", String::new());
+        (!url.is_empty()).then(|| Self { client: Client::new(), url })
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    fn format(&self) -> Format {
+        Format::Discord
+    }
+
+    fn max_len(&self) -> usize {
+        1900 // Discord caps content at 2000
+    }
+
+    async fn deliver(&self, n: &Notification) -> Result<(), DeliveryError> {
+        let body = json!({
+            "content": n.message.render(Format::Discord),
+            "allowed_mentions": { "parse": [] }, // Never ping @everyone from dynamic text
+        });
+        send(self.client.post(&self.url).json(&body)).await
+    }
+}
+
+/// Slack incoming webhook (`SLACK_WEBHOOK_URL`).
+pub struct SlackNotifier {
+    client: Client,
+    url: String,
+}
+
+impl SlackNotifier {
+    pub fn from_env() -> Option<Self> {
+        let url = env_or("SLACK_WEBHOOK_URL", String::new());
+        (!url.is_empty()).then(|| Self { client: Client::new(), url })
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    fn format(&self) -> Format {
+        Format::Slack
+    }
+
+    async fn deliver(&self, n: &Notification) -> Result<(), DeliveryError> {
+        let body = json!({ "text": n.message.render(Format::Slack) });
+        send(self.client.post(&self.url).json(&body)).await
+    }
+}
+
+/// Generic JSON webhook (`WEBHOOK_URL`, optional `WEBHOOK_TOKEN` sent as a bearer token).
+/// One POST per notification, never coalesced, so receivers can dispatch on `event`.
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+    token: Option<String>,
+}
+
+impl WebhookNotifier {
+    pub fn from_env() -> Option<Self> {
+        let url = env_or("WEBHOOK_URL", String::new());
+        let token = Some(env_or("WEBHOOK_TOKEN", String::new())).filter(|t| !t.is_empty());
+        (!url.is_empty()).then(|| Self { client: Client::new(), url, token })
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn format(&self) -> Format {
+        Format::Plain
+    }
+
+    fn max_len(&self) -> usize {
+        usize::MAX
+    }
+
+    fn coalesce(&self) -> bool {
+        false
+    }
+
+    async fn deliver(&self, n: &Notification) -> Result<(), DeliveryError> {
+        let body = json!({
+            "ts_utc": Utc::now(),
+            "event": n.event,
+            "critical": n.critical,
+            "title": n.message.title(),
+            "text": n.message.render(Format::Plain),
+            "blocks": n.message.blocks,
+        });
+        let mut req = self.client.post(&self.url).json(&body);
+        if let Some(token) = &self.token {
+            req = req.bearer_auth(token);
+        }
+        send(req).await
+    }
+}
+
+/// Plain-text email over SMTP. Configured by `SMTP_HOST`, `SMTP_PORT`, `SMTP_USER`,
+/// `SMTP_PASS`, `SMTP_FROM`, `SMTP_TO` (comma-separated) and `SMTP_TLS` (starttls | tls | none).
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    /// `Ok(None)` when `SMTP_HOST` is unset; `Err` when it is set but the rest is unusable.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let host = env_or("SMTP_HOST", String::new());
+        if host.is_empty() { return Ok(None); }
+
+        let mut builder = match env_or("SMTP_TLS", "starttls".to_string()).to_lowercase().as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?,
+        };
+        let port: u16 = env_or("SMTP_PORT", 0);
+        if port > 0 {
+            builder = builder.port(port);
+        }
+        let user = env_or("SMTP_USER", String::new());
+        if !user.is_empty() {
+            builder = builder.credentials(Credentials::new(user, env_or("SMTP_PASS", String::new())));
+        }
+
+        let from: Mailbox = env_or("SMTP_FROM", String::new()).parse()?;
+        let to = env_or("SMTP_TO", String::new())
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| s.trim().parse())
+            .collect::<Result<Vec<Mailbox>, _>>()?;
+        if to.is_empty() { anyhow::bail!("SMTP_TO is empty"); }
+
+        Ok(Some(Self { transport: builder.timeout(Some(Duration::from_secs(20))).build(), from, to }))
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    fn format(&self) -> Format {
+        Format::Plain
+    }
+
+    fn max_len(&self) -> usize {
+        100_000
+    }
+
+    async fn deliver(&self, n: &Notification) -> Result<(), DeliveryError> {
+        let mut builder = lettre::Message::builder()
+            .from(self.from.clone())
+            .subject(format!("[Trading Bot] {}", n.message.title().unwrap_or("Notification")));
+        for to in &self.to {
+            builder = builder.to(to.clone());
+        }
+        let email = builder.body(n.message.render(Format::Plain))
+            .map_err(|e| DeliveryError::Rejected(e.to_string()))?;
+
+        match self.transport.send(email).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.is_permanent() => Err(DeliveryError::Rejected(e.to_string())),
+            Err(e) => Err(DeliveryError::Transient(e.to_string())),
+        }
+    }
+}
+
+/// Writes alerts to the log. The fallback when no other backend is configured,
+/// or alongside them with `NOTIFY_STDOUT=true`.
+pub struct StdoutNotifier;
+
+impl Notifier for StdoutNotifier {
+    fn name(&self) -> &'static str {
+        "stdout"
+    }
+
+    fn format(&self) -> Format {
+        Format::Plain
+    }
+
+    fn min_interval(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn coalesce(&self) -> bool {
+        false
+    }
+
+    async fn deliver(&self, n: &Notification) -> Result<(), DeliveryError> {
+        info!("📣 [{:?}] {}", n.event, n.message.render(Format::Plain).replace('\n', " | "));
+        Ok(())
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use crate::config::env_or;
+use crate::message::{Format, Message};
+use crate::notifiers::{DiscordNotifier, EmailNotifier, SlackNotifier, StdoutNotifier, WebhookNotifier};
+use crate::telegram::TelegramBot;
+
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+const MAX_ATTEMPTS: u32 = 8; // Normal messages only; critical ones retry until delivered
+const MAX_QUEUED_PER_TARGET: usize = 200;
+
+/// What a notification is about. Backends subscribe to a subset via `<NAME>_EVENTS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    System,     // Startup and infrastructure problems
+    Signal,
+    Settlement,
+    Halt,       // Kill switch
+    Report,     // Periodic performance reports
+    Reply,      // Answer to an operator command; goes only to the backend it came from
+    Rejection,  // Order stopped by the pre-trade checks
+}
+
+impl EventKind {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.trim().to_lowercase().as_str() {
+            "system" => EventKind::System,
+            "signal" | "signals" => EventKind::Signal,
+            "settlement" | "settlements" | "trade" | "trades" => EventKind::Settlement,
+            "halt" | "risk" => EventKind::Halt,
+            "report" | "reports" => EventKind::Report,
+            "rejection" | "rejections" | "rejected" => EventKind::Rejection,
+            _ => return None,
+        })
+    }
+}
+
+/// One message on its way to one backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub event: EventKind,
+    pub target: Option<String>, // Backend-specific recipient (e.g. a Telegram chat); None = its default
+    pub message: Message,
+    pub critical: bool,
+    #[serde(skip)]
+    attempts: u32,
+}
+
+impl Notification {
+    pub fn new(event: EventKind, target: Option<String>, message: Message, critical: bool) -> Self {
+        Self { event, target, message, critical, attempts: 0 }
+    }
+}
+
+/// Why a send did not go through.
+#[derive(Debug)]
+pub enum DeliveryError {
+    RateLimited(Duration), // Wait this long before the next send
+    Transient(String),     // Network or 5xx; worth retrying
+    Rejected(String),      // Retrying the same payload will not help
+}
+
+/// An alert backend. Implementations make one delivery attempt; queueing, retries,
+/// pacing and coalescing are handled by the worker `Alerts` runs for each backend.
+pub trait Notifier: Send + Sync + 'static {
+    /// Lowercase name, also the env prefix for `<NAME>_EVENTS` and `<NAME>_MIN_INTERVAL_MS`.
+    fn name(&self) -> &'static str;
+
+    fn format(&self) -> Format;
+
+    /// Largest rendered message the backend accepts; coalescing stays under it.
+    fn max_len(&self) -> usize {
+        4000
+    }
+
+    /// Default spacing between sends to one recipient; `<NAME>_MIN_INTERVAL_MS` overrides it.
+    fn min_interval(&self) -> Duration {
+        Duration::from_millis(1100)
+    }
+
+    /// Whether queued messages may be merged into one send.
+    fn coalesce(&self) -> bool {
+        true
+    }
+
+    fn deliver(&self, notification: &Notification) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+}
+
+/// Shared handle to every configured backend. Sending never waits on the network.
+#[derive(Clone)]
+pub struct Alerts {
+    routes: Arc<Vec<Route>>,
+}
+
+struct Route {
+    name: &'static str,
+    events: Option<HashSet<EventKind>>, // None = everything
+    tx: mpsc::UnboundedSender<Notification>,
+}
+
+impl Alerts {
+    /// Builds the backends configured in the environment. Telegram comes in as a bot so
+    /// the command poller can share it. With nothing configured, alerts go to stdout.
+    pub fn from_env(state_dir: &str, telegram: Option<TelegramBot>) -> Self {
+        let mut routes = Vec::new();
+
+        if let Some(bot) = telegram { routes.push(spawn_route(bot, state_dir)); }
+        if let Some(n) = DiscordNotifier::from_env() { routes.push(spawn_route(n, state_dir)); }
+        if let Some(n) = SlackNotifier::from_env() { routes.push(spawn_route(n, state_dir)); }
+        if let Some(n) = WebhookNotifier::from_env() { routes.push(spawn_route(n, state_dir)); }
+        match EmailNotifier::from_env() {
+            Ok(Some(n)) => routes.push(spawn_route(n, state_dir)),
+            Ok(None) => {}
+            Err(e) => error!("EMAIL ALERTS DISABLED: {}", e),
+        }
+        if routes.is_empty() || env_or("NOTIFY_STDOUT", false) {
+            routes.push(spawn_route(StdoutNotifier, state_dir));
+        }
+
+        for r in &routes {
+            let events = r.events.as_ref()
+                .map(|e| e.iter().map(|k| format!("{:?}", k)).collect::<Vec<_>>().join(","))
+                .unwrap_or_else(|| "all".to_string());
+            info!("📣 ALERT BACKEND: {} ({})", r.name, events);
+        }
+        Self { routes: Arc::new(routes) }
+    }
+
+    /// Routes a message to every backend subscribed to `event`.
+    pub fn send(&self, event: EventKind, message: Message) {
+        self.dispatch(event, message, false);
+    }
+
+    /// Like `send`, but retried without limit and persisted until delivered.
+    pub fn critical(&self, event: EventKind, message: Message) {
+        self.dispatch(event, message, true);
+    }
+
+    /// Answers a command on the backend it arrived on.
+    pub fn reply(&self, backend: &str, target: &str, message: Message) {
+        let Some(route) = self.routes.iter().find(|r| r.name == backend) else { return };
+        let n = Notification::new(EventKind::Reply, Some(target.to_string()), message, false);
+        if route.tx.send(n).is_err() {
+            error!("{} NOTIFIER STOPPED: Reply lost", route.name.to_uppercase());
+        }
+    }
+
+    fn dispatch(&self, event: EventKind, message: Message, critical: bool) {
+        for route in self.routes.iter().filter(|r| r.events.as_ref().is_none_or(|e| e.contains(&event))) {
+            let n = Notification::new(event, None, message.clone(), critical);
+            if route.tx.send(n).is_err() {
+                error!("{} NOTIFIER STOPPED: Message lost", route.name.to_uppercase());
+            }
+        }
+    }
+}
+
+fn spawn_route<N: Notifier>(backend: N, state_dir: &str) -> Route {
+    let name = backend.name();
+    let prefix = name.to_uppercase();
+    let events = env_or(&format!("{}_EVENTS", prefix), "all".to_string());
+    let events = if events.trim().eq_ignore_ascii_case("all") {
+        None
+    } else {
+        Some(events.split(',').filter_map(EventKind::parse).collect())
+    };
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let worker = DeliveryWorker {
+        min_interval: Duration::from_millis(env_or(&format!("{}_MIN_INTERVAL_MS", prefix), backend.min_interval().as_millis() as u64)),
+        outbox: PathBuf::from(state_dir).join(format!("notify_outbox_{}.json", name)),
+        backend,
+        targets: HashMap::new(),
+        global_pause: Instant::now(),
+    };
+    tokio::spawn(worker.run(rx));
+    Route { name, events, tx }
+}
+
+/// Per-recipient queue and pacing.
+struct TargetQueue {
+    queue: VecDeque<Notification>,
+    next_send: Instant,
+}
+
+struct DeliveryWorker<N: Notifier> {
+    backend: N,
+    outbox: PathBuf,
+    targets: HashMap<Option<String>, TargetQueue>,
+    min_interval: Duration, // Per-recipient spacing between sends
+    global_pause: Instant,  // Set by rate-limit responses
+}
+
+impl<N: Notifier> DeliveryWorker<N> {
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<Notification>) {
+        let restored = self.load_outbox();
+        if restored > 0 {
+            info!("📬 {}: {} undelivered critical alerts restored", self.backend.name().to_uppercase(), restored);
+        }
+
+        loop {
+            let wake = self.next_due();
+            tokio::select! {
+                n = rx.recv() => match n {
+                    Some(n) => self.enqueue(n),
+                    None => return,
+                },
+                _ = sleep_until(wake) => self.send_due().await,
+            }
+        }
+    }
+
+    fn enqueue(&mut self, n: Notification) {
+        let critical = n.critical;
+        let target = self.targets.entry(n.target.clone()).or_insert_with(|| TargetQueue {
+            queue: VecDeque::new(),
+            next_send: Instant::now(),
+        });
+        target.queue.push_back(n);
+
+        // Bound memory during long outages by shedding the oldest routine messages
+        while target.queue.len() > MAX_QUEUED_PER_TARGET {
+            match target.queue.iter().position(|m| !m.critical) {
+                Some(i) => {
+                    target.queue.remove(i);
+                    warn!("⚠️ {} BACKLOG FULL: Dropped oldest message", self.backend.name().to_uppercase());
+                }
+                None => break,
+            }
+        }
+        if critical { self.save_outbox(); }
+    }
+
+    /// Earliest moment any recipient may be sent to, or None when everything is delivered.
+    fn next_due(&self) -> Option<Instant> {
+        self.targets.values()
+            .filter(|t| !t.queue.is_empty())
+            .map(|t| t.next_send.max(self.global_pause))
+            .min()
+    }
+
+    async fn send_due(&mut self) {
+        let now = Instant::now();
+        if now < self.global_pause { return; }
+        let due: Vec<Option<String>> = self.targets.iter()
+            .filter(|(_, t)| !t.queue.is_empty() && t.next_send <= now)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for target in due {
+            let Some(batch) = self.coalesce(&target) else { continue };
+            let result = self.backend.deliver(&batch).await;
+            self.settle(&target, batch, result);
+            if Instant::now() < self.global_pause { break; }
+        }
+    }
+
+    /// Merges routine messages waiting for a recipient into as few as fit the size limit,
+    /// so a backlog drains in a few sends instead of one per alert. Critical alerts always
+    /// go alone: a rejected batch is dropped, and it must not take one down with it.
+    fn coalesce(&mut self, target: &Option<String>) -> Option<Notification> {
+        let format = self.backend.format();
+        let max_len = self.backend.max_len();
+        let enabled = self.backend.coalesce();
+        let queue = &mut self.targets.get_mut(target)?.queue;
+
+        let mut merged = queue.pop_front()?;
+        let mut len = merged.message.render(format).len();
+        // Images travel alone; merging would drop or misplace them
+        let enabled = enabled && !merged.critical && merged.message.image.is_none();
+        while let Some(next) = queue.front().filter(|n| enabled && !n.critical && n.message.image.is_none()) {
+            let next_len = next.message.render(format).len();
+            if len + next_len + 2 > max_len { break; }
+            let next = queue.pop_front()?;
+            merged.message.append(next.message);
+            merged.attempts = merged.attempts.max(next.attempts);
+            len += next_len + 2;
+        }
+        Some(merged)
+    }
+
+    fn settle(&mut self, target: &Option<String>, mut n: Notification, result: Result<(), DeliveryError>) {
+        let name = self.backend.name().to_uppercase();
+        let now = Instant::now();
+        let mut next_send = now + self.min_interval;
+        let requeue = match result {
+            Ok(()) => false,
+            Err(DeliveryError::RateLimited(wait)) => {
+                warn!("⚠️ {} RATE LIMITED: Backing off {}s", name, wait.as_secs());
+                self.global_pause = now + wait;
+                true // Rate limiting is not the message's fault
+            }
+            Err(DeliveryError::Rejected(reason)) => {
+                // Resending the same payload would block the queue forever
+                error!("{} REJECTED MESSAGE ({}): {:?}", name, reason, n.message.title());
+                false
+            }
+            Err(DeliveryError::Transient(reason)) => {
+                n.attempts += 1;
+                if !n.critical && n.attempts >= MAX_ATTEMPTS {
+                    error!("{} DELIVERY GAVE UP after {} attempts ({}): {:?}", name, n.attempts, reason, n.message.title());
+                    false
+                } else {
+                    let backoff = self.min_interval.saturating_mul(1 << n.attempts.min(16)).min(MAX_BACKOFF);
+                    warn!("⚠️ {} SEND FAILED (attempt {}), retrying in {}s: {}", name, n.attempts, backoff.as_secs(), reason);
+                    next_send = now + backoff;
+                    true
+                }
+            }
+        };
+
+        let critical = n.critical;
+        if let Some(t) = self.targets.get_mut(target) {
+            t.next_send = next_send;
+            if requeue { t.queue.push_front(n); }
+        }
+        // The outbox tracks queued critical alerts; drop delivered or abandoned ones from it
+        if critical && !requeue { self.save_outbox(); }
+    }
+
+    fn load_outbox(&mut self) -> usize {
+        let Ok(raw) = fs::read_to_string(&self.outbox) else { return 0 };
+        let pending: Vec<Notification> = match serde_json::from_str(&raw) {
+            Ok(p) => p,
+            Err(e) => {
+                error!("CORRUPT NOTIFIER OUTBOX {}, IGNORING: {}", self.outbox.display(), e);
+                return 0;
+            }
+        };
+        let count = pending.len();
+        for n in pending {
+            self.enqueue(n);
+        }
+        count
+    }
+
+    /// Rewrites the outbox with the critical alerts still queued.
+    fn save_outbox(&self) {
+        let pending: Vec<&Notification> = self.targets.values().flat_map(|t| t.queue.iter()).filter(|n| n.critical).collect();
+        let result = serde_json::to_string(&pending)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| {
+                if let Some(dir) = self.outbox.parent() { fs::create_dir_all(dir)?; }
+                let tmp = self.outbox.with_extension("json.tmp");
+                fs::write(&tmp, json)?;
+                fs::rename(&tmp, &self.outbox)?;
+                Ok(())
+            });
+        if let Err(e) = result {
+            error!("FAILED TO PERSIST NOTIFIER OUTBOX: {}", e);
+        }
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use crate::message::MessageBuilder;
+
+    /// Rejects everything it is handed, keeping what it saw.
+    struct Rejecting(Arc<Mutex<Vec<Notification>>>);
+
+    impl Notifier for Rejecting {
+        fn name(&self) -> &'static str {
+            "rejecting"
+        }
+
+        fn format(&self) -> Format {
+            Format::Plain
+        }
+
+        async fn deliver(&self, n: &Notification) -> Result<(), DeliveryError> {
+            self.0.lock().unwrap().push(n.clone());
+            Err(DeliveryError::Rejected("bad payload".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn critical_alerts_are_never_merged() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let dir = std::env::temp_dir().join(format!("notify-{}", std::process::id()));
+        let mut worker = DeliveryWorker {
+            backend: Rejecting(seen.clone()),
+            outbox: dir.join("outbox.json"),
+            targets: HashMap::new(),
+            min_interval: Duration::ZERO,
+            global_pause: Instant::now(),
+        };
+        let msg = |text: &str| MessageBuilder::new().line(text).build();
+        worker.enqueue(Notification::new(EventKind::Settlement, None, msg("r1"), false));
+        worker.enqueue(Notification::new(EventKind::Settlement, None, msg("r2"), false));
+        worker.enqueue(Notification::new(EventKind::Halt, None, msg("halt"), true));
+        worker.enqueue(Notification::new(EventKind::Halt, None, msg("halt again"), true));
+        worker.enqueue(Notification::new(EventKind::Settlement, None, msg("r3"), false));
+        while worker.next_due().is_some() { worker.send_due().await; }
+
+        let sent: Vec<(String, bool)> = seen.lock().unwrap().iter()
+            .map(|n| (n.message.render(Format::Plain), n.critical))
+            .collect();
+        assert_eq!(sent, vec![
+            ("r1\n\nr2".to_string(), false),
+            ("halt".to_string(), true),
+            ("halt again".to_string(), true),
+            ("r3".to_string(), false),
+        ]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use crate::config::env_or;
+use crate::executor::{trade_id_of, ExecError, ExecutionReport, Executor, OrderAck, OrderRequest, OrderState, Side};
+use crate::fills::Liquidity;
+
+// Finished orders kept for /positions and late duplicate reports
+const MAX_FINISHED: usize = 200;
+
+/// Lifecycle of an order as the engine sees it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OmsState {
+    New, // Sent, not yet acknowledged
+    Acked,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+}
+
+impl OmsState {
+    fn from_venue(state: OrderState) -> Self {
+        match state {
+            OrderState::New => OmsState::Acked,
+            OrderState::PartiallyFilled => OmsState::PartiallyFilled,
+            OrderState::Filled => OmsState::Filled,
+            OrderState::Canceled | OrderState::Expired => OmsState::Cancelled,
+            OrderState::Rejected => OmsState::Rejected,
+        }
+    }
+
+    pub fn is_final(&self) -> bool {
+        matches!(self, OmsState::Filled | OmsState::Cancelled | OmsState::Rejected)
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            OmsState::New => 0,
+            OmsState::Acked => 1,
+            OmsState::PartiallyFilled => 2,
+            _ => 3,
+        }
+    }
+
+    /// Orders only move forward; updates arriving out of order cannot undo a fill.
+    fn can_become(&self, next: OmsState) -> bool {
+        !self.is_final() && next.rank() >= self.rank()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OmsState::New => "NEW",
+            OmsState::Acked => "ACKED",
+            OmsState::PartiallyFilled => "PARTIALLY FILLED",
+            OmsState::Filled => "FILLED",
+            OmsState::Cancelled => "CANCELLED",
+            OmsState::Rejected => "REJECTED",
+        }
+    }
+}
+
+/// Timeouts, read from `OMS_ACK_TIMEOUT_SECS` (10), `OMS_STALE_SECS` (30) and
+/// `OMS_RECONCILE_SECS` (60).
+#[derive(Debug, Clone, Copy)]
+pub struct OmsConfig {
+    pub ack_timeout: Duration,   // New orders with no ack are looked up, then written off
+    pub stale_after: Duration,   // Working orders older than this are cancelled
+    pub reconcile_every: Duration,
+}
+
+impl OmsConfig {
+    pub fn from_env() -> Self {
+        Self {
+            ack_timeout: Duration::seconds(env_or("OMS_ACK_TIMEOUT_SECS", 10)),
+            stale_after: Duration::seconds(env_or("OMS_STALE_SECS", 30)),
+            reconcile_every: Duration::seconds(env_or("OMS_RECONCILE_SECS", 60)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ManagedOrder {
+    pub request: OrderRequest,
+    pub state: OmsState,
+    pub exchange_order_id: Option<i64>,
+    pub filled_qty: f64,
+    pub avg_price: f64,
+    pub fees: f64,
+    pub liquidity: Option<Liquidity>, // Of the latest stream-reported execution
+    pub streamed_qty: f64,            // Quantity the stream has reported; its fees are in `fees`
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub cancel_sent: bool,
+}
+
+impl ManagedOrder {
+    pub fn side(&self) -> Side {
+        self.request.side()
+    }
+}
+
+/// Sits between the engine and an executor: tracks every order from submission to a
+/// final state, cancels stale ones and reconciles with the venue after stream gaps.
+pub struct OrderManager<E: Executor> {
+    executor: E,
+    config: OmsConfig,
+    orders: HashMap<String, ManagedOrder>,
+    updates: Vec<ExecutionReport>,
+    last_reconcile: DateTime<Utc>,
+    pub position: f64, // Net filled base quantity, + long / - short
+}
+
+impl<E: Executor> OrderManager<E> {
+    pub fn new(executor: E, config: OmsConfig) -> Self {
+        Self { executor, config, orders: HashMap::new(), updates: Vec::new(), last_reconcile: Utc::now(), position: 0.0 }
+    }
+
+    pub fn venue(&self) -> &'static str {
+        self.executor.name()
+    }
+
+    /// Orders that have not reached a final state, oldest first.
+    pub fn open_orders(&self) -> Vec<&ManagedOrder> {
+        let mut open: Vec<_> = self.orders.values().filter(|o| !o.state.is_final()).collect();
+        open.sort_by_key(|o| o.created);
+        open
+    }
+
+    /// Base quantity still to trade for the venue position to reach `target` (+ long /
+    /// - short). None while an order is working, since its fills are not counted yet.
+    pub fn gap_to(&self, target: f64) -> Option<f64> {
+        if self.orders.values().any(|o| !o.state.is_final()) { return None; }
+        Some(target - self.position)
+    }
+
+    /// Registers and sends an order. A lost response is resolved with a status query;
+    /// if that fails too the order stays New until the ack timeout.
+    pub async fn submit(&mut self, request: OrderRequest) -> Result<OrderAck, ExecError> {
+        let cid = request.client_order_id();
+        if self.orders.contains_key(&cid) {
+            return Err(ExecError::Rejected { code: 0, msg: format!("duplicate client order id {}", cid) });
+        }
+        let now = Utc::now();
+        self.orders.insert(cid.clone(), ManagedOrder {
+            request: request.clone(),
+            state: OmsState::New,
+            exchange_order_id: None,
+            filled_qty: 0.0,
+            avg_price: 0.0,
+            fees: 0.0,
+            liquidity: None,
+            streamed_qty: 0.0,
+            created: now,
+            updated: now,
+            cancel_sent: false,
+        });
+
+        let mut result = self.executor.submit(&request).await;
+        if let Err(ExecError::Unknown(e)) = &result {
+            warn!("⚠️ ORDER {} OUTCOME UNKNOWN ({}), querying status", cid, e);
+            let lost = ExecError::Unknown(e.clone());
+            result = match self.executor.status(&cid).await {
+                Ok(ack) => Ok(ack),
+                Err(_) => Err(lost),
+            };
+        }
+        match &result {
+            Ok(ack) => self.apply_ack(ack, None),
+            Err(ExecError::Unknown(_)) => {}
+            Err(e) => self.finish(&cid, OmsState::Rejected, Some(e.to_string())),
+        }
+        result
+    }
+
+    /// Applies stream updates, runs timeouts and reconciles when due. Returns every
+    /// change since the last call, including fills inferred from status queries.
+    pub async fn poll(&mut self, now: DateTime<Utc>) -> Vec<ExecutionReport> {
+        for report in self.executor.drain_reports() {
+            self.apply_report(report);
+        }
+
+        let gap = self.executor.take_reconnected();
+        if gap || now - self.last_reconcile > self.config.reconcile_every {
+            self.reconcile(gap).await;
+            self.last_reconcile = now;
+        }
+
+        for cid in self.open_ids() {
+            let Some(order) = self.orders.get(&cid) else { continue };
+            let age = now - order.created;
+            match order.state {
+                OmsState::New if age > self.config.ack_timeout => match self.executor.status(&cid).await {
+                    Ok(ack) => self.apply_ack(&ack, None),
+                    Err(ExecError::NotFound) => self.finish(&cid, OmsState::Rejected,
+                        Some(format!("no ack after {}s", self.config.ack_timeout.num_seconds()))),
+                    Err(e) => warn!("⚠️ STATUS {} FAILED: {}", cid, e),
+                },
+                OmsState::Acked | OmsState::PartiallyFilled if age > self.config.stale_after && !order.cancel_sent => {
+                    info!("⌛ ORDER {} STALE after {}s, cancelling", cid, age.num_seconds());
+                    if let Some(o) = self.orders.get_mut(&cid) { o.cancel_sent = true; }
+                    match self.executor.cancel(&cid).await {
+                        Ok(ack) => self.apply_ack(&ack, Some("stale".to_string())),
+                        // Already done: the stream or the next reconcile will say how
+                        Err(ExecError::NotFound) => {}
+                        Err(e) => {
+                            warn!("⚠️ CANCEL {} FAILED: {}", cid, e);
+                            if let Some(o) = self.orders.get_mut(&cid) { o.cancel_sent = false; }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.prune();
+        std::mem::take(&mut self.updates)
+    }
+
+    /// Queries every open order. After a stream gap (`gap`), the venue's answer wins
+    /// over whatever we last heard.
+    async fn reconcile(&mut self, gap: bool) {
+        let open = self.open_ids();
+        if open.is_empty() { return; }
+        if gap {
+            info!("🔄 RECONCILING {} OPEN ORDERS after stream (re)connect", open.len());
+        }
+        for cid in open {
+            match self.executor.status(&cid).await {
+                Ok(ack) => self.apply_ack(&ack, gap.then(|| "reconciled".to_string())),
+                Err(ExecError::NotFound) if self.orders.get(&cid).is_some_and(|o| o.state == OmsState::New) => {
+                    // Never reached the venue; the ack timeout writes it off
+                }
+                Err(e) => warn!("⚠️ RECONCILE {} FAILED: {}", cid, e),
+            }
+        }
+    }
+
+    fn open_ids(&self) -> Vec<String> {
+        self.open_orders().iter().map(|o| o.request.client_order_id()).collect()
+    }
+
+    /// Folds a stream update into the order book. Updates for unknown orders (placed by
+    /// a previous session or by hand) are passed through untouched.
+    ///
+    /// Fees and liquidity only come from the stream, so they are folded for every new
+    /// execution, even one a REST ack already counted into the position. Such a report is
+    /// passed on with `last_qty` cut to the part not reported before.
+    fn apply_report(&mut self, mut report: ExecutionReport) {
+        let Some(order) = self.orders.get_mut(&report.client_order_id) else {
+            warn!("⚠️ UPDATE FOR UNTRACKED ORDER {} ({})", report.client_order_id, report.state.label());
+            self.updates.push(report);
+            return;
+        };
+        let next = OmsState::from_venue(report.state);
+        let new_qty = report.cum_qty - order.filled_qty;
+        let new_exec = report.cum_qty - order.streamed_qty > 1e-12;
+        if new_qty <= 0.0 && !new_exec && (next == order.state || !order.state.can_become(next)) {
+            return; // Duplicate or stale update
+        }
+
+        if new_exec {
+            order.streamed_qty = report.cum_qty;
+            order.fees += report.fee;
+            order.liquidity = report.liquidity.or(order.liquidity);
+        }
+        if new_qty > 0.0 {
+            self.position += if order.side() == Side::Buy { new_qty } else { -new_qty };
+            order.filled_qty = report.cum_qty;
+            order.avg_price = report.avg_price;
+        }
+        report.last_qty = report.last_qty.min(new_qty.max(0.0));
+        if order.state.can_become(next) {
+            transition(order, next);
+        }
+        order.exchange_order_id = order.exchange_order_id.or(report.exchange_order_id);
+        order.updated = Utc::now();
+        self.updates.push(report);
+    }
+
+    /// Folds a REST answer (place, cancel, status) into the book. Fills the stream has not
+    /// reported yet are emitted as synthetic reports so nothing is missed.
+    fn apply_ack(&mut self, ack: &OrderAck, note: Option<String>) {
+        let Some(order) = self.orders.get_mut(&ack.client_order_id) else { return };
+        let next = OmsState::from_venue(ack.state);
+        let new_qty = ack.filled_qty - order.filled_qty;
+        if !order.state.can_become(next) && new_qty <= 1e-12 {
+            return;
+        }
+
+        let (mut last_qty, mut last_price) = (0.0, 0.0);
+        if new_qty > 1e-12 {
+            last_qty = new_qty;
+            last_price = (ack.avg_price * ack.filled_qty - order.avg_price * order.filled_qty) / new_qty;
+            self.position += if order.side() == Side::Buy { new_qty } else { -new_qty };
+            order.filled_qty = ack.filled_qty;
+            order.avg_price = ack.avg_price;
+        }
+        let from = order.state;
+        if order.state.can_become(next) {
+            transition(order, next);
+        }
+        order.exchange_order_id = order.exchange_order_id.or(ack.exchange_order_id);
+        order.updated = Utc::now();
+
+        if last_qty > 0.0 || from != order.state {
+            let report = ExecutionReport {
+                trade_id: trade_id_of(&ack.client_order_id),
+                client_order_id: ack.client_order_id.clone(),
+                exchange_order_id: order.exchange_order_id,
+                side: order.side(),
+                state: ack.state,
+                last_qty,
+                last_price,
+                cum_qty: order.filled_qty,
+                avg_price: order.avg_price,
+                fee: 0.0, // Only the stream reports commissions
+                fee_asset: String::new(),
+                liquidity: None,
+                reason: note,
+                time: order.updated,
+            };
+            self.updates.push(report);
+        }
+    }
+
+    /// Moves an order to a final state the venue did not report itself.
+    fn finish(&mut self, cid: &str, state: OmsState, reason: Option<String>) {
+        let Some(order) = self.orders.get_mut(cid) else { return };
+        if !order.state.can_become(state) { return; }
+        transition(order, state);
+        order.updated = Utc::now();
+        self.updates.push(ExecutionReport {
+            trade_id: Some(order.request.trade_id),
+            client_order_id: cid.to_string(),
+            exchange_order_id: order.exchange_order_id,
+            side: order.side(),
+            state: if state == OmsState::Rejected { OrderState::Rejected } else { OrderState::Canceled },
+            last_qty: 0.0,
+            last_price: 0.0,
+            cum_qty: order.filled_qty,
+            avg_price: order.avg_price,
+            fee: 0.0,
+            fee_asset: String::new(),
+            liquidity: None,
+            reason,
+            time: order.updated,
+        });
+    }
+
+    fn prune(&mut self) {
+        let finished = self.orders.values().filter(|o| o.state.is_final()).count();
+        if finished <= MAX_FINISHED { return; }
+        let mut done: Vec<_> = self.orders.iter()
+            .filter(|(_, o)| o.state.is_final())
+            .map(|(cid, o)| (o.updated, cid.clone()))
+            .collect();
+        done.sort();
+        for (_, cid) in done.into_iter().take(finished - MAX_FINISHED) {
+            self.orders.remove(&cid);
+        }
+    }
+}
+
+fn transition(order: &mut ManagedOrder, next: OmsState) {
+    if order.state != next {
+        info!("📋 ORDER {} {} → {}", order.request.client_order_id(), order.state.label(), next.label());
+        order.state = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers every order with a fill and hands out queued stream reports.
+    struct Scripted {
+        reports: Vec<ExecutionReport>,
+    }
+
+    impl Executor for Scripted {
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        async fn submit(&mut self, order: &OrderRequest) -> Result<OrderAck, ExecError> {
+            Ok(OrderAck { client_order_id: order.client_order_id(), exchange_order_id: Some(1), state: OrderState::Filled,
+                filled_qty: order.quantity, avg_price: 100.0 })
+        }
+
+        async fn cancel(&mut self, _: &str) -> Result<OrderAck, ExecError> {
+            Err(ExecError::NotFound)
+        }
+
+        async fn status(&mut self, _: &str) -> Result<OrderAck, ExecError> {
+            Err(ExecError::NotFound)
+        }
+
+        fn drain_reports(&mut self) -> Vec<ExecutionReport> {
+            std::mem::take(&mut self.reports)
+        }
+    }
+
+    fn request(trade_id: u64) -> OrderRequest {
+        OrderRequest {
+            trade_id,
+            symbol: "BTCUSDT".to_string(),
+            direction: "UP".to_string(),
+            price: 100.0,
+            stake_pct: 1.0,
+            notional: 100.0,
+            close_quantity: 0.0,
+            quantity: 1.0,
+        }
+    }
+
+    fn fill(cid: &str, last_qty: f64, cum_qty: f64, fee: f64) -> ExecutionReport {
+        ExecutionReport {
+            trade_id: trade_id_of(cid),
+            client_order_id: cid.to_string(),
+            exchange_order_id: Some(1),
+            side: Side::Buy,
+            state: if cum_qty >= 1.0 { OrderState::Filled } else { OrderState::PartiallyFilled },
+            last_qty,
+            last_price: 100.0,
+            cum_qty,
+            avg_price: 100.0,
+            fee,
+            fee_asset: "USDT".to_string(),
+            liquidity: Some(Liquidity::Taker),
+            reason: None,
+            time: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_fees_count_after_a_filled_ack() {
+        let mut oms = OrderManager::new(Scripted { reports: Vec::new() }, OmsConfig::from_env());
+        let cid = request(1).client_order_id();
+        oms.submit(request(1)).await.unwrap();
+        assert_eq!(oms.position, 1.0);
+
+        // The RESULT ack was already FILLED; the stream's two executions arrive afterwards
+        oms.executor.reports = vec![fill(&cid, 0.4, 0.4, 0.04), fill(&cid, 0.6, 1.0, 0.06), fill(&cid, 0.6, 1.0, 0.06)];
+        let updates = oms.poll(Utc::now()).await;
+
+        let order = &oms.orders[&cid];
+        assert!((order.fees - 0.1).abs() < 1e-12);
+        assert_eq!(order.liquidity, Some(Liquidity::Taker));
+        assert_eq!(oms.position, 1.0);
+        // The ack's synthetic fill and both stream reports; none double-counts the quantity
+        let reports: Vec<(f64, f64)> = updates.iter().map(|r| (r.last_qty, r.fee)).collect();
+        assert_eq!(reports, vec![(1.0, 0.0), (0.0, 0.04), (0.0, 0.06)]);
+    }
+
+    #[tokio::test]
+    async fn stream_first_then_ack_counts_once() {
+        let mut oms = OrderManager::new(Scripted { reports: Vec::new() }, OmsConfig::from_env());
+        let cid = request(2).client_order_id();
+        oms.orders.insert(cid.clone(), ManagedOrder {
+            request: request(2),
+            state: OmsState::Acked,
+            exchange_order_id: Some(1),
+            filled_qty: 0.0,
+            avg_price: 0.0,
+            fees: 0.0,
+            liquidity: None,
+            streamed_qty: 0.0,
+            created: Utc::now(),
+            updated: Utc::now(),
+            cancel_sent: false,
+        });
+        oms.executor.reports = vec![fill(&cid, 1.0, 1.0, 0.1)];
+        let updates = oms.poll(Utc::now()).await;
+        assert_eq!(updates.iter().map(|r| r.last_qty).collect::<Vec<_>>(), vec![1.0]);
+
+        oms.apply_ack(&OrderAck { client_order_id: cid.clone(), exchange_order_id: Some(1), state: OrderState::Filled,
+            filled_qty: 1.0, avg_price: 100.0 }, None);
+        assert!(oms.updates.is_empty());
+        assert_eq!(oms.position, 1.0);
+        assert!((oms.orders[&cid].fees - 0.1).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn gap_waits_for_working_orders() {
+        let mut oms = OrderManager::new(Scripted { reports: Vec::new() }, OmsConfig::from_env());
+        assert_eq!(oms.gap_to(-0.5), Some(-0.5));
+        oms.submit(request(3)).await.unwrap();
+        assert_eq!(oms.gap_to(0.0), Some(-1.0)); // Filled long 1, flat wanted
+
+        oms.orders.get_mut(&request(3).client_order_id()).unwrap().state = OmsState::Acked;
+        assert_eq!(oms.gap_to(0.0), None);
+    }
+}
@@ -0,0 +1,358 @@
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::ops::Range;
+use crate::backtest;
+use crate::config::env_or;
+use crate::model::{SignalParams, TradeData};
+use crate::performance::{PerformanceReport, PerformanceTracker};
+use crate::simulator::SimMode;
+use crate::strategy::WhaleMonteCarlo;
+use crate::{recorder, storage};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Search {
+    Grid,
+    Random(usize), // Samples per fold
+}
+
+/// What a candidate is ranked by on its training window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    Sharpe,
+    Return,
+    ProfitFactor,
+    Expectancy,
+}
+
+impl Metric {
+    fn parse(raw: &str) -> Self {
+        match raw.to_lowercase().as_str() {
+            "return" | "pnl" => Metric::Return,
+            "profit_factor" | "pf" => Metric::ProfitFactor,
+            "expectancy" => Metric::Expectancy,
+            _ => Metric::Sharpe,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Metric::Sharpe => "sharpe",
+            Metric::Return => "return",
+            Metric::ProfitFactor => "profit factor",
+            Metric::Expectancy => "expectancy",
+        }
+    }
+
+    fn score(&self, report: &PerformanceReport) -> f64 {
+        match self {
+            Metric::Sharpe => report.sharpe,
+            Metric::Return => report.total_return,
+            Metric::ProfitFactor => report.profit_factor.min(1_000.0), // No-loss runs would always win
+            Metric::Expectancy => report.expectancy,
+        }
+    }
+}
+
+/// One searched threshold: the values a grid tries, and the range random search draws from.
+struct Dimension {
+    name: &'static str,
+    values: Vec<f64>,
+    get: fn(&SignalParams) -> f64,
+    set: fn(&mut SignalParams, f64),
+}
+
+impl Dimension {
+    /// `OPT_<NAME>` (comma-separated) replaces the default values.
+    fn new(name: &'static str, defaults: &[f64], get: fn(&SignalParams) -> f64, set: fn(&mut SignalParams, f64)) -> Self {
+        let key = format!("OPT_{}", name.to_uppercase());
+        let mut values: Vec<f64> = env_or(&key, String::new())
+            .split(',')
+            .filter_map(|v| v.trim().parse().ok())
+            .collect();
+        if values.is_empty() {
+            values = defaults.to_vec();
+        }
+        Self { name, values, get, set }
+    }
+}
+
+fn dimensions() -> Vec<Dimension> {
+    vec![
+        Dimension::new("prob_cutoff", &[0.50, 0.55, 0.60, 0.70], |p| p.prob_cutoff, |p, v| p.prob_cutoff = v),
+        Dimension::new("ofi", &[0.1, 0.2, 0.3], |p| p.ofi_threshold, |p, v| p.ofi_threshold = v),
+        Dimension::new("conf_low_vol", &[50.0, 70.0, 88.0], |p| p.conf_low_vol, |p, v| p.conf_low_vol = v),
+        Dimension::new("conf", &[50.0, 75.0, 90.0], |p| p.conf, |p, v| p.conf = v),
+        Dimension::new("conf_high_vol", &[50.0, 80.0, 94.0], |p| p.conf_high_vol, |p, v| p.conf_high_vol = v),
+        Dimension::new("warmup", &[20.0, 50.0, 100.0], |p| p.warmup_ticks as f64, |p, v| p.warmup_ticks = (v.round() as usize).clamp(2, 100)),
+    ]
+}
+
+pub struct OptimizerConfig {
+    pub source: String,
+    pub search: Search,
+    pub metric: Metric,
+    pub folds: usize,
+    pub anchored: bool,    // Train on everything before the test window instead of one window
+    pub min_trades: usize, // Candidates with fewer training trades are not eligible
+    pub mc_paths: usize,
+    pub seed: u64,
+    pub mode: SimMode,
+}
+
+impl OptimizerConfig {
+    /// `None` unless `OPTIMIZE_FROM` names a recording (file or dir) or a SQLite tick store.
+    /// Also reads `OPT_SEARCH` (grid | random), `OPT_SAMPLES` (200), `OPT_METRIC` (sharpe |
+    /// return | profit_factor | expectancy), `OPT_FOLDS` (4), `OPT_ANCHORED` (false),
+    /// `OPT_MIN_TRADES` (10), `OPT_MC_PATHS` (1000) and `OPT_SEED` (42).
+    pub fn from_env() -> Option<Self> {
+        let source = env_or("OPTIMIZE_FROM", String::new());
+        if source.is_empty() { return None; }
+        let search = match env_or("OPT_SEARCH", "grid".to_string()).to_lowercase().as_str() {
+            "random" => Search::Random(env_or("OPT_SAMPLES", 200usize).max(1)),
+            _ => Search::Grid,
+        };
+        Some(Self {
+            source,
+            search,
+            metric: Metric::parse(&env_or("OPT_METRIC", "sharpe".to_string())),
+            folds: env_or("OPT_FOLDS", 4usize).max(1),
+            anchored: env_or("OPT_ANCHORED", false),
+            min_trades: env_or("OPT_MIN_TRADES", 10),
+            mc_paths: env_or("OPT_MC_PATHS", 1_000usize).max(100),
+            seed: env_or("OPT_SEED", 42),
+            mode: SimMode::from_env(),
+        })
+    }
+}
+
+/// Best candidate of one training window and how it did on the next, unseen window.
+struct FoldResult {
+    train: (DateTime<Utc>, DateTime<Utc>),
+    test: (DateTime<Utc>, DateTime<Utc>),
+    best: Option<(SignalParams, PerformanceReport)>,
+    oos: Option<PerformanceTracker>,
+    baseline: PerformanceTracker, // Current thresholds on the same test window
+}
+
+/// Walk-forward search: the ticks are cut into `folds + 1` equal time windows, each fold
+/// picks the best candidate on its training window and is scored on the window after it.
+pub fn run(config: OptimizerConfig) -> anyhow::Result<()> {
+    let ticks = load(&config.source)?;
+    let (Some(first), Some(last)) = (ticks.first(), ticks.last()) else {
+        anyhow::bail!("no ticks in {}", config.source);
+    };
+    let dims = dimensions();
+    let mut baseline = SignalParams::from_env();
+    baseline.mc_paths = config.mc_paths;
+    baseline.seed = Some(config.seed); // Every backtest replays the same Monte Carlo draws
+    let search = match config.search {
+        Search::Grid => format!("grid of {}", dims.iter().map(|d| d.values.len()).product::<usize>()),
+        Search::Random(n) => format!("{} random samples", n),
+    };
+    info!("📐 OPTIMIZER: {} ticks {} → {} | {} | {} folds{} | ranked by {}", ticks.len(), first.timestamp, last.timestamp,
+        search, config.folds, if config.anchored { " (anchored)" } else { "" }, config.metric.label());
+
+    let bounds = bounds(&ticks, config.folds + 1);
+    let time_range = |slice: &[TradeData]| (
+        slice.first().map_or(first.timestamp, |t| t.timestamp),
+        slice.last().map_or(first.timestamp, |t| t.timestamp),
+    );
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut folds = Vec::new();
+    for fold in 0..config.folds {
+        let (train, test) = fold_windows(&bounds, fold, config.anchored);
+        let (train, test) = (&ticks[train], &ticks[test]);
+        let candidates = candidates(&dims, config.search, baseline, &mut rng);
+
+        let scored: Vec<(SignalParams, PerformanceReport)> = candidates
+            .into_par_iter()
+            .map(|params| (params, backtest_params(train, params, config.mode).report()))
+            .filter(|(_, report)| report.trades >= config.min_trades)
+            .collect();
+        let best = scored.into_iter()
+            .max_by(|a, b| config.metric.score(&a.1).total_cmp(&config.metric.score(&b.1)));
+
+        let result = FoldResult {
+            train: time_range(train),
+            test: time_range(test),
+            oos: best.as_ref().map(|(params, _)| backtest_params(test, *params, config.mode)),
+            baseline: backtest_params(test, baseline, config.mode),
+            best,
+        };
+        log_fold(fold, config.folds, &result, config.metric);
+        folds.push(result);
+    }
+
+    summarise(&folds, &dims, &baseline);
+    Ok(())
+}
+
+fn load(source: &str) -> anyhow::Result<Vec<TradeData>> {
+    let mut ticks = if [".db", ".sqlite", ".sqlite3"].iter().any(|ext| source.ends_with(ext)) {
+        storage::load_ticks(source, DateTime::<Utc>::UNIX_EPOCH, Utc::now())?
+    } else {
+        recorder::read_recording(source)?.map(|r| r.map(|r| r.tick)).collect::<anyhow::Result<_>>()?
+    };
+    ticks.sort_by_key(|t| t.timestamp);
+    Ok(ticks)
+}
+
+/// Index of the first tick of each of `windows` equal time spans, plus `ticks.len()`.
+/// Cut by time, so quiet and busy periods get equal spans.
+fn bounds(ticks: &[TradeData], windows: usize) -> Vec<usize> {
+    let (Some(first), Some(last)) = (ticks.first(), ticks.last()) else { return vec![0; windows + 1] };
+    let span = (last.timestamp - first.timestamp) / windows as i32;
+    (0..=windows)
+        .map(|i| if i == windows { ticks.len() } else { ticks.partition_point(|t| t.timestamp < first.timestamp + span * i as i32) })
+        .collect()
+}
+
+/// Training and test tick ranges of `fold`: the window after the training one is the test.
+fn fold_windows(bounds: &[usize], fold: usize, anchored: bool) -> (Range<usize>, Range<usize>) {
+    let train = bounds[if anchored { 0 } else { fold }]..bounds[fold + 1];
+    (train, bounds[fold + 1]..bounds[fold + 2])
+}
+
+fn candidates(dims: &[Dimension], search: Search, base: SignalParams, rng: &mut StdRng) -> Vec<SignalParams> {
+    match search {
+        Search::Grid => dims.iter().fold(vec![base], |acc, dim| {
+            acc.iter()
+                .flat_map(|p| dim.values.iter().map(move |&v| {
+                    let mut next = *p;
+                    (dim.set)(&mut next, v);
+                    next
+                }))
+                .collect()
+        }),
+        Search::Random(samples) => (0..samples)
+            .map(|_| {
+                let mut params = base;
+                for dim in dims {
+                    let lo = dim.values.iter().copied().fold(f64::INFINITY, f64::min);
+                    let hi = dim.values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                    (dim.set)(&mut params, if hi > lo { rng.random_range(lo..=hi) } else { lo });
+                }
+                params
+            })
+            .collect(),
+    }
+}
+
+fn backtest_params(ticks: &[TradeData], params: SignalParams, mode: SimMode) -> PerformanceTracker {
+    backtest::run(ticks, &mut WhaleMonteCarlo::new(params), mode)
+}
+
+fn log_fold(fold: usize, folds: usize, result: &FoldResult, metric: Metric) {
+    let fmt = |(from, to): (DateTime<Utc>, DateTime<Utc>)| format!("{} → {}", from.format("%m-%d %H:%M"), to.format("%m-%d %H:%M"));
+    info!("📐 FOLD {}/{} | Train {} | Test {}", fold + 1, folds, fmt(result.train), fmt(result.test));
+    match (&result.best, &result.oos) {
+        (Some((params, train)), Some(oos)) => {
+            info!("   Best: {}", params);
+            info!("   In-sample: {:.2} {} ({} trades) | Out-of-sample: {}", metric.score(train), metric.label(), train.trades, oos.report().one_line());
+        }
+        _ => warn!("   No candidate reached OPT_MIN_TRADES on the training window"),
+    }
+    info!("   Baseline out-of-sample: {}", result.baseline.report().one_line());
+}
+
+/// Chains the out-of-sample trades of every fold into one track record, next to the
+/// current thresholds over the same windows.
+fn summarise(folds: &[FoldResult], dims: &[Dimension], baseline: &SignalParams) {
+    let chain = |pick: &dyn Fn(&FoldResult) -> Option<&PerformanceTracker>| {
+        let start = folds.first().map_or(Utc::now(), |f| f.test.0);
+        let mut combined = PerformanceTracker::new(10_000.0, start);
+        for tracker in folds.iter().filter_map(pick) {
+            for t in &tracker.trades {
+                combined.push(t.closed_at, t.pnl);
+            }
+        }
+        combined.report()
+    };
+    let oos = chain(&|f| f.oos.as_ref());
+    let base = chain(&|f| Some(&f.baseline));
+
+    info!("📐 WALK-FORWARD OUT-OF-SAMPLE ({} of {} folds traded)", folds.iter().filter(|f| f.oos.is_some()).count(), folds.len());
+    for line in oos.to_string().lines() {
+        info!("   {}", line);
+    }
+    info!("📐 BASELINE ({})", baseline);
+    for line in base.to_string().lines() {
+        info!("   {}", line);
+    }
+
+    // A threshold picked in most folds is a real choice; one that jumps around is noise
+    let picks: Vec<SignalParams> = folds.iter().filter_map(|f| f.best.as_ref().map(|(p, _)| *p)).collect();
+    for dim in dims {
+        let chosen: Vec<String> = picks.iter().map(|p| format!("{:.2}", (dim.get)(p))).collect();
+        if !chosen.is_empty() {
+            info!("   {}: {}", dim.name, chosen.join(", "));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn ticks(seconds: &[i64]) -> Vec<TradeData> {
+        let start: DateTime<Utc> = "2026-01-01T00:00:00Z".parse().unwrap();
+        seconds.iter()
+            .map(|&s| TradeData { timestamp: start + Duration::seconds(s), price: 50_000.0, quantity: 0.01, is_buyer_maker: false })
+            .collect()
+    }
+
+    #[test]
+    fn windows_split_by_time_and_each_fold_tests_on_the_next() {
+        // 0..100s in 5 windows of 20s, with the 40-60s window empty
+        let mut seconds: Vec<i64> = (0..40).chain(60..=100).collect();
+        seconds.push(100);
+        let ticks = ticks(&seconds);
+        let bounds = bounds(&ticks, 5);
+        assert_eq!(bounds, vec![0, 20, 40, 40, 60, ticks.len()]);
+
+        assert_eq!(fold_windows(&bounds, 0, false), (0..20, 20..40));
+        assert_eq!(fold_windows(&bounds, 1, false), (20..40, 40..40));
+        assert_eq!(fold_windows(&bounds, 3, false), (40..60, 60..ticks.len()));
+        // Anchored folds train on everything before their test window
+        assert_eq!(fold_windows(&bounds, 3, true), (0..60, 60..ticks.len()));
+
+        assert_eq!(super::bounds(&[], 3), vec![0; 4]);
+    }
+
+    fn dims() -> Vec<Dimension> {
+        vec![
+            Dimension { name: "prob_cutoff", values: vec![0.55, 0.65], get: |p| p.prob_cutoff, set: |p, v| p.prob_cutoff = v },
+            Dimension { name: "conf", values: vec![50.0, 70.0, 90.0], get: |p| p.conf, set: |p, v| p.conf = v },
+        ]
+    }
+
+    #[test]
+    fn grid_search_tries_every_combination_once() {
+        let base = SignalParams::from_env();
+        let grid = candidates(&dims(), Search::Grid, base, &mut StdRng::seed_from_u64(1));
+        let mut tried: Vec<(f64, f64)> = grid.iter().map(|p| (p.prob_cutoff, p.conf)).collect();
+        tried.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(tried, vec![(0.55, 50.0), (0.55, 70.0), (0.55, 90.0), (0.65, 50.0), (0.65, 70.0), (0.65, 90.0)]);
+        // Thresholds outside the search keep the base values
+        assert!(grid.iter().all(|p| p.ofi_threshold == base.ofi_threshold && p.warmup_ticks == base.warmup_ticks));
+    }
+
+    #[test]
+    fn random_search_draws_within_each_range_and_repeats_by_seed() {
+        let base = SignalParams::from_env();
+        let draw = |seed| candidates(&dims(), Search::Random(50), base, &mut StdRng::seed_from_u64(seed));
+        let samples = draw(9);
+        assert_eq!(samples.len(), 50);
+        assert!(samples.iter().all(|p| (0.55..=0.65).contains(&p.prob_cutoff) && (50.0..=90.0).contains(&p.conf)));
+
+        let key = |ps: &[SignalParams]| ps.iter().map(|p| (p.prob_cutoff, p.conf)).collect::<Vec<_>>();
+        assert_eq!(key(&samples), key(&draw(9)));
+        assert_ne!(key(&samples), key(&draw(10)));
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt;
+use chrono::{DateTime, Duration, Utc};
+use crate::simulator::ClosedTrade;
+
+const SECS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
+
+/// One settled trade as seen by the analytics.
+#[derive(Debug, Clone)]
+pub struct TradeResult {
+    pub closed_at: DateTime<Utc>,
+    pub pnl: f64,
+    pub ret: f64, // PnL as a fraction of equity before the trade
+}
+
+/// Equity curve and trade-level statistics, fed from closed trades.
+pub struct PerformanceTracker {
+    pub starting_equity: f64,
+    pub started_at: DateTime<Utc>,
+    pub trades: Vec<TradeResult>,
+    pub equity_curve: Vec<(DateTime<Utc>, f64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PerformanceReport {
+    pub trades: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub win_rate: f64,           // %
+    pub final_equity: f64,
+    pub total_return: f64,       // %
+    pub annualised_return: f64,  // %, compounded over the tracked span
+    pub sharpe: f64,             // Annualised, per-trade returns, zero risk-free rate
+    pub sortino: f64,
+    pub max_drawdown: f64,       // % from peak
+    pub max_drawdown_duration: Duration,
+    pub profit_factor: f64,      // Gross profit / gross loss (infinite with no losses)
+    pub net_pnl: f64,            // $ summed over the closed trades
+    pub expectancy: f64,         // Mean $ PnL per trade
+    pub avg_win: f64,
+    pub avg_loss: f64,
+    pub best_trade: f64,
+    pub worst_trade: f64,
+    pub max_win_streak: u32,
+    pub max_loss_streak: u32,
+    pub current_streak: i32,     // Positive = wins in a row, negative = losses
+}
+
+impl PerformanceTracker {
+    pub fn new(starting_equity: f64, started_at: DateTime<Utc>) -> Self {
+        Self {
+            starting_equity,
+            started_at,
+            trades: Vec::new(),
+            equity_curve: vec![(started_at, starting_equity)],
+        }
+    }
+
+    pub fn current_equity(&self) -> f64 {
+        self.equity_curve.last().map_or(self.starting_equity, |&(_, e)| e)
+    }
+
+    pub fn record(&mut self, trade: &ClosedTrade) {
+        self.push(trade.close_time, trade.pnl);
+    }
+
+    /// Adds a settled PnL, e.g. history loaded from the trade store.
+    pub fn push(&mut self, closed_at: DateTime<Utc>, pnl: f64) {
+        let before = self.current_equity();
+        let ret = if before > 0.0 { pnl / before } else { 0.0 };
+        self.trades.push(TradeResult { closed_at, pnl, ret });
+        self.equity_curve.push((closed_at, before + pnl));
+    }
+
+    /// The trades closed at or after `from`, starting from the equity at that moment.
+    /// Used for period reports.
+    pub fn since(&self, from: DateTime<Utc>) -> PerformanceTracker {
+        let start_equity = self.equity_curve.iter()
+            .take_while(|&&(t, _)| t < from)
+            .last()
+            .map_or(self.starting_equity, |&(_, e)| e);
+        let mut window = PerformanceTracker::new(start_equity, from.max(self.started_at));
+        for t in self.trades.iter().filter(|t| t.closed_at >= from) {
+            window.push(t.closed_at, t.pnl);
+        }
+        window
+    }
+
+    pub fn report(&self) -> PerformanceReport {
+        let pnls: Vec<f64> = self.trades.iter().map(|t| t.pnl).collect();
+        let returns: Vec<f64> = self.trades.iter().map(|t| t.ret).collect();
+        let n = pnls.len();
+
+        let wins: Vec<f64> = pnls.iter().copied().filter(|&p| p > 0.0).collect();
+        let losses: Vec<f64> = pnls.iter().copied().filter(|&p| p < 0.0).collect();
+        let gross_profit: f64 = wins.iter().sum();
+        let gross_loss: f64 = -losses.iter().sum::<f64>();
+        let net_pnl: f64 = pnls.iter().sum();
+
+        let final_equity = self.current_equity();
+        let total_return = final_equity / self.starting_equity - 1.0;
+
+        // Annualise over the tracked span (start to last settlement)
+        let end = self.trades.last().map_or(self.started_at, |t| t.closed_at);
+        let years = (end - self.started_at).num_seconds() as f64 / SECS_PER_YEAR;
+        let annualised_return = if years > 0.0 && final_equity > 0.0 {
+            (final_equity / self.starting_equity).powf(1.0 / years) - 1.0
+        } else {
+            0.0
+        };
+        let trades_per_year = if years > 0.0 { n as f64 / years } else { 0.0 };
+
+        let mean_ret = mean(&returns);
+        let sharpe = ratio(mean_ret, std_dev(&returns, mean_ret), trades_per_year);
+        let downside = (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / n.max(1) as f64).sqrt();
+        let sortino = ratio(mean_ret, downside, trades_per_year);
+
+        let (max_drawdown, max_drawdown_duration) = self.drawdown();
+        let (max_win_streak, max_loss_streak, current_streak) = streaks(&pnls);
+
+        PerformanceReport {
+            trades: n,
+            wins: wins.len(),
+            losses: losses.len(),
+            win_rate: if n > 0 { wins.len() as f64 / n as f64 * 100.0 } else { 0.0 },
+            final_equity,
+            total_return: total_return * 100.0,
+            annualised_return: annualised_return * 100.0,
+            sharpe,
+            sortino,
+            max_drawdown: max_drawdown * 100.0,
+            max_drawdown_duration,
+            profit_factor: if gross_loss > 0.0 { gross_profit / gross_loss } else if gross_profit > 0.0 { f64::INFINITY } else { 0.0 },
+            net_pnl,
+            expectancy: if n > 0 { net_pnl / n as f64 } else { 0.0 },
+            avg_win: mean(&wins),
+            avg_loss: mean(&losses),
+            best_trade: pnls.iter().copied().reduce(f64::max).unwrap_or(0.0),
+            worst_trade: pnls.iter().copied().reduce(f64::min).unwrap_or(0.0),
+            max_win_streak,
+            max_loss_streak,
+            current_streak,
+        }
+    }
+
+    /// Deepest peak-to-trough fall, and the longest time spent below a previous peak.
+    fn drawdown(&self) -> (f64, Duration) {
+        let mut peak = f64::NEG_INFINITY;
+        let mut peak_time = self.started_at;
+        let mut max_dd: f64 = 0.0;
+        let mut max_duration = Duration::zero();
+
+        for &(t, equity) in &self.equity_curve {
+            if equity >= peak {
+                peak = equity;
+                peak_time = t;
+            } else {
+                max_dd = max_dd.max((peak - equity) / peak);
+                max_duration = max_duration.max(t - peak_time);
+            }
+        }
+        (max_dd, max_duration)
+    }
+}
+
+fn mean(xs: &[f64]) -> f64 {
+    if xs.is_empty() { 0.0 } else { xs.iter().sum::<f64>() / xs.len() as f64 }
+}
+
+fn std_dev(xs: &[f64], mean: f64) -> f64 {
+    if xs.len() < 2 { return 0.0; }
+    (xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (xs.len() - 1) as f64).sqrt()
+}
+
+fn ratio(mean: f64, dispersion: f64, periods_per_year: f64) -> f64 {
+    // Identical returns leave float dust, not risk; dividing by it gives absurd ratios
+    if dispersion > 1e-12 { mean / dispersion * periods_per_year.sqrt() } else { 0.0 }
+}
+
+/// (longest win streak, longest loss streak, current streak). Flat trades break a streak.
+fn streaks(pnls: &[f64]) -> (u32, u32, i32) {
+    let (mut best_win, mut best_loss, mut current) = (0u32, 0u32, 0i32);
+    for &p in pnls {
+        current = if p > 0.0 {
+            current.max(0) + 1
+        } else if p < 0.0 {
+            current.min(0) - 1
+        } else {
+            0
+        };
+        best_win = best_win.max(current.max(0) as u32);
+        best_loss = best_loss.max((-current).max(0) as u32);
+    }
+    (best_win, best_loss, current)
+}
+
+impl PerformanceReport {
+    /// Single-line form for the heartbeat dashboard.
+    pub fn one_line(&self) -> String {
+        format!("Ret: {:+.2}% | Sharpe: {:.2} | MaxDD: {:.2}% | PF: {:.2} | Exp: ${:+.2} | Streak: {:+}",
+            self.total_return, self.sharpe, self.max_drawdown, self.profit_factor, self.expectancy, self.current_streak)
+    }
+}
+
+impl fmt::Display for PerformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Trades: {} ({}W / {}L) | Win Rate: {:.1}%", self.trades, self.wins, self.losses, self.win_rate)?;
+        writeln!(f, "Equity: ${:.2} | Return: {:+.2}% | Annualised: {:+.2}%", self.final_equity, self.total_return, self.annualised_return)?;
+        writeln!(f, "Sharpe: {:.2} | Sortino: {:.2}", self.sharpe, self.sortino)?;
+        writeln!(f, "Max DD: {:.2}% (longest {}m underwater)", self.max_drawdown, self.max_drawdown_duration.num_minutes())?;
+        writeln!(f, "Net PnL: ${:+.2} | Profit Factor: {:.2} | Expectancy: ${:+.2}", self.net_pnl, self.profit_factor, self.expectancy)?;
+        writeln!(f, "Avg Win: ${:.2} | Avg Loss: ${:.2}", self.avg_win, self.avg_loss)?;
+        writeln!(f, "Best: ${:+.2} | Worst: ${:+.2}", self.best_trade, self.worst_trade)?;
+        write!(f, "Streaks: {}W max / {}L max | Current: {:+}", self.max_win_streak, self.max_loss_streak, self.current_streak)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(pnls: &[f64]) -> PerformanceTracker {
+        let start = Utc::now();
+        let mut tracker = PerformanceTracker::new(10_000.0, start);
+        for (i, &pnl) in pnls.iter().enumerate() {
+            tracker.push(start + Duration::minutes(i as i64 + 1), pnl);
+        }
+        tracker
+    }
+
+    #[test]
+    fn best_and_worst_trade_with_only_losses() {
+        let report = tracker(&[-5.0, -2.0, -9.0]).report();
+        assert_eq!(report.best_trade, -2.0);
+        assert_eq!(report.worst_trade, -9.0);
+    }
+
+    #[test]
+    fn best_and_worst_trade_with_only_wins() {
+        let report = tracker(&[3.0, 7.0]).report();
+        assert_eq!(report.best_trade, 7.0);
+        assert_eq!(report.worst_trade, 3.0);
+    }
+
+    #[test]
+    fn window_pnl_sums_only_its_trades() {
+        let full = tracker(&[100.0, -40.0, 25.5, -10.0]);
+        let from = full.trades[2].closed_at;
+        let report = full.since(from).report();
+        assert_eq!(report.trades, 2);
+        assert_eq!(report.net_pnl, 15.5);
+        assert_eq!(report.final_equity - full.since(from).starting_equity, 15.5);
+    }
+
+    #[test]
+    fn best_and_worst_trade_without_trades() {
+        let report = tracker(&[]).report();
+        assert_eq!((report.best_trade, report.worst_trade), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,301 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use anyhow::{bail, Context};
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use crate::model::TradeData;
+use crate::simulator::{PaperWallet, WalletSnapshot};
+
+/// Wallet inputs, journaled before they are applied. The wallet is deterministic on
+/// tick time, so replaying them on top of the last snapshot rebuilds its exact state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WalletEvent {
+    Tick(TradeData),
+    Order {
+        #[serde(default)]
+        trade_id: u64,
+        direction: String,
+        price: f64,
+        stake_pct: f64,
+    },
+    Flatten, // Kill switch closed the book
+}
+
+#[derive(Serialize, Deserialize)]
+struct JournalEntry {
+    seq: u64,
+    event: WalletEvent,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotFile {
+    seq: u64, // Last journal entry already folded into `wallet`
+    wallet: WalletSnapshot,
+}
+
+/// Snapshot + write-ahead journal for the paper wallet. Ticks are buffered and made durable
+/// at checkpoints; orders are synced as they are recorded, together with every tick before them.
+pub struct WalletStore {
+    snapshot_path: PathBuf,
+    journal_path: PathBuf,
+    journal: BufWriter<File>,
+    seq: u64,
+}
+
+impl WalletStore {
+    /// Opens (or creates) the store in `dir`.
+    pub fn open(dir: &str) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("creating state dir {}", dir))?;
+        let dir = PathBuf::from(dir);
+        let journal_path = dir.join("wallet_journal.jsonl");
+        let journal = OpenOptions::new().create(true).append(true).open(&journal_path)?;
+
+        Ok(Self {
+            snapshot_path: dir.join("wallet_state.json"),
+            journal_path,
+            journal: BufWriter::new(journal),
+            seq: 0,
+        })
+    }
+
+    /// Loads the last snapshot and replays the journal on top of it.
+    /// Returns false when there was no saved state.
+    pub fn restore(&mut self, wallet: &mut PaperWallet) -> anyhow::Result<bool> {
+        let mut restored = false;
+
+        if self.snapshot_path.exists() {
+            let raw = fs::read_to_string(&self.snapshot_path)?;
+            let file: SnapshotFile = serde_json::from_str(&raw).context("corrupt wallet snapshot")?;
+            if file.wallet.mode != wallet.mode {
+                bail!("saved state is {} mode but SIM_MODE is {}", file.wallet.mode.label(), wallet.mode.label());
+            }
+            wallet.restore(file.wallet);
+            self.seq = file.seq;
+            restored = true;
+        }
+
+        let mut replayed = 0;
+        let reader = BufReader::new(File::open(&self.journal_path)?);
+        let mut lines = reader.lines();
+        while let Some(line) = lines.next() {
+            let line = line?;
+            let Ok(entry) = serde_json::from_str::<JournalEntry>(&line) else {
+                // A torn final line from a crash mid-write is expected; anything after it is corruption
+                if lines.any(|l| l.map_or(true, |l| !l.trim().is_empty())) {
+                    bail!("corrupt wallet journal entry after seq {}", self.seq);
+                }
+                warn!("⚠️ JOURNAL: Ignoring torn final entry after seq {}", self.seq);
+                break;
+            };
+            if entry.seq <= self.seq { continue; }
+
+            apply(wallet, entry.event);
+            self.seq = entry.seq;
+            replayed += 1;
+            restored = true;
+        }
+
+        if restored {
+            info!("💾 WALLET RESTORED | Balance: ${:.2} | Open: {} | Replayed {} events",
+                wallet.balance, wallet.active_trades.len() + wallet.position.iter().count(), replayed);
+        }
+        Ok(restored)
+    }
+
+    /// Renames the snapshot and journal to `<name>.failed-<time>` and starts an empty journal,
+    /// so state that failed to restore survives the fresh start. Returns the suffix used.
+    pub fn set_aside(&mut self) -> anyhow::Result<String> {
+        let suffix = format!("failed-{}", Utc::now().format("%Y%m%dT%H%M%S"));
+        for path in [&self.snapshot_path, &self.journal_path] {
+            if path.exists() {
+                fs::rename(path, format!("{}.{}", path.display(), suffix))
+                    .with_context(|| format!("moving {} aside", path.display()))?;
+            }
+        }
+        self.journal = BufWriter::new(OpenOptions::new().create(true).append(true).open(&self.journal_path)?);
+        self.seq = 0;
+        Ok(suffix)
+    }
+
+    /// Appends an event to the journal, in the order it is applied to the wallet. Orders and
+    /// flattens are synced to disk straight away; ticks wait in the buffer for the next `checkpoint`.
+    pub fn record(&mut self, event: WalletEvent) {
+        self.seq += 1;
+        let durable = !matches!(event, WalletEvent::Tick(_));
+        let entry = JournalEntry { seq: self.seq, event };
+        let result = serde_json::to_string(&entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| Ok(writeln!(self.journal, "{}", line)?));
+
+        if let Err(e) = result {
+            error!("FAILED TO WRITE WALLET JOURNAL: {}", e);
+        } else if durable {
+            self.checkpoint();
+        }
+    }
+
+    /// Flushes buffered entries and syncs the journal, so a crash cannot lose them.
+    pub fn checkpoint(&mut self) {
+        let result = self.journal.flush().and_then(|_| self.journal.get_ref().sync_data());
+        if let Err(e) = result {
+            error!("FAILED TO SYNC WALLET JOURNAL: {}", e);
+        }
+    }
+
+    /// Atomically replaces the snapshot, then truncates the journal it covers.
+    pub fn snapshot(&mut self, wallet: &PaperWallet) {
+        if let Err(e) = self.write_snapshot(wallet) {
+            error!("FAILED TO SNAPSHOT WALLET: {}", e);
+        }
+    }
+
+    fn write_snapshot(&mut self, wallet: &PaperWallet) -> anyhow::Result<()> {
+        let file = SnapshotFile { seq: self.seq, wallet: wallet.snapshot() };
+        let tmp_path = self.snapshot_path.with_extension("json.tmp");
+
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(serde_json::to_string(&file)?.as_bytes())?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, &self.snapshot_path)?;
+
+        // Entries up to `seq` are now in the snapshot. If we crash before this
+        // truncation, replay skips them by sequence number.
+        let journal = File::create(&self.journal_path)?;
+        journal.sync_all()?;
+        self.journal = BufWriter::new(OpenOptions::new().append(true).open(&self.journal_path)?);
+        Ok(())
+    }
+}
+
+/// Applies a journaled event exactly as the live loop did.
+fn apply(wallet: &mut PaperWallet, event: WalletEvent) {
+    match event {
+        WalletEvent::Tick(tick) => wallet.update(&tick),
+        WalletEvent::Order { trade_id, direction, price, stake_pct } => {
+            wallet.open_trade(trade_id, direction, price, stake_pct)
+        }
+        WalletEvent::Flatten => wallet.flatten(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::SimMode;
+
+    fn state_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("wallet-store-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn tick(price: f64) -> WalletEvent {
+        WalletEvent::Tick(TradeData { timestamp: Utc::now(), price, quantity: 0.01, is_buyer_maker: false })
+    }
+
+    fn at(secs: i64) -> chrono::DateTime<Utc> {
+        "2026-01-01T10:00:00Z".parse::<chrono::DateTime<Utc>>().unwrap() + chrono::Duration::seconds(secs)
+    }
+
+    fn tick_at(secs: i64, price: f64) -> WalletEvent {
+        WalletEvent::Tick(TradeData { timestamp: at(secs), price, quantity: 0.01, is_buyer_maker: false })
+    }
+
+    fn order(trade_id: u64, price: f64) -> WalletEvent {
+        WalletEvent::Order { trade_id, direction: "UP".to_string(), price, stake_pct: 1.0 }
+    }
+
+    #[test]
+    fn replay_rebuilds_the_wallet_and_settles_downtime_expiries_on_fresh_prices() {
+        let dir = state_dir("replay");
+        let mut live = PaperWallet::new(SimMode::Binary);
+        live.fill_model.latency = std::time::Duration::ZERO;
+        let mut store = WalletStore::open(&dir).unwrap();
+        let feed = |store: &mut WalletStore, wallet: &mut PaperWallet, event: WalletEvent| {
+            store.record(event.clone());
+            apply(wallet, event);
+        };
+
+        // Trade 1 settles before the snapshot, trade 2 is still open when the process dies
+        for secs in 0..=80 {
+            feed(&mut store, &mut live, tick_at(secs, 100.0 + (secs % 7) as f64));
+            match secs {
+                5 => feed(&mut store, &mut live, order(1, 105.0)),
+                40 => store.snapshot(&live),
+                75 => feed(&mut store, &mut live, order(2, 105.0)),
+                _ => {}
+            }
+        }
+        store.checkpoint();
+        drop(store);
+        assert_eq!((live.wins + live.losses, live.active_trades.len()), (1, 1));
+
+        let mut restored = PaperWallet::new(SimMode::Binary);
+        restored.fill_model.latency = std::time::Duration::ZERO;
+        assert!(WalletStore::open(&dir).unwrap().restore(&mut restored).unwrap());
+        assert_eq!(serde_json::to_value(restored.snapshot()).unwrap(), serde_json::to_value(live.snapshot()).unwrap());
+
+        // Down from t=80 to t=3600: trade 2 expired at t=135 with no price near it
+        restored.drain_closed();
+        restored.settle_overdue(at(3_600));
+        assert!(restored.drain_closed().is_empty(), "pre-shutdown ticks must not settle it");
+
+        apply(&mut restored, tick_at(3_601, 90.0));
+        let closed = restored.drain_closed();
+        assert_eq!(closed.iter().map(|c| (c.trade_id, c.exit_price)).collect::<Vec<_>>(), vec![(2, 90.0)]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn torn_final_line_is_ignored() {
+        let dir = state_dir("torn");
+        let mut store = WalletStore::open(&dir).unwrap();
+        store.record(tick(100.0));
+        drop(store);
+        let journal = PathBuf::from(&dir).join("wallet_journal.jsonl");
+        let mut raw = fs::read_to_string(&journal).unwrap();
+        raw.push_str("{\"seq\":2,\"ev");
+        fs::write(&journal, raw).unwrap();
+
+        let mut wallet = PaperWallet::new(SimMode::Binary);
+        assert!(WalletStore::open(&dir).unwrap().restore(&mut wallet).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_line_before_more_entries_fails() {
+        let dir = state_dir("corrupt");
+        fs::create_dir_all(&dir).unwrap();
+        let journal = PathBuf::from(&dir).join("wallet_journal.jsonl");
+        let entry = |seq: u64| serde_json::to_string(&JournalEntry { seq, event: tick(100.0) }).unwrap();
+        fs::write(&journal, format!("{}\ngarbage\n{}\n", entry(1), entry(2))).unwrap();
+
+        let mut wallet = PaperWallet::new(SimMode::Binary);
+        assert!(WalletStore::open(&dir).unwrap().restore(&mut wallet).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn set_aside_keeps_unrestorable_state() {
+        let dir = state_dir("aside");
+        let mut store = WalletStore::open(&dir).unwrap();
+        store.snapshot(&PaperWallet::new(SimMode::Spot));
+        store.record(tick(100.0));
+        drop(store);
+
+        let mut wallet = PaperWallet::new(SimMode::Binary);
+        let mut store = WalletStore::open(&dir).unwrap();
+        assert!(store.restore(&mut wallet).is_err(), "mode mismatch");
+        let suffix = store.set_aside().unwrap();
+        store.snapshot(&wallet);
+
+        let kept = PathBuf::from(&dir).join(format!("wallet_state.json.{}", suffix));
+        let saved: SnapshotFile = serde_json::from_str(&fs::read_to_string(kept).unwrap()).unwrap();
+        assert_eq!(saved.wallet.mode, SimMode::Spot);
+        let journal = fs::read_to_string(PathBuf::from(&dir).join(format!("wallet_journal.jsonl.{}", suffix))).unwrap();
+        assert_eq!(journal.lines().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
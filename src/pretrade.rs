@@ -0,0 +1,236 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use chrono::{DateTime, Duration, Utc};
+use crate::config::env_or;
+use crate::executor::{OrderRequest, Side};
+use crate::simulator::SimMode;
+
+/// Exchange trading rules for one symbol: lot size, tick size and minimum notional.
+#[derive(Debug, Clone)]
+pub struct SymbolRules {
+    pub symbol: String,
+    pub tick_size: f64,
+    pub step_size: f64,
+    pub min_qty: f64,
+    pub max_qty: f64,
+    pub min_notional: f64,
+}
+
+impl SymbolRules {
+    /// Paper trading rules from `LOT_STEP` (0.00001), `TICK_SIZE` (0.01), `MIN_QTY` (0.00001)
+    /// and `MIN_NOTIONAL` (5 USDT), matching Binance spot BTCUSDT.
+    pub fn from_env(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            tick_size: env_or("TICK_SIZE", 0.01),
+            step_size: env_or("LOT_STEP", 0.00001),
+            min_qty: env_or("MIN_QTY", 0.00001),
+            max_qty: f64::INFINITY,
+            min_notional: env_or("MIN_NOTIONAL", 5.0),
+        }
+    }
+
+    /// Rounds down to the lot step, so an order never exceeds its stake.
+    pub fn round_qty(&self, qty: f64) -> f64 {
+        if self.step_size <= 0.0 { return qty; }
+        // Nudge before flooring so 0.3 / 0.1 style float error does not lose a whole step
+        snap(((qty / self.step_size) + 1e-9).floor() * self.step_size, self.step_size)
+    }
+
+    pub fn round_price(&self, price: f64) -> f64 {
+        if self.tick_size <= 0.0 { return price; }
+        snap((price / self.tick_size).round() * self.tick_size, self.tick_size)
+    }
+
+    /// Decimal places needed to print a lot-rounded quantity.
+    pub fn qty_decimals(&self) -> usize {
+        decimals(self.step_size)
+    }
+}
+
+/// Drops the float noise left by multiplying with the step (0.1 * 3 = 0.30000000000000004).
+fn snap(value: f64, step: f64) -> f64 {
+    let scale = 10f64.powi(decimals(step) as i32);
+    (value * scale).round() / scale
+}
+
+fn decimals(step: f64) -> usize {
+    if step <= 0.0 || step >= 1.0 { 0 } else { (-step.log10()).round() as usize }
+}
+
+/// Engine-side limits, independent of the exchange.
+#[derive(Debug, Clone)]
+pub struct PreTradeLimits {
+    pub max_notional: f64,     // USDT per order
+    pub price_collar_pct: f64, // Max distance of the order price from the latest market trade
+    pub max_qty: f64,          // Fat-finger cap, BTC per order
+    pub symbols: HashSet<String>,
+    pub duplicate_window: Duration,
+}
+
+impl PreTradeLimits {
+    /// Reads `MAX_ORDER_NOTIONAL` (5000), `PRICE_COLLAR_PCT` (1.0), `MAX_ORDER_QTY` (0.5),
+    /// `SYMBOL_ALLOWLIST` (BTCUSDT, comma-separated) and `DUPLICATE_WINDOW_SECS` (5).
+    pub fn from_env() -> Self {
+        Self {
+            max_notional: env_or("MAX_ORDER_NOTIONAL", 5000.0),
+            price_collar_pct: env_or("PRICE_COLLAR_PCT", 1.0),
+            max_qty: env_or("MAX_ORDER_QTY", 0.5),
+            symbols: env_or("SYMBOL_ALLOWLIST", "BTCUSDT".to_string())
+                .split(',')
+                .map(|s| s.trim().to_uppercase())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            duplicate_window: Duration::seconds(env_or("DUPLICATE_WINDOW_SECS", 5)),
+        }
+    }
+}
+
+/// Why an order was stopped before leaving the engine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreTradeReject {
+    SymbolNotAllowed(String),
+    MaxNotional { notional: f64, max: f64 },
+    PriceCollar { price: f64, reference: f64, max_pct: f64 },
+    FatFinger { qty: f64, max: f64 },
+    NothingToSell,
+    BelowMinimum { qty: f64, notional: f64 },
+    Duplicate { side: Side, secs: i64 },
+}
+
+impl fmt::Display for PreTradeReject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreTradeReject::SymbolNotAllowed(s) => write!(f, "symbol {} not in allow-list", s),
+            PreTradeReject::MaxNotional { notional, max } => write!(f, "notional ${:.2} over ${:.2} limit", notional, max),
+            PreTradeReject::PriceCollar { price, reference, max_pct } => write!(f, "price {:.2} more than {:.2}% from {:.2}", price, max_pct, reference),
+            PreTradeReject::FatFinger { qty, max } => write!(f, "quantity {:.5} over {:.5} limit", qty, max),
+            PreTradeReject::NothingToSell => write!(f, "spot has no position to sell"),
+            PreTradeReject::BelowMinimum { qty, notional } => write!(f, "quantity {:.5} (${:.2}) below exchange minimum", qty, notional),
+            PreTradeReject::Duplicate { side, secs } => write!(f, "duplicate {} within {}s", side.label(), secs),
+        }
+    }
+}
+
+/// The last gate before an order reaches an executor. Also fixes the order's quantity
+/// and price to the exchange's lot and tick sizes.
+pub struct PreTradeChecks {
+    pub limits: PreTradeLimits,
+    pub rules: SymbolRules,
+    mode: SimMode,
+    recent: VecDeque<(DateTime<Utc>, String, Side)>,
+}
+
+impl PreTradeChecks {
+    pub fn new(limits: PreTradeLimits, rules: SymbolRules, mode: SimMode) -> Self {
+        Self { limits, rules, mode, recent: VecDeque::new() }
+    }
+
+    /// `market_price` is the collar's reference: the L2 mid when a book is live, else the
+    /// latest trade. Without a book the collar only catches orders priced off an older tick
+    /// (queued or timer intents); one priced off the current tick always passes it.
+    /// `now` is tick time, so duplicate detection behaves the same in replays.
+    pub fn check(&mut self, order: &mut OrderRequest, market_price: f64, now: DateTime<Utc>) -> Result<(), PreTradeReject> {
+        let limits = &self.limits;
+        if !limits.symbols.contains(&order.symbol) {
+            return Err(PreTradeReject::SymbolNotAllowed(order.symbol.clone()));
+        }
+        if order.notional > limits.max_notional {
+            return Err(PreTradeReject::MaxNotional { notional: order.notional, max: limits.max_notional });
+        }
+        if market_price > 0.0 && ((order.price - market_price) / market_price).abs() * 100.0 > limits.price_collar_pct {
+            return Err(PreTradeReject::PriceCollar { price: order.price, reference: market_price, max_pct: limits.price_collar_pct });
+        }
+
+        order.price = self.rules.round_price(order.price);
+        let side = order.side();
+        // Binary options have a stake, not a quantity
+        if self.mode != SimMode::Binary {
+            let raw = match (self.mode, side) {
+                (SimMode::Spot, Side::Sell) if order.close_quantity <= 0.0 => return Err(PreTradeReject::NothingToSell),
+                (SimMode::Spot, Side::Sell) => order.close_quantity,
+                _ => order.close_quantity + order.notional / order.price,
+            };
+            let qty = self.rules.round_qty(raw);
+            let max = limits.max_qty.min(self.rules.max_qty);
+            if qty > max {
+                return Err(PreTradeReject::FatFinger { qty, max });
+            }
+            if qty < self.rules.min_qty || qty * order.price < self.rules.min_notional {
+                return Err(PreTradeReject::BelowMinimum { qty, notional: qty * order.price });
+            }
+            order.quantity = qty;
+        }
+
+        while self.recent.front().is_some_and(|(t, _, _)| now - *t > limits.duplicate_window) {
+            self.recent.pop_front();
+        }
+        if self.recent.iter().any(|(_, symbol, s)| *symbol == order.symbol && *s == side) {
+            return Err(PreTradeReject::Duplicate { side, secs: limits.duplicate_window.num_seconds() });
+        }
+        self.recent.push_back((now, order.symbol.clone(), side));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checks() -> PreTradeChecks {
+        let limits = PreTradeLimits {
+            max_notional: 5000.0,
+            price_collar_pct: 1.0,
+            max_qty: 0.5,
+            symbols: HashSet::from(["BTCUSDT".to_string()]),
+            duplicate_window: Duration::seconds(5),
+        };
+        let rules = SymbolRules {
+            symbol: "BTCUSDT".to_string(),
+            tick_size: 0.01,
+            step_size: 0.00001,
+            min_qty: 0.00001,
+            max_qty: f64::INFINITY,
+            min_notional: 5.0,
+        };
+        PreTradeChecks::new(limits, rules, SimMode::Perpetual)
+    }
+
+    fn order(price: f64) -> OrderRequest {
+        OrderRequest {
+            trade_id: 1,
+            symbol: "BTCUSDT".to_string(),
+            direction: "UP".to_string(),
+            price,
+            stake_pct: 1.0,
+            notional: 100.0,
+            close_quantity: 0.0,
+            quantity: 0.0,
+        }
+    }
+
+    #[test]
+    fn collar_rejects_an_off_market_order() {
+        let mut checks = checks();
+        let rejected = checks.check(&mut order(50_600.0), 50_000.0, Utc::now());
+        assert_eq!(rejected, Err(PreTradeReject::PriceCollar { price: 50_600.0, reference: 50_000.0, max_pct: 1.0 }));
+        assert!(checks.check(&mut order(49_600.0), 50_000.0, Utc::now()).is_ok());
+    }
+
+    #[test]
+    fn rounds_an_accepted_order_to_the_lot_step() {
+        let mut order = order(50_000.004);
+        assert!(checks().check(&mut order, 50_000.0, Utc::now()).is_ok());
+        assert_eq!((order.price, order.quantity), (50_000.0, 0.002));
+    }
+
+    #[test]
+    fn duplicates_are_timed_by_the_given_tick() {
+        let mut checks = checks();
+        let t0: DateTime<Utc> = "2026-01-01T10:00:00Z".parse().unwrap();
+        assert!(checks.check(&mut order(50_000.0), 50_000.0, t0).is_ok());
+        let again = checks.check(&mut order(50_000.0), 50_000.0, t0 + Duration::seconds(5));
+        assert_eq!(again, Err(PreTradeReject::Duplicate { side: Side::Buy, secs: 5 }));
+        assert!(checks.check(&mut order(50_000.0), 50_000.0, t0 + Duration::seconds(6)).is_ok());
+    }
+}
@@ -0,0 +1,382 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+use crate::bars::Bar;
+use crate::config::env_or;
+use crate::model::TradeData;
+
+const QUEUE_CAPACITY: usize = 10_000;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Where a tick came from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TickSource {
+    CoinCap,   // Real price from the REST API
+    Synthetic, // Safety-net random walk while the API was unreachable
+}
+
+/// One line of a recording: the tick as the engine saw it, plus provenance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedTick {
+    pub seq: u64,                  // Per-session, gap-free
+    pub session: String,           // Recorder start time; ties files of one run together
+    pub received: DateTime<Utc>,   // Wall clock when the recorder saw it
+    pub source: TickSource,
+    pub tick: TradeData,
+}
+
+/// One line of a bar file, written next to the tick file of the same part.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedBar {
+    pub session: String,
+    pub bar: Bar,
+}
+
+/// What the recorder thread is asked to write.
+enum Entry {
+    Tick(TickSource, TradeData),
+    Bar(Box<Bar>),
+    Close, // Finish the files and stop, even while feed handles are still alive
+}
+
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    pub dir: String,
+    pub rotate_every: Duration,
+    pub max_bytes: u64, // Uncompressed bytes per file
+}
+
+impl RecorderConfig {
+    /// `None` unless `RECORD_TICKS=true`. Also reads `RECORD_DIR`, `RECORD_ROTATE_MINS` and `RECORD_MAX_MB`.
+    pub fn from_env() -> Option<Self> {
+        if !env_or("RECORD_TICKS", false) { return None; }
+        Some(Self {
+            dir: env_or("RECORD_DIR", "recordings".to_string()),
+            rotate_every: Duration::from_secs(env_or("RECORD_ROTATE_MINS", 60u64) * 60),
+            max_bytes: env_or("RECORD_MAX_MB", 100u64) * 1024 * 1024,
+        })
+    }
+}
+
+/// Handle to the recorder thread. Recording only enqueues, so a slow disk never stalls the feed.
+#[derive(Clone)]
+pub struct TickRecorder {
+    tx: SyncSender<Entry>,
+}
+
+impl TickRecorder {
+    /// The returned `RecorderThread` belongs to the shutdown path, which must `close` it
+    /// for the last file to get its gzip trailer.
+    pub fn spawn(config: RecorderConfig) -> anyhow::Result<(Self, RecorderThread)> {
+        fs::create_dir_all(&config.dir)?;
+        let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let session = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        info!("🎙️ TICK RECORDER ON: {} (session {})", config.dir, session);
+        let writer = thread::Builder::new()
+            .name("tick-recorder".to_string())
+            .spawn(move || RecorderWriter::new(config, session).run(rx))?;
+        Ok((Self { tx: tx.clone() }, RecorderThread { tx, writer }))
+    }
+
+    pub fn record(&self, source: TickSource, tick: &TradeData) {
+        match self.tx.try_send(Entry::Tick(source, *tick)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => warn!("⚠️ RECORDER QUEUE FULL: Tick dropped, recording has a gap"),
+            Err(TrySendError::Disconnected(_)) => error!("TICK RECORDER DIED: Tick dropped"),
+        }
+    }
+
+    /// Closed bars go to a `bars-*` file beside the ticks.
+    pub fn record_bar(&self, bar: &Bar) {
+        match self.tx.try_send(Entry::Bar(Box::new(bar.clone()))) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => warn!("⚠️ RECORDER QUEUE FULL: {} bar dropped", bar.kind),
+            Err(TrySendError::Disconnected(_)) => error!("TICK RECORDER DIED: Bar dropped"),
+        }
+    }
+}
+
+/// Owner of the recorder thread.
+pub struct RecorderThread {
+    tx: SyncSender<Entry>,
+    writer: thread::JoinHandle<()>,
+}
+
+impl RecorderThread {
+    /// Writes what is still queued, finishes the files and waits for the thread.
+    pub fn close(self) {
+        let Self { tx, writer } = self;
+        // Queued behind every earlier entry; a dead writer has nothing left to finish
+        let _ = tx.send(Entry::Close);
+        if writer.join().is_err() {
+            error!("TICK RECORDER PANICKED: The last file may be truncated");
+        } else {
+            info!("🎙️ TICK RECORDER CLOSED");
+        }
+    }
+}
+
+struct RecorderWriter {
+    config: RecorderConfig,
+    session: String,
+    seq: u64,
+    part: u32,
+    file: Option<GzEncoder<File>>,
+    bar_file: Option<GzEncoder<File>>,
+    opened_at: Instant,
+    bytes: u64,
+}
+
+impl RecorderWriter {
+    fn new(config: RecorderConfig, session: String) -> Self {
+        Self { config, session, seq: 0, part: 0, file: None, bar_file: None, opened_at: Instant::now(), bytes: 0 }
+    }
+
+    fn run(mut self, rx: Receiver<Entry>) {
+        let mut last_flush = Instant::now();
+        loop {
+            match rx.recv_timeout(FLUSH_INTERVAL) {
+                Ok(Entry::Tick(source, tick)) => {
+                    if let Err(e) = self.write(source, tick) {
+                        error!("TICK RECORDER WRITE FAILED: {}", e);
+                        self.file = None; // Start a fresh file on the next tick
+                    }
+                }
+                Ok(Entry::Bar(bar)) => {
+                    if let Err(e) = self.write_bar(*bar) {
+                        error!("BAR RECORDER WRITE FAILED: {}", e);
+                    }
+                }
+                Ok(Entry::Close) | Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+
+            // Sync-flush so a crash loses at most a few seconds and the file stays readable
+            if last_flush.elapsed() >= FLUSH_INTERVAL {
+                for file in [self.file.as_mut(), self.bar_file.as_mut()].into_iter().flatten() {
+                    if let Err(e) = file.flush() {
+                        error!("TICK RECORDER FLUSH FAILED: {}", e);
+                    }
+                }
+                last_flush = Instant::now();
+            }
+        }
+        self.close();
+    }
+
+    fn write(&mut self, source: TickSource, tick: TradeData) -> anyhow::Result<()> {
+        let due = self.opened_at.elapsed() >= self.config.rotate_every || self.bytes >= self.config.max_bytes;
+        if self.file.is_none() || due {
+            self.rotate()?;
+        }
+
+        self.seq += 1;
+        let rec = RecordedTick { seq: self.seq, session: self.session.clone(), received: Utc::now(), source, tick };
+        let line = serde_json::to_string(&rec)? + "\n";
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(line.as_bytes())?;
+        }
+        self.bytes += line.len() as u64;
+        Ok(())
+    }
+
+    /// Bars share the tick file's part, so they start once the first tick has been written.
+    fn write_bar(&mut self, bar: Bar) -> anyhow::Result<()> {
+        let Some(file) = self.bar_file.as_mut() else { return Ok(()) };
+        let rec = RecordedBar { session: self.session.clone(), bar };
+        let line = serde_json::to_string(&rec)? + "\n";
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> anyhow::Result<()> {
+        self.close();
+        self.part += 1;
+        let dir = PathBuf::from(&self.config.dir);
+        let path = dir.join(format!("ticks-{}-{:04}.jsonl.gz", self.session, self.part));
+        self.file = Some(GzEncoder::new(File::create(&path)?, Compression::default()));
+        let bar_path = dir.join(format!("bars-{}-{:04}.jsonl.gz", self.session, self.part));
+        self.bar_file = Some(GzEncoder::new(File::create(bar_path)?, Compression::default()));
+        self.opened_at = Instant::now();
+        self.bytes = 0;
+        info!("🎙️ RECORDING TO {}", path.display());
+        Ok(())
+    }
+
+    /// Writes the gzip trailers and syncs the finished files.
+    fn close(&mut self) {
+        for encoder in [self.file.take(), self.bar_file.take()].into_iter().flatten() {
+            match encoder.finish() {
+                Ok(file) => { let _ = file.sync_all(); }
+                Err(e) => error!("TICK RECORDER CLOSE FAILED: {}", e),
+            }
+        }
+    }
+}
+
+/// Streams a recording back one tick at a time: a single `.jsonl`/`.jsonl.gz` file, or every
+/// such tick file in a directory in name order (names sort by session, then part). Bar files are skipped.
+pub fn read_recording(path: &str) -> anyhow::Result<RecordingReader> {
+    let path = Path::new(path);
+    let files = if path.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(path)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.to_string_lossy().ends_with(".jsonl") || p.to_string_lossy().ends_with(".jsonl.gz"))
+            .filter(|p| !p.file_name().is_some_and(|n| n.to_string_lossy().starts_with("bars-")))
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+    Ok(RecordingReader { files: files.into_iter(), current: None, line: String::new() })
+}
+
+/// A tick file being read and how many ticks it has yielded.
+struct OpenRecording {
+    path: PathBuf,
+    reader: Box<dyn BufRead + Send>,
+    ticks: u64,
+}
+
+/// Yields the ticks of a recording in order, holding only one line in memory.
+pub struct RecordingReader {
+    files: std::vec::IntoIter<PathBuf>,
+    current: Option<OpenRecording>,
+    line: String,
+}
+
+impl Iterator for RecordingReader {
+    type Item = anyhow::Result<RecordedTick>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(file) = self.current.as_mut() else {
+                let path = self.files.next()?;
+                let raw = match File::open(&path) {
+                    Ok(raw) => raw,
+                    Err(e) => return Some(Err(anyhow::anyhow!("{}: {}", path.display(), e))),
+                };
+                let reader: Box<dyn BufRead + Send> = if path.extension().is_some_and(|e| e == "gz") {
+                    Box::new(BufReader::new(MultiGzDecoder::new(raw)))
+                } else {
+                    Box::new(BufReader::new(raw))
+                };
+                self.current = Some(OpenRecording { path, reader, ticks: 0 });
+                continue;
+            };
+
+            self.line.clear();
+            // A file cut off by a crash ends in a torn line or a missing gzip trailer
+            let parsed = match file.reader.read_line(&mut self.line) {
+                Ok(0) => {
+                    self.current = None;
+                    continue;
+                }
+                Ok(_) => serde_json::from_str::<RecordedTick>(self.line.trim_end()).map_err(anyhow::Error::from),
+                Err(e) => Err(e.into()),
+            };
+            match parsed {
+                Ok(rec) => {
+                    file.ticks += 1;
+                    return Some(Ok(rec));
+                }
+                Err(e) => {
+                    warn!("⚠️ RECORDING {} TRUNCATED after {} ticks: {}", file.path.display(), file.ticks, e);
+                    self.current = None;
+                }
+            }
+        }
+    }
+}
+
+/// Replay source: feeds a recording into the engine in place of the live stream.
+/// `speed` 1.0 keeps the original pacing, 10.0 is ten times faster, 0 means no waiting.
+/// The file is streamed on a blocking thread, so a long recording never sits in memory.
+pub async fn start_replay_stream(path: String, speed: f64, tx: Sender<TradeData>) -> anyhow::Result<()> {
+    tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+        info!("⏪ REPLAYING {} (speed {}x)", path, speed);
+        let mut prev: Option<DateTime<Utc>> = None;
+        let mut count = 0u64;
+        for rec in read_recording(&path)? {
+            let rec = rec?;
+            if let Some(prev) = prev.filter(|_| speed > 0.0) {
+                let gap = (rec.tick.timestamp - prev).to_std().unwrap_or_default();
+                thread::sleep(gap.div_f64(speed));
+            }
+            prev = Some(rec.tick.timestamp);
+            if tx.blocking_send(rec.tick).is_err() { break; }
+            count += 1;
+        }
+        info!("⏪ REPLAY FINISHED: {} ticks", count);
+        Ok(())
+    }).await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn line(seq: u64) -> String {
+        let tick = TradeData {
+            timestamp: Utc.timestamp_millis_opt(1_700_000_000_000 + seq as i64).unwrap(),
+            price: 100.0 + seq as f64,
+            quantity: 1.0,
+            is_buyer_maker: false,
+        };
+        let rec = RecordedTick { seq, session: "s".to_string(), received: tick.timestamp, source: TickSource::CoinCap, tick };
+        serde_json::to_string(&rec).unwrap() + "\n"
+    }
+
+    #[test]
+    fn streams_files_in_order_and_stops_at_a_torn_line() {
+        let dir = std::env::temp_dir().join(format!("recording-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut gz = GzEncoder::new(File::create(dir.join("ticks-s-0001.jsonl.gz")).unwrap(), Compression::default());
+        gz.write_all((line(1) + &line(2)).as_bytes()).unwrap();
+        gz.finish().unwrap();
+        fs::write(dir.join("ticks-s-0002.jsonl"), line(3) + "{\"seq\":4,\"sess\n").unwrap();
+        fs::write(dir.join("bars-s-0001.jsonl"), "not a tick\n").unwrap();
+
+        let seqs: Vec<u64> = read_recording(dir.to_str().unwrap()).unwrap().map(|r| r.unwrap().seq).collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn close_finishes_the_file_while_the_feed_still_holds_a_handle() {
+        let dir = std::env::temp_dir().join(format!("recorder-close-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = RecorderConfig { dir: dir.display().to_string(), rotate_every: Duration::from_secs(3600), max_bytes: u64::MAX };
+        let (feed, thread) = TickRecorder::spawn(config).unwrap();
+        for seq in 1..=3 {
+            let tick: RecordedTick = serde_json::from_str(&line(seq)).unwrap();
+            feed.record(TickSource::CoinCap, &tick.tick);
+        }
+        thread.close();
+
+        // A strict decoder fails on a missing gzip trailer
+        let path = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path())
+            .find(|p| p.file_name().unwrap().to_string_lossy().starts_with("ticks-")).unwrap();
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(File::open(path).unwrap()), &mut text).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        drop(feed);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
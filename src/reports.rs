@@ -0,0 +1,164 @@
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use log::warn;
+use crate::config::env_or;
+use crate::message::{self, Message};
+use crate::performance::PerformanceTracker;
+
+const CHART_WIDTH: u32 = 800;
+const CHART_HEIGHT: u32 = 400;
+const CHART_MARGIN: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Daily,
+    Weekly,
+}
+
+impl Period {
+    fn length(&self) -> Duration {
+        match self {
+            Period::Daily => Duration::days(1),
+            Period::Weekly => Duration::weeks(1),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Period::Daily => "DAILY REPORT",
+            Period::Weekly => "WEEKLY REPORT",
+        }
+    }
+}
+
+/// When summary reports go out, in UTC. Tracks what has fired so each slot sends once.
+pub struct ReportSchedule {
+    daily_at: Option<NaiveTime>,
+    weekly_at: Option<(Weekday, NaiveTime)>,
+    chart: bool,
+    last_daily: DateTime<Utc>,
+    last_weekly: DateTime<Utc>,
+}
+
+impl ReportSchedule {
+    /// Reads `REPORT_DAILY_AT` ("HH:MM", default 00:00), `REPORT_WEEKLY_AT` ("mon HH:MM",
+    /// default mon 00:00) and `REPORT_CHART` (attach an equity chart, default true).
+    /// An empty or "off" value disables that report.
+    pub fn from_env(now: DateTime<Utc>) -> Self {
+        let daily = env_or("REPORT_DAILY_AT", "00:00".to_string());
+        let weekly = env_or("REPORT_WEEKLY_AT", "mon 00:00".to_string());
+
+        let daily_at = parse_time(&daily);
+        let weekly_at = weekly.split_once(' ')
+            .and_then(|(day, time)| Some((day.parse::<Weekday>().ok()?, parse_time(time)?)));
+        for (key, raw, ok) in [("REPORT_DAILY_AT", &daily, daily_at.is_some()), ("REPORT_WEEKLY_AT", &weekly, weekly_at.is_some())] {
+            if !ok && !is_off(raw) {
+                warn!("⚠️ {}={:?} not understood, that report is disabled", key, raw);
+            }
+        }
+
+        Self { daily_at, weekly_at, chart: env_or("REPORT_CHART", true), last_daily: now, last_weekly: now }
+    }
+
+    /// Periods whose slot passed since the last call.
+    pub fn due(&mut self, now: DateTime<Utc>) -> Vec<Period> {
+        let mut due = Vec::new();
+        if let Some(at) = self.daily_at {
+            let slot = now.date_naive().and_time(at).and_utc();
+            if slot <= now && slot > self.last_daily {
+                self.last_daily = now;
+                due.push(Period::Daily);
+            }
+        }
+        if let Some((day, at)) = self.weekly_at {
+            let days_back = (7 + now.weekday().num_days_from_monday() - day.num_days_from_monday()) % 7;
+            let slot = (now.date_naive() - Duration::days(days_back as i64)).and_time(at).and_utc();
+            if slot <= now && slot > self.last_weekly {
+                self.last_weekly = now;
+                due.push(Period::Weekly);
+            }
+        }
+        due
+    }
+
+    /// Builds the report for the period ending `now`.
+    pub fn build(&self, period: Period, performance: &PerformanceTracker, now: DateTime<Utc>) -> Message {
+        let from = now - period.length();
+        let window = performance.since(from);
+        let title = format!("{} {} → {}", period.label(), from.format("%b %d %H:%M"), now.format("%b %d %H:%M UTC"));
+
+        let chart = if self.chart && window.equity_curve.len() >= 2 {
+            equity_chart(&window.equity_curve)
+                .map_err(|e| warn!("⚠️ EQUITY CHART FAILED: {}", e))
+                .ok()
+        } else {
+            None
+        };
+        message::period_report(&title, &window.report(), chart)
+    }
+}
+
+fn parse_time(raw: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(raw.trim(), "%H:%M").ok()
+}
+
+fn is_off(raw: &str) -> bool {
+    matches!(raw.trim().to_lowercase().as_str(), "" | "off" | "none" | "false")
+}
+
+/// Renders an equity curve as a PNG line chart: green above the starting equity,
+/// red below, with the starting level as a grey baseline.
+pub fn equity_chart(curve: &[(DateTime<Utc>, f64)]) -> anyhow::Result<Vec<u8>> {
+    let (w, h, m) = (CHART_WIDTH, CHART_HEIGHT, CHART_MARGIN);
+    let mut pixels = vec![255u8; (w * h * 3) as usize];
+
+    let t0 = curve[0].0.timestamp_millis() as f64;
+    let t1 = (curve[curve.len() - 1].0.timestamp_millis() as f64).max(t0 + 1.0);
+    let base = curve[0].1;
+    let lo = curve.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let hi = curve.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+    let pad = ((hi - lo) * 0.1).max(hi.abs() * 1e-4).max(1e-9);
+    let (lo, hi) = (lo - pad, hi + pad);
+
+    let x = |t: DateTime<Utc>| m as f64 + (t.timestamp_millis() as f64 - t0) / (t1 - t0) * (w - 2 * m) as f64;
+    let y = |e: f64| m as f64 + (hi - e) / (hi - lo) * (h - 2 * m) as f64;
+
+    // Frame and baseline
+    let grey = [200, 200, 200];
+    for (a, b) in [((m, m), (w - m, m)), ((m, h - m), (w - m, h - m)), ((m, m), (m, h - m)), ((w - m, m), (w - m, h - m))] {
+        line(&mut pixels, w, h, (a.0 as f64, a.1 as f64), (b.0 as f64, b.1 as f64), grey);
+    }
+    line(&mut pixels, w, h, (m as f64, y(base)), ((w - m) as f64, y(base)), grey);
+
+    // Step chart: equity only moves when a trade settles
+    for pair in curve.windows(2) {
+        let (t_a, e_a) = pair[0];
+        let (t_b, e_b) = pair[1];
+        let colour = |e: f64| if e >= base { [22, 163, 74] } else { [220, 38, 38] };
+        line(&mut pixels, w, h, (x(t_a), y(e_a)), (x(t_b), y(e_a)), colour(e_a));
+        line(&mut pixels, w, h, (x(t_b), y(e_a)), (x(t_b), y(e_b)), colour(e_b));
+    }
+
+    let mut png_bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_bytes, w, h);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(png_bytes)
+}
+
+/// Draws a 2px line by stepping along its longer axis.
+fn line(pixels: &mut [u8], w: u32, h: u32, from: (f64, f64), to: (f64, f64), rgb: [u8; 3]) {
+    let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.0) as u32;
+    for i in 0..=steps {
+        let f = i as f64 / steps as f64;
+        let px = from.0 + (to.0 - from.0) * f;
+        let py = from.1 + (to.1 - from.1) * f;
+        for (dx, dy) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+            let (cx, cy) = ((px + dx) as i64, (py + dy) as i64);
+            if cx >= 0 && cy >= 0 && (cx as u32) < w && (cy as u32) < h {
+                let idx = ((cy as u32 * w + cx as u32) * 3) as usize;
+                pixels[idx..idx + 3].copy_from_slice(&rgb);
+            }
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use crate::config::env_or;
+use crate::simulator::{ClosedTrade, PaperWallet};
+
+/// Hard limits enforced between the signal engine and the wallet. 0 disables a limit.
+#[derive(Debug, Clone, Copy)]
+pub struct RiskLimits {
+    pub max_positions: usize,
+    pub max_exposure_pct: f64,    // Open stake/notional as % of equity
+    pub daily_loss_pct: f64,      // From equity at the start of the UTC day
+    pub max_drawdown_pct: f64,    // From peak equity
+    pub max_consecutive_losses: u32,
+}
+
+impl RiskLimits {
+    /// Reads `RISK_MAX_POSITIONS`, `RISK_MAX_EXPOSURE_PCT`, `RISK_DAILY_LOSS_PCT`,
+    /// `RISK_MAX_DRAWDOWN_PCT` and `RISK_MAX_CONSECUTIVE_LOSSES`.
+    pub fn from_env() -> Self {
+        Self {
+            max_positions: env_or("RISK_MAX_POSITIONS", 3),
+            max_exposure_pct: env_or("RISK_MAX_EXPOSURE_PCT", 15.0),
+            daily_loss_pct: env_or("RISK_DAILY_LOSS_PCT", 5.0),
+            max_drawdown_pct: env_or("RISK_MAX_DRAWDOWN_PCT", 20.0),
+            max_consecutive_losses: env_or("RISK_MAX_CONSECUTIVE_LOSSES", 5),
+        }
+    }
+}
+
+/// Why a trade was not allowed through.
+#[derive(Debug, Clone)]
+pub enum RiskRejection {
+    Halted(String),
+    MaxPositions { open: usize, limit: usize },
+    MaxExposure { after_pct: f64, limit_pct: f64 },
+}
+
+impl fmt::Display for RiskRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskRejection::Halted(reason) => write!(f, "KILL SWITCH ACTIVE ({})", reason),
+            RiskRejection::MaxPositions { open, limit } => write!(f, "{} positions open (limit {})", open, limit),
+            RiskRejection::MaxExposure { after_pct, limit_pct } =>
+                write!(f, "exposure would be {:.1}% of equity (limit {:.1}%)", after_pct, limit_pct),
+        }
+    }
+}
+
+const SAVE_INTERVAL_SECS: i64 = 10; // Tick time between saves of a rising peak
+
+/// Progress towards the limits, kept across restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RiskState {
+    peak_equity: f64,
+    day: NaiveDate,
+    day_start_equity: f64,
+    consecutive_losses: u32,
+}
+
+/// Pre-trade gate plus a latched kill switch. Once tripped, the switch survives restarts
+/// (a latch file in the state dir) and only clears on a manual reset. Peak equity, the
+/// daily baseline and the loss streak are saved next to it, so a restart clears nothing.
+pub struct RiskManager {
+    pub limits: RiskLimits,
+    pub peak_equity: f64,
+    pub day: NaiveDate,
+    pub day_start_equity: f64,
+    pub consecutive_losses: u32,
+    pub halted: Option<String>,
+    latch_path: PathBuf,
+    state_path: PathBuf,
+    saved_at: DateTime<Utc>,
+    dirty: bool, // A new peak not saved yet
+}
+
+impl RiskManager {
+    pub fn new(limits: RiskLimits, wallet: &PaperWallet, state_dir: &str) -> Self {
+        let _ = fs::create_dir_all(state_dir);
+        let latch_path = PathBuf::from(state_dir).join("risk_halt");
+        let halted = fs::read_to_string(&latch_path).ok().map(|r| r.trim().to_string());
+        if let Some(reason) = &halted {
+            warn!("🛑 KILL SWITCH STILL LATCHED FROM PREVIOUS RUN: {}", reason);
+        }
+
+        let state_path = PathBuf::from(state_dir).join("risk_state.json");
+        let equity = wallet.equity();
+        let today = wallet.clock.date_naive();
+        let mut risk = Self {
+            limits,
+            peak_equity: equity,
+            day: today,
+            day_start_equity: equity,
+            consecutive_losses: 0,
+            halted,
+            latch_path,
+            state_path,
+            saved_at: wallet.clock,
+            dirty: false,
+        };
+        match fs::read_to_string(&risk.state_path).map(|raw| serde_json::from_str::<RiskState>(&raw)) {
+            Ok(Ok(saved)) => {
+                risk.peak_equity = saved.peak_equity.max(equity);
+                if saved.day == today {
+                    risk.day_start_equity = saved.day_start_equity;
+                }
+                risk.consecutive_losses = saved.consecutive_losses;
+                info!("🛡️ RISK STATE RESTORED | Peak: ${:.2} | Day start: ${:.2} | Loss streak: {}",
+                    risk.peak_equity, risk.day_start_equity, risk.consecutive_losses);
+            }
+            Ok(Err(e)) => warn!("⚠️ COULD NOT READ RISK STATE, LIMITS START FROM CURRENT EQUITY: {}", e),
+            Err(_) => {} // First run
+        }
+        risk.save(wallet.clock);
+        risk
+    }
+
+    /// Tracks peak equity and the daily baseline; trips on daily loss or drawdown.
+    /// Returns the reason when this call tripped the switch.
+    pub fn observe(&mut self, wallet: &PaperWallet) -> Option<String> {
+        let equity = wallet.equity();
+        let today = wallet.clock.date_naive();
+        if today != self.day {
+            self.day = today;
+            self.day_start_equity = equity;
+            self.save(wallet.clock);
+        }
+        if equity > self.peak_equity {
+            self.peak_equity = equity;
+            self.dirty = true;
+        }
+        if self.dirty && wallet.clock - self.saved_at >= Duration::seconds(SAVE_INTERVAL_SECS) {
+            self.save(wallet.clock);
+        }
+
+        // A baseline at or below zero has nothing left to lose a percentage of
+        if self.day_start_equity > 0.0 {
+            let daily_loss = (self.day_start_equity - equity) / self.day_start_equity * 100.0;
+            if self.limits.daily_loss_pct > 0.0 && daily_loss >= self.limits.daily_loss_pct {
+                return self.trip(format!("daily loss {:.2}% >= {:.2}%", daily_loss, self.limits.daily_loss_pct));
+            }
+        }
+
+        if self.peak_equity > 0.0 {
+            let drawdown = (self.peak_equity - equity) / self.peak_equity * 100.0;
+            if self.limits.max_drawdown_pct > 0.0 && drawdown >= self.limits.max_drawdown_pct {
+                return self.trip(format!("drawdown {:.2}% from peak ${:.2}", drawdown, self.peak_equity));
+            }
+        }
+        None
+    }
+
+    /// Counts the loss streak; trips the consecutive-loss breaker.
+    pub fn on_close(&mut self, trade: &ClosedTrade) -> Option<String> {
+        if trade.pnl < 0.0 {
+            self.consecutive_losses += 1;
+        } else if trade.pnl > 0.0 {
+            self.consecutive_losses = 0;
+        }
+        self.save(trade.close_time);
+
+        let limit = self.limits.max_consecutive_losses;
+        if limit > 0 && self.consecutive_losses >= limit {
+            return self.trip(format!("{} consecutive losses", self.consecutive_losses));
+        }
+        None
+    }
+
+    /// Decides whether a new trade staking `stake_pct` of balance may be opened.
+    pub fn check(&self, wallet: &PaperWallet, stake_pct: f64) -> Result<(), RiskRejection> {
+        if let Some(reason) = &self.halted {
+            return Err(RiskRejection::Halted(reason.clone()));
+        }
+
+        let open = wallet.open_positions();
+        if self.limits.max_positions > 0 && open >= self.limits.max_positions {
+            return Err(RiskRejection::MaxPositions { open, limit: self.limits.max_positions });
+        }
+
+        let equity = wallet.equity();
+        let new_stake = wallet.balance * stake_pct / 100.0;
+        let after_pct = if equity > 0.0 { (wallet.exposure() + new_stake) / equity * 100.0 } else { f64::INFINITY };
+        if self.limits.max_exposure_pct > 0.0 && after_pct > self.limits.max_exposure_pct {
+            return Err(RiskRejection::MaxExposure { after_pct, limit_pct: self.limits.max_exposure_pct });
+        }
+
+        Ok(())
+    }
+
+    /// Trips the kill switch by hand. Returns false if it was already latched.
+    pub fn halt(&mut self, reason: String) -> bool {
+        self.trip(reason).is_some()
+    }
+
+    /// Clears the kill switch and its latch file.
+    pub fn reset(&mut self, wallet: &PaperWallet) {
+        self.halted = None;
+        self.consecutive_losses = 0;
+        self.peak_equity = wallet.equity();
+        self.day_start_equity = wallet.equity();
+        self.save(wallet.clock);
+        let _ = fs::remove_file(&self.latch_path);
+        info!("✅ KILL SWITCH RESET | Equity: ${:.2}", wallet.equity());
+    }
+
+    /// Atomically replaces the saved progress.
+    fn save(&mut self, now: DateTime<Utc>) {
+        let state = RiskState {
+            peak_equity: self.peak_equity,
+            day: self.day,
+            day_start_equity: self.day_start_equity,
+            consecutive_losses: self.consecutive_losses,
+        };
+        let tmp_path = self.state_path.with_extension("json.tmp");
+        let result = serde_json::to_string(&state)
+            .map_err(anyhow::Error::from)
+            .and_then(|raw| {
+                fs::write(&tmp_path, raw)?;
+                fs::rename(&tmp_path, &self.state_path)?;
+                Ok(())
+            });
+        match result {
+            Ok(()) => {
+                self.saved_at = now;
+                self.dirty = false;
+            }
+            Err(e) => error!("FAILED TO SAVE RISK STATE: {}", e),
+        }
+    }
+
+    fn trip(&mut self, reason: String) -> Option<String> {
+        if self.halted.is_some() { return None; }
+
+        error!("🛑 KILL SWITCH TRIPPED: {}", reason);
+        if let Err(e) = fs::write(&self.latch_path, &reason) {
+            error!("FAILED TO WRITE KILL SWITCH LATCH: {}", e);
+        }
+        self.halted = Some(reason.clone());
+        Some(reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::{ExitReason, PriceSource, SimMode};
+
+    fn state_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("risk-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn loss(wallet: &PaperWallet) -> ClosedTrade {
+        ClosedTrade {
+            trade_id: 1, direction: "UP".to_string(), reason: ExitReason::Expiry, entry_price: 100.0, exit_price: 99.0,
+            price_source: PriceSource::Fill, pnl: -10.0, open_time: wallet.clock, close_time: wallet.clock,
+        }
+    }
+
+    fn limits(daily_loss_pct: f64, max_drawdown_pct: f64, max_consecutive_losses: u32) -> RiskLimits {
+        RiskLimits { max_positions: 0, max_exposure_pct: 0.0, daily_loss_pct, max_drawdown_pct, max_consecutive_losses }
+    }
+
+    fn win(wallet: &PaperWallet) -> ClosedTrade {
+        ClosedTrade { pnl: 10.0, ..loss(wallet) }
+    }
+
+    #[test]
+    fn daily_loss_trips_from_the_day_start_equity() {
+        let dir = state_dir("daily");
+        let mut wallet = PaperWallet::new(SimMode::Binary);
+        wallet.balance = 10_000.0;
+        let mut risk = RiskManager::new(limits(5.0, 0.0, 0), &wallet, &dir);
+        wallet.balance = 9_501.0;
+        assert_eq!(risk.observe(&wallet), None);
+        wallet.balance = 9_500.0;
+        assert_eq!(risk.observe(&wallet).as_deref(), Some("daily loss 5.00% >= 5.00%"));
+
+        // The next UTC day starts a fresh baseline
+        let dir2 = state_dir("daily-next");
+        let mut risk = RiskManager::new(limits(5.0, 0.0, 0), &wallet, &dir2);
+        wallet.clock += Duration::days(1);
+        wallet.balance = 9_100.0;
+        assert_eq!(risk.observe(&wallet), None);
+        assert_eq!(risk.day_start_equity, 9_100.0);
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&dir2).unwrap();
+    }
+
+    #[test]
+    fn drawdown_trips_from_the_peak() {
+        let dir = state_dir("drawdown");
+        let mut wallet = PaperWallet::new(SimMode::Binary);
+        wallet.balance = 10_000.0;
+        let mut risk = RiskManager::new(limits(0.0, 10.0, 0), &wallet, &dir);
+        wallet.balance = 12_000.0;
+        assert_eq!(risk.observe(&wallet), None);
+        wallet.balance = 10_900.0;
+        assert_eq!(risk.observe(&wallet), None);
+        wallet.balance = 10_800.0;
+        assert_eq!(risk.observe(&wallet).as_deref(), Some("drawdown 10.00% from peak $12000.00"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn consecutive_losses_trip_and_a_win_resets_the_streak() {
+        let dir = state_dir("streak");
+        let wallet = PaperWallet::new(SimMode::Binary);
+        let mut risk = RiskManager::new(limits(0.0, 0.0, 3), &wallet, &dir);
+        assert_eq!(risk.on_close(&loss(&wallet)), None);
+        assert_eq!(risk.on_close(&loss(&wallet)), None);
+        assert_eq!(risk.on_close(&win(&wallet)), None);
+        assert_eq!(risk.on_close(&loss(&wallet)), None);
+        assert_eq!(risk.on_close(&loss(&wallet)), None);
+        assert_eq!(risk.on_close(&loss(&wallet)).as_deref(), Some("3 consecutive losses"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn kill_switch_stays_latched_until_reset() {
+        let dir = state_dir("latch");
+        let mut wallet = PaperWallet::new(SimMode::Binary);
+        wallet.balance = 10_000.0;
+        let mut risk = RiskManager::new(limits(5.0, 0.0, 0), &wallet, &dir);
+        wallet.balance = 9_000.0;
+        assert!(risk.observe(&wallet).is_some());
+
+        // Recovering equity, another trip and a restart all leave it latched
+        wallet.balance = 11_000.0;
+        assert_eq!(risk.observe(&wallet), None);
+        assert!(!risk.halt("manual".to_string()));
+        let mut risk = RiskManager::new(limits(5.0, 0.0, 0), &wallet, &dir);
+        assert!(matches!(risk.check(&wallet, 1.0), Err(RiskRejection::Halted(r)) if r.starts_with("daily loss")));
+
+        risk.reset(&wallet);
+        assert!(risk.check(&wallet, 1.0).is_ok());
+        assert!(RiskManager::new(limits(5.0, 0.0, 0), &wallet, &dir).halted.is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn restart_keeps_peak_day_start_and_loss_streak() {
+        let dir = state_dir("restart");
+        let mut wallet = PaperWallet::new(SimMode::Binary);
+        wallet.balance = 10_000.0;
+        let mut risk = RiskManager::new(RiskLimits::from_env(), &wallet, &dir);
+        wallet.balance = 10_500.0;
+        wallet.clock += Duration::seconds(SAVE_INTERVAL_SECS);
+        risk.observe(&wallet);
+        wallet.balance = 10_300.0;
+        risk.on_close(&loss(&wallet));
+        risk.on_close(&loss(&wallet));
+
+        let restarted = RiskManager::new(RiskLimits::from_env(), &wallet, &dir);
+        assert_eq!(restarted.peak_equity, 10_500.0);
+        assert_eq!(restarted.day_start_equity, 10_000.0);
+        assert_eq!(restarted.consecutive_losses, 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn zero_equity_does_not_divide_by_zero() {
+        let dir = state_dir("zero");
+        let mut wallet = PaperWallet::new(SimMode::Binary);
+        wallet.balance = 0.0;
+        let mut risk = RiskManager::new(RiskLimits::from_env(), &wallet, &dir);
+        assert_eq!(risk.observe(&wallet), None);
+        assert!(matches!(risk.check(&wallet, 1.0), Err(RiskRejection::MaxExposure { .. })));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
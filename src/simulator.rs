@@ -1,85 +1,1014 @@
 use std::collections::VecDeque;
-use std::time::{Instant, Duration};
-use log::info;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use crate::config::env_or;
+use crate::fills::{FillModel, Liquidity, OrderBook};
+use crate::model::TradeData;
+
+// Perpetual funding is exchanged at 00:00, 08:00 and 16:00 UTC (Binance schedule)
+const FUNDING_INTERVAL_SECS: i64 = 8 * 60 * 60;
+const BINARY_EXPIRY_SECS: i64 = 60;
+// Ticks kept for expiry lookups, comfortably longer than a binary expiry
+const PRICE_HISTORY_SECS: i64 = 300;
+
+/// What kind of product the paper wallet executes signals as.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SimMode {
+    Binary,    // 60s binary options with a fixed 85% payout
+    Spot,      // Long-only linear position, DOWN signals only close
+    Perpetual, // Long/short linear position with funding
+}
+
+impl SimMode {
+    /// Reads `SIM_MODE` (binary | spot | perp). Defaults to binary.
+    pub fn from_env() -> Self {
+        match std::env::var("SIM_MODE").unwrap_or_default().to_lowercase().as_str() {
+            "spot" => SimMode::Spot,
+            "perp" | "perpetual" | "futures" => SimMode::Perpetual,
+            _ => SimMode::Binary,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SimMode::Binary => "BINARY",
+            SimMode::Spot => "SPOT",
+            SimMode::Perpetual => "PERP",
+        }
+    }
+}
+
+/// Why a simulated trade was closed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitReason {
+    Expiry,       // Binary option reached its 60s expiry
+    StopLoss,
+    TakeProfit,
+    TrailingStop,
+    TimeStop,
+    Signal,       // Closed by an opposite signal
+    KillSwitch,   // Flattened by a risk trip or /halt
+}
+
+impl ExitReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExitReason::Expiry => "EXPIRY",
+            ExitReason::StopLoss => "STOP LOSS",
+            ExitReason::TakeProfit => "TAKE PROFIT",
+            ExitReason::TrailingStop => "TRAILING STOP",
+            ExitReason::TimeStop => "TIME STOP",
+            ExitReason::Signal => "OPPOSITE SIGNAL",
+            ExitReason::KillSwitch => "KILL SWITCH",
+        }
+    }
+}
+
+/// Exit levels applied to every new linear position. A value of 0 disables that exit.
+#[derive(Debug, Clone, Copy)]
+pub struct ExitRules {
+    pub stop_loss_pct: f64,
+    pub take_profit_pct: f64,
+    pub trailing_pct: f64,
+    pub max_hold_secs: u64,
+}
+
+impl ExitRules {
+    /// Reads `STOP_LOSS_PCT`, `TAKE_PROFIT_PCT`, `TRAILING_STOP_PCT` and `MAX_HOLD_SECS`.
+    pub fn from_env() -> Self {
+        Self {
+            stop_loss_pct: env_or("STOP_LOSS_PCT", 0.5),
+            take_profit_pct: env_or("TAKE_PROFIT_PCT", 1.0),
+            trailing_pct: env_or("TRAILING_STOP_PCT", 0.0),
+            max_hold_secs: env_or("MAX_HOLD_SECS", 3600),
+        }
+    }
+}
+
+/// Which price a binary option settles against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettlementPolicy {
+    LastTrade,    // Last trade at or before expiry
+    Interpolated, // Linear between the last trade before and the first trade after expiry
+}
+
+/// What happens when the settlement price equals the strike.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TieRule {
+    Loss,   // Broker keeps the stake
+    Refund, // Stake returned, counted as a push
+}
+
+impl SettlementPolicy {
+    /// Reads `SETTLEMENT_PRICE` (last | interpolated). Defaults to last.
+    /// `SETTLEMENT_MAX_STALE_SECS` (10) bounds how old the last trade before expiry may be.
+    pub fn from_env() -> Self {
+        match std::env::var("SETTLEMENT_PRICE").unwrap_or_default().to_lowercase().as_str() {
+            "interpolated" | "interp" => SettlementPolicy::Interpolated,
+            _ => SettlementPolicy::LastTrade,
+        }
+    }
+}
+
+impl TieRule {
+    /// Reads `TIE_RULE` (loss | refund). Defaults to loss.
+    pub fn from_env() -> Self {
+        match std::env::var("TIE_RULE").unwrap_or_default().to_lowercase().as_str() {
+            "refund" | "push" => TieRule::Refund,
+            _ => TieRule::Loss,
+        }
+    }
+}
+
+/// Where a closed trade's exit price came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceSource {
+    Fill,                                                      // Simulated fill on a live tick
+    LastTrade { at: DateTime<Utc> },                           // Binary: last trade at or before expiry
+    Interpolated { before: DateTime<Utc>, after: DateTime<Utc> }, // Binary: between the bracketing trades
+    FirstAfter { at: DateTime<Utc> },                          // Binary: nothing fresh before expiry (feed gap, downtime)
+}
+
+impl PriceSource {
+    pub fn label(&self) -> String {
+        match self {
+            PriceSource::Fill => "FILL".to_string(),
+            PriceSource::LastTrade { at } => format!("LAST TRADE @ {}", at.format("%H:%M:%S%.3f")),
+            PriceSource::Interpolated { before, after } => format!("INTERPOLATED {} / {}",
+                before.format("%H:%M:%S%.3f"), after.format("%H:%M:%S%.3f")),
+            PriceSource::FirstAfter { at } => format!("FIRST TRADE AFTER @ {}", at.format("%H:%M:%S%.3f")),
+        }
+    }
+}
+
+/// Execution record for every simulated fill, entries and exits alike.
+#[derive(Debug, Clone)]
+pub struct FillRecord {
+    pub trade_id: u64,
+    pub time: DateTime<Utc>,
+    pub side: String,          // UP/DOWN for binary, BUY/SELL for linear
+    pub price: f64,
+    pub reference_price: f64,  // Tick price the fill model started from
+    pub quantity: f64,         // BTC (0 for binary)
+    pub stake: f64,            // Binary stake or linear notional, $
+    pub fee: f64,
+    pub liquidity: Liquidity,
+}
+
+/// Settlement record for any closed trade, binary or linear.
+#[derive(Debug, Clone)]
+pub struct ClosedTrade {
+    pub trade_id: u64,
+    pub direction: String,
+    pub reason: ExitReason,
+    pub entry_price: f64,
+    pub exit_price: f64,
+    pub price_source: PriceSource,
+    pub pnl: f64, // Net of fees and funding
+    pub open_time: DateTime<Utc>,
+    pub close_time: DateTime<Utc>,
+}
+
+/// Everything about a wallet that must survive a restart. Config (fees, exits, policies)
+/// is re-read from the environment instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletSnapshot {
+    pub mode: SimMode,
+    pub balance: f64,
+    pub active_trades: VecDeque<VirtualTrade>,
+    pub position: Option<LinearPosition>,
+    pub wins: u32,
+    pub losses: u32,
+    pub refunds: u32,
+    pub realized_pnl: f64,
+    pub fees_paid: f64,
+    pub slippage_paid: f64,
+    pub funding_paid: f64,
+    pub mark_price: f64,
+    pub avg_trade_qty: f64,
+    pub pending_orders: VecDeque<PendingOrder>,
+    pub price_history: VecDeque<(DateTime<Utc>, f64)>,
+    pub clock: DateTime<Utc>,
+}
 
 pub struct PaperWallet {
+    pub mode: SimMode,
     pub balance: f64,
     pub active_trades: VecDeque<VirtualTrade>,
+    pub position: Option<LinearPosition>,
     pub wins: u32,
     pub losses: u32,
+    pub refunds: u32, // Binary ties settled under `TieRule::Refund`
+    pub realized_pnl: f64,  // Gross price PnL of closed linear positions
+    pub fees_paid: f64,
+    pub slippage_paid: f64, // Cost of fills versus the reference tick price
+    pub funding_paid: f64,  // Positive = paid, negative = received
+    pub fill_model: FillModel,
+    pub book: Option<OrderBook>, // Latest L2 snapshot for `SLIPPAGE_MODEL=book`; not persisted
+    pub funding_rate: f64,  // Per funding interval, longs pay shorts when positive
+    pub mark_price: f64,
+    pub avg_trade_qty: f64, // EWMA of tick size, drives volume-dependent slippage
+    pub pending_orders: VecDeque<PendingOrder>,
+    pub settlement_policy: SettlementPolicy,
+    pub tie_rule: TieRule,
+    pub settlement_max_stale: Duration, // Older last trades wait for the first trade after expiry
+    pub price_history: VecDeque<(DateTime<Utc>, f64)>,
+    pub clock: DateTime<Utc>, // Timestamp of the latest tick; all wallet timing runs on tick time
+    pub exit_rules: ExitRules,
+    pub closed: Vec<ClosedTrade>, // Settled since the last `drain_closed`
+    pub fills: Vec<FillRecord>,   // Filled since the last `drain_fills`
+}
+
+/// A decision waiting out the configured fill latency.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingOrder {
+    #[serde(default)]
+    pub trade_id: u64,
+    pub direction: String,
+    pub stake_pct: f64,
+    pub decided_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VirtualTrade {
+    #[serde(default)]
+    pub id: u64,
     pub entry_price: f64,
     pub direction: String,
     pub stake: f64,
-    pub open_time: Instant,
+    pub open_time: DateTime<Utc>,
+    pub expiry: DateTime<Utc>,
+}
+
+/// A net linear (spot or perpetual) position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinearPosition {
+    #[serde(default)]
+    pub id: u64,           // Trade id of the order that opened it; scale-ins keep it
+    pub direction: String, // "UP" = Long, "DOWN" = Short
+    pub quantity: f64,     // BTC
+    pub avg_entry: f64,
+    pub fees_paid: f64,
+    pub funding_paid: f64,
+    pub open_time: DateTime<Utc>,
+    pub last_funding: DateTime<Utc>, // Last funding time settled, or the open time
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
+    pub trailing_pct: Option<f64>,
+    pub best_price: f64, // High-water mark (long) / low-water mark (short) for the trailing stop
+    pub max_hold_secs: Option<i64>,
+}
+
+impl LinearPosition {
+    fn side(&self) -> f64 {
+        if self.direction == "UP" { 1.0 } else { -1.0 }
+    }
+
+    pub fn notional(&self, mark: f64) -> f64 {
+        self.quantity * mark
+    }
+
+    pub fn unrealized_pnl(&self, mark: f64) -> f64 {
+        self.side() * (mark - self.avg_entry) * self.quantity
+    }
+
+    /// Re-anchors stop-loss and take-profit to the current average entry. The trailing
+    /// stop's `best_price` is left to the caller.
+    fn set_exit_levels(&mut self, rules: &ExitRules) {
+        let pct = |p: f64| if p > 0.0 { Some(p / 100.0) } else { None };
+        let side = self.side();
+        self.stop_loss = pct(rules.stop_loss_pct).map(|p| self.avg_entry * (1.0 - side * p));
+        self.take_profit = pct(rules.take_profit_pct).map(|p| self.avg_entry * (1.0 + side * p));
+        self.trailing_pct = pct(rules.trailing_pct);
+        self.max_hold_secs = (rules.max_hold_secs > 0).then_some(rules.max_hold_secs as i64);
+    }
+
+    /// Checks all exits against a new tick and returns the triggered reason with its fill price.
+    /// Stops are market orders and fill at the triggering tick (gaps fill worse than the level).
+    /// Take-profit is a resting limit and fills at its level, never better.
+    /// When several trigger on the same tick the most pessimistic one wins.
+    fn check_exit(&mut self, price: f64, now: DateTime<Utc>) -> Option<(ExitReason, f64)> {
+        let side = self.side();
+        // True when `price` has reached `level` moving against the position
+        let breached = |level: f64| side * (level - price) >= 0.0;
+
+        if let Some(sl) = self.stop_loss {
+            if breached(sl) {
+                return Some((ExitReason::StopLoss, price));
+            }
+        }
+
+        // Trailing level uses the best price seen *before* this tick
+        if let Some(trail) = self.trailing_pct {
+            let level = self.best_price * (1.0 - side * trail);
+            if breached(level) {
+                return Some((ExitReason::TrailingStop, price));
+            }
+        }
+        if side * (price - self.best_price) > 0.0 {
+            self.best_price = price;
+        }
+
+        if let Some(tp) = self.take_profit {
+            if side * (price - tp) >= 0.0 {
+                return Some((ExitReason::TakeProfit, tp));
+            }
+        }
+
+        if let Some(max_hold) = self.max_hold_secs {
+            if now - self.open_time >= Duration::seconds(max_hold) {
+                return Some((ExitReason::TimeStop, price));
+            }
+        }
+
+        None
+    }
 }
 
 impl PaperWallet {
-    pub fn new() -> Self {
+    pub fn new(mode: SimMode) -> Self {
+        // Binance VIP0 (maker, taker): 0.10%/0.10% spot, 0.02%/0.04% USDT-M futures
+        let (default_maker, default_taker) = match mode {
+            SimMode::Spot => (0.001, 0.001),
+            SimMode::Perpetual => (0.0002, 0.0004),
+            SimMode::Binary => (0.0, 0.0),
+        };
+
         Self {
+            mode,
             balance: 10_000.0, // Starting Balance
             active_trades: VecDeque::new(),
+            position: None,
             wins: 0,
             losses: 0,
+            refunds: 0,
+            realized_pnl: 0.0,
+            fees_paid: 0.0,
+            slippage_paid: 0.0,
+            funding_paid: 0.0,
+            fill_model: FillModel::from_env(default_maker, default_taker),
+            book: None,
+            funding_rate: env_or("FUNDING_RATE", 0.0001),
+            mark_price: 0.0,
+            avg_trade_qty: 0.0,
+            pending_orders: VecDeque::new(),
+            settlement_policy: SettlementPolicy::from_env(),
+            tie_rule: TieRule::from_env(),
+            settlement_max_stale: Duration::seconds(env_or("SETTLEMENT_MAX_STALE_SECS", 10)),
+            price_history: VecDeque::new(),
+            clock: Utc::now(),
+            exit_rules: ExitRules::from_env(),
+            closed: Vec::new(),
+            fills: Vec::new(),
         }
     }
 
-    pub fn open_trade(&mut self, direction: String, price: f64, stake_pct: f64) {
+    pub fn snapshot(&self) -> WalletSnapshot {
+        WalletSnapshot {
+            mode: self.mode,
+            balance: self.balance,
+            active_trades: self.active_trades.clone(),
+            position: self.position.clone(),
+            wins: self.wins,
+            losses: self.losses,
+            refunds: self.refunds,
+            realized_pnl: self.realized_pnl,
+            fees_paid: self.fees_paid,
+            slippage_paid: self.slippage_paid,
+            funding_paid: self.funding_paid,
+            mark_price: self.mark_price,
+            avg_trade_qty: self.avg_trade_qty,
+            pending_orders: self.pending_orders.clone(),
+            price_history: self.price_history.clone(),
+            clock: self.clock,
+        }
+    }
+
+    pub fn restore(&mut self, snap: WalletSnapshot) {
+        self.mode = snap.mode;
+        self.balance = snap.balance;
+        self.active_trades = snap.active_trades;
+        self.position = snap.position;
+        self.wins = snap.wins;
+        self.losses = snap.losses;
+        self.refunds = snap.refunds;
+        self.realized_pnl = snap.realized_pnl;
+        self.fees_paid = snap.fees_paid;
+        self.slippage_paid = snap.slippage_paid;
+        self.funding_paid = snap.funding_paid;
+        self.mark_price = snap.mark_price;
+        self.avg_trade_qty = snap.avg_trade_qty;
+        self.pending_orders = snap.pending_orders;
+        self.price_history = snap.price_history;
+        self.clock = snap.clock;
+    }
+
+    /// Settles binary trades that expired while the bot was down, against the last
+    /// recorded price at or before each expiry. A trade whose expiry fell into the downtime
+    /// has no fresh price yet and waits for the first tick after the restart.
+    pub fn settle_overdue(&mut self, now: DateTime<Utc>) {
+        if now > self.clock {
+            self.clock = now;
+        }
+        self.settle_expired();
+    }
+
+    /// Takes every fill since the last call, for journaling.
+    pub fn drain_fills(&mut self) -> Vec<FillRecord> {
+        std::mem::take(&mut self.fills)
+    }
+
+    /// Takes every trade settled since the last call, for journaling and alerts.
+    pub fn drain_closed(&mut self) -> Vec<ClosedTrade> {
+        std::mem::take(&mut self.closed)
+    }
+
+    /// Balance plus unrealised PnL of the open linear position.
+    pub fn equity(&self) -> f64 {
+        match &self.position {
+            Some(pos) => self.balance + pos.unrealized_pnl(self.mark_price),
+            None => self.balance,
+        }
+    }
+
+    /// Open binary trades, the linear position and orders still waiting to fill.
+    pub fn open_positions(&self) -> usize {
+        self.active_trades.len() + self.position.iter().count() + self.pending_orders.len()
+    }
+
+    /// Capital currently at risk: binary stakes plus linear notional at mark.
+    /// Pending orders count at their intended stake.
+    pub fn exposure(&self) -> f64 {
+        let stakes: f64 = self.active_trades.iter().map(|t| t.stake).sum();
+        let notional = self.position.as_ref().map_or(0.0, |p| p.notional(self.mark_price));
+        let pending: f64 = self.pending_orders.iter().map(|o| self.balance * o.stake_pct / 100.0).sum();
+        stakes + notional + pending
+    }
+
+    /// Executes a signal. With a non-zero fill latency the order is queued and filled
+    /// against the first tick at or after `decision + latency` (see `update`).
+    pub fn open_trade(&mut self, trade_id: u64, direction: String, price: f64, stake_pct: f64) {
+        if !self.fill_model.latency.is_zero() {
+            info!("⏱️ ORDER QUEUED | {} | Latency: {}ms", direction, self.fill_model.latency.as_millis());
+            self.pending_orders.push_back(PendingOrder {
+                trade_id,
+                direction,
+                stake_pct,
+                decided_at: self.clock,
+            });
+            return;
+        }
+        self.execute(trade_id, direction, price, stake_pct);
+    }
+
+    fn execute(&mut self, trade_id: u64, direction: String, price: f64, stake_pct: f64) {
+        match self.mode {
+            SimMode::Binary => self.open_binary(trade_id, direction, price, stake_pct),
+            SimMode::Spot | SimMode::Perpetual => self.open_linear(trade_id, direction, price, stake_pct),
+        }
+    }
+
+    fn open_binary(&mut self, trade_id: u64, direction: String, price: f64, stake_pct: f64) {
         let stake_amount = self.balance * (stake_pct / 100.0);
-        
+
+        self.fills.push(FillRecord {
+            trade_id,
+            time: self.clock,
+            side: direction.clone(),
+            price,
+            reference_price: price,
+            quantity: 0.0,
+            stake: stake_amount,
+            fee: 0.0,
+            liquidity: Liquidity::Taker,
+        });
         self.active_trades.push_back(VirtualTrade {
+            id: trade_id,
             entry_price: price,
             direction,
             stake: stake_amount,
-            open_time: Instant::now(),
+            open_time: self.clock,
+            expiry: self.clock + Duration::seconds(BINARY_EXPIRY_SECS),
         });
 
         info!("🎰 TRADE OPENED | Stake: ${:.2} | Entry: {:.2}", stake_amount, price);
     }
 
-    pub fn update(&mut self, current_price: f64) {
-        let mut new_wins = 0;
-        let mut new_losses = 0;
+    fn open_linear(&mut self, trade_id: u64, direction: String, price: f64, stake_pct: f64) {
+        self.mark_price = price;
+
+        // Opposite signal: close first, then flip (perps only)
+        if self.position.as_ref().is_some_and(|p| p.direction != direction) {
+            self.close_position(price, ExitReason::Signal);
+            if self.mode == SimMode::Spot {
+                return;
+            }
+        }
+
+        if self.mode == SimMode::Spot && direction == "DOWN" {
+            info!("🚫 SPOT: No position to sell, DOWN signal ignored");
+            return;
+        }
 
-        // Extract indices of expired trades
-        let mut finished_indices = Vec::new();
-        for (i, trade) in self.active_trades.iter().enumerate() {
-            if trade.open_time.elapsed() >= Duration::from_secs(60) {
-                finished_indices.push(i);
+        let quantity = self.balance * (stake_pct / 100.0) / price;
+        let side = if direction == "UP" { 1.0 } else { -1.0 };
+        let fill = self.fill_model.market(side, quantity, price, self.avg_trade_qty, self.book.as_ref());
+        let (fee, fill_price, quantity) = (fill.fee, fill.price, fill.quantity);
+        let notional = fill_price * quantity;
+        self.balance -= fee;
+        self.fees_paid += fee;
+        self.slippage_paid += (fill_price - price).abs() * quantity;
+
+        self.fills.push(FillRecord {
+            trade_id: self.position.as_ref().map_or(trade_id, |p| p.id),
+            time: self.clock,
+            side: if side > 0.0 { "BUY" } else { "SELL" }.to_string(),
+            price: fill_price,
+            reference_price: price,
+            quantity,
+            stake: notional,
+            fee,
+            liquidity: fill.liquidity,
+        });
+
+        match &mut self.position {
+            Some(pos) => {
+                // Scale in: volume-weighted average entry
+                let total_qty = pos.quantity + quantity;
+                pos.avg_entry = (pos.avg_entry * pos.quantity + fill_price * quantity) / total_qty;
+                pos.quantity = total_qty;
+                pos.fees_paid += fee;
+                pos.set_exit_levels(&self.exit_rules);
+                // The trail restarts from the new fill, not the high of the smaller position
+                pos.best_price = fill_price;
+                info!("➕ POSITION INCREASED | {} {:.5} BTC | Avg Entry: {:.2} | Fee: ${:.2}",
+                    pos.direction, pos.quantity, pos.avg_entry, fee);
+            }
+            None => {
+                let now = self.clock;
+                info!("📈 POSITION OPENED | {} {:.5} BTC @ {:.2} | Notional: ${:.2} | Fee: ${:.2}",
+                    direction, quantity, fill_price, notional, fee);
+                let mut pos = LinearPosition {
+                    id: trade_id,
+                    direction,
+                    quantity,
+                    avg_entry: fill_price,
+                    fees_paid: fee,
+                    funding_paid: 0.0,
+                    open_time: now,
+                    last_funding: now,
+                    stop_loss: None,
+                    take_profit: None,
+                    trailing_pct: None,
+                    best_price: fill_price,
+                    max_hold_secs: None,
+                };
+                pos.set_exit_levels(&self.exit_rules);
+                self.position = Some(pos);
             }
         }
+    }
+
+    /// Kill switch: drops queued orders and closes the linear position at the mark.
+    /// Binary trades cannot be closed early and still settle at expiry.
+    pub fn flatten(&mut self) {
+        if !self.pending_orders.is_empty() {
+            warn!("🛑 {} QUEUED ORDERS DROPPED by the kill switch", self.pending_orders.len());
+            self.pending_orders.clear();
+        }
+        self.close_position(self.mark_price, ExitReason::KillSwitch);
+    }
+
+    /// Closes the whole linear position, realising PnL net of fees and funding.
+    /// Take-profit rests as a maker limit at `price`; every other exit is a taker market order.
+    fn close_position(&mut self, price: f64, reason: ExitReason) {
+        let Some(pos) = self.position.take() else { return };
+
+        let fill = if reason == ExitReason::TakeProfit {
+            self.fill_model.limit(pos.quantity, price)
+        } else {
+            self.fill_model.market(-pos.side(), pos.quantity, price, self.avg_trade_qty, self.book.as_ref())
+        };
+        let exit_price = fill.price;
+        let gross = pos.unrealized_pnl(exit_price);
+        let exit_fee = fill.fee;
+        let net = gross - pos.fees_paid - exit_fee - pos.funding_paid;
+        let liquidity = if fill.liquidity == Liquidity::Maker { "MAKER" } else { "TAKER" };
+
+        self.balance += gross - exit_fee;
+        self.realized_pnl += gross;
+        self.fees_paid += exit_fee;
+        self.slippage_paid += (exit_price - price).abs() * pos.quantity;
+
+        self.fills.push(FillRecord {
+            trade_id: pos.id,
+            time: self.clock,
+            side: if pos.side() > 0.0 { "SELL" } else { "BUY" }.to_string(),
+            price: exit_price,
+            reference_price: price,
+            quantity: pos.quantity,
+            stake: exit_price * pos.quantity,
+            fee: exit_fee,
+            liquidity: fill.liquidity,
+        });
+
+        if net > 0.0 {
+            self.wins += 1;
+            info!("🏆 POSITION CLOSED ({}, {}) | {} | Net: +${:.2} | Exit: {:.2} vs Entry: {:.2} | Held: {}s",
+                reason.label(), liquidity, pos.direction, net, exit_price, pos.avg_entry, (self.clock - pos.open_time).num_seconds());
+        } else {
+            self.losses += 1;
+            info!("💀 POSITION CLOSED ({}, {}) | {} | Net: -${:.2} | Exit: {:.2} vs Entry: {:.2} | Held: {}s",
+                reason.label(), liquidity, pos.direction, net.abs(), exit_price, pos.avg_entry, (self.clock - pos.open_time).num_seconds());
+        }
+
+        self.closed.push(ClosedTrade {
+            trade_id: pos.id,
+            direction: pos.direction,
+            reason,
+            entry_price: pos.avg_entry,
+            exit_price,
+            price_source: PriceSource::Fill,
+            pnl: net,
+            open_time: pos.open_time,
+            close_time: self.clock,
+        });
+    }
+
+    pub fn update(&mut self, tick: &TradeData) {
+        let current_price = tick.price;
+        self.clock = tick.timestamp;
+        self.mark_price = current_price;
+        self.avg_trade_qty = if self.avg_trade_qty == 0.0 {
+            tick.quantity
+        } else {
+            0.95 * self.avg_trade_qty + 0.05 * tick.quantity
+        };
+
+        self.price_history.push_back((tick.timestamp, current_price));
+        // Never prune past the oldest open binary trade, whatever the window
+        let window = self.clock - Duration::seconds(PRICE_HISTORY_SECS);
+        let horizon = self.active_trades.iter().map(|t| t.open_time).min().map_or(window, |t| t.min(window));
+        while self.price_history.front().is_some_and(|&(t, _)| t < horizon) {
+            self.price_history.pop_front();
+        }
 
-        // Process expired trades
-        for i in finished_indices.into_iter().rev() {
+        // Fill orders whose latency has elapsed at this tick's price
+        let latency = Duration::from_std(self.fill_model.latency).unwrap_or_default();
+        while self.pending_orders.front().is_some_and(|o| self.clock - o.decided_at >= latency) {
+            let order = self.pending_orders.pop_front().unwrap();
+            self.execute(order.trade_id, order.direction, current_price, order.stake_pct);
+        }
+
+        self.apply_funding(current_price);
+
+        // Linear exits are evaluated on every tick
+        if let Some((reason, fill)) = self.position.as_mut().and_then(|p| p.check_exit(current_price, tick.timestamp)) {
+            self.close_position(fill, reason);
+        }
+
+        self.settle_expired();
+    }
+
+    /// Settles every binary trade whose expiry is at or before the latest tick,
+    /// at the price prevailing at expiry rather than the tick that revealed it.
+    fn settle_expired(&mut self) {
+        let mut new_wins = 0;
+        let mut new_losses = 0;
+        let mut new_refunds = 0;
+
+        let mut i = 0;
+        while i < self.active_trades.len() {
+            let trade = &self.active_trades[i];
+            let settlement = if trade.expiry <= self.clock { self.settlement_price(trade.expiry) } else { None };
+            let Some((settle_price, price_source)) = settlement else {
+                i += 1;
+                continue;
+            };
             let trade = self.active_trades.remove(i).unwrap();
             let is_win = match trade.direction.as_str() {
-                "UP" => current_price > trade.entry_price,
-                "DOWN" => current_price < trade.entry_price,
+                "UP" => settle_price > trade.entry_price,
+                "DOWN" => settle_price < trade.entry_price,
                 _ => false,
             };
+            let is_tie = settle_price == trade.entry_price;
 
-            if is_win {
+            let pnl = if is_win {
                 let profit = trade.stake * 0.85; // 85% Payout
                 self.balance += profit;
                 new_wins += 1;
-                info!("🏆 WINNER | +${:.2} | Price: {:.2} vs Entry: {:.2}", profit, current_price, trade.entry_price);
+                info!("🏆 WINNER | +${:.2} | Price: {:.2} vs Entry: {:.2} | {}", profit, settle_price, trade.entry_price, price_source.label());
+                profit
+            } else if is_tie && self.tie_rule == TieRule::Refund {
+                new_refunds += 1;
+                info!("↩️ REFUND | Tie at {:.2} | {}", settle_price, price_source.label());
+                0.0
             } else {
                 self.balance -= trade.stake;
                 new_losses += 1;
-                info!("💀 LOSS | -${:.2} | Price: {:.2} vs Entry: {:.2}", trade.stake, current_price, trade.entry_price);
-            }
+                info!("💀 LOSS | -${:.2} | Price: {:.2} vs Entry: {:.2} | {}", trade.stake, settle_price, trade.entry_price, price_source.label());
+                -trade.stake
+            };
+
+            self.closed.push(ClosedTrade {
+                trade_id: trade.id,
+                direction: trade.direction,
+                reason: ExitReason::Expiry,
+                entry_price: trade.entry_price,
+                exit_price: settle_price,
+                price_source,
+                pnl,
+                open_time: trade.open_time,
+                close_time: trade.expiry,
+            });
         }
 
-        if new_wins > 0 || new_losses > 0 {
+        if new_wins > 0 || new_losses > 0 || new_refunds > 0 {
             self.wins += new_wins;
             self.losses += new_losses;
+            self.refunds += new_refunds;
             let total = self.wins + self.losses;
-            let win_rate = (self.wins as f64 / total as f64) * 100.0;
-            
-            info!("💰 WALLET UPDATE | Balance: ${:.2} | Win Rate: {:.1}% ({}/{})", 
-                self.balance, win_rate, self.wins, total);
+            let win_rate = if total > 0 { (self.wins as f64 / total as f64) * 100.0 } else { 0.0 };
+
+            info!("💰 WALLET UPDATE | Balance: ${:.2} | Win Rate: {:.1}% ({}/{}) | Refunds: {}",
+                self.balance, win_rate, self.wins, total, self.refunds);
+        }
+    }
+
+    /// Price prevailing at `expiry` under the settlement policy, and where it came from.
+    /// A last trade older than `settlement_max_stale` does not count: the first trade after
+    /// expiry is used instead, and until one arrives the trade stays open (None).
+    fn settlement_price(&self, expiry: DateTime<Utc>) -> Option<(f64, PriceSource)> {
+        let before = self.price_history.iter().rev()
+            .find(|(t, _)| *t <= expiry)
+            .filter(|(t, _)| expiry - *t <= self.settlement_max_stale);
+        let after = self.price_history.iter().find(|(t, _)| *t > expiry);
+
+        match (before, after, self.settlement_policy) {
+            (Some(&(t0, p0)), Some(&(t1, p1)), SettlementPolicy::Interpolated) if t0 < expiry => {
+                let w = (expiry - t0).num_milliseconds() as f64 / (t1 - t0).num_milliseconds() as f64;
+                Some((p0 + (p1 - p0) * w, PriceSource::Interpolated { before: t0, after: t1 }))
+            }
+            (Some(&(t0, p0)), _, _) => Some((p0, PriceSource::LastTrade { at: t0 })),
+            (None, Some(&(t1, p1)), _) => Some((p1, PriceSource::FirstAfter { at: t1 })),
+            (None, None, _) => None,
+        }
+    }
+
+    /// Settles perpetual funding for every funding time passed while the position was open.
+    fn apply_funding(&mut self, mark: f64) {
+        if self.mode != SimMode::Perpetual { return; }
+        let Some(pos) = self.position.as_mut() else { return };
+
+        loop {
+            let due = next_funding_after(pos.last_funding);
+            if self.clock < due { break; }
+            // Positive rate: longs pay, shorts receive
+            let payment = pos.side() * pos.notional(mark) * self.funding_rate;
+            self.balance -= payment;
+            self.funding_paid += payment;
+            pos.funding_paid += payment;
+            pos.last_funding = due;
+            info!("💸 FUNDING | {} | ${:+.2} | Rate: {:.4}%", pos.direction, -payment, self.funding_rate * 100.0);
+        }
+    }
+}
+
+/// First funding time on the fixed UTC schedule strictly after `t`.
+fn next_funding_after(t: DateTime<Utc>) -> DateTime<Utc> {
+    let next = (t.timestamp().div_euclid(FUNDING_INTERVAL_SECS) + 1) * FUNDING_INTERVAL_SECS;
+    DateTime::from_timestamp(next, 0).unwrap_or(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fills::{FeeSchedule, SlippageModel};
+
+    fn tick(ts: &str) -> TradeData {
+        TradeData { timestamp: ts.parse().unwrap(), price: 50_000.0, quantity: 0.01, is_buyer_maker: false }
+    }
+
+    fn perpetual_opened_at(ts: &str) -> PaperWallet {
+        let mut wallet = PaperWallet::new(SimMode::Perpetual);
+        wallet.fill_model.latency = std::time::Duration::ZERO;
+        wallet.funding_rate = 0.0001;
+        wallet.update(&tick(ts));
+        wallet.open_trade(1, "UP".to_string(), 50_000.0, 10.0);
+        wallet
+    }
+
+    /// A wallet that fills at the tick with no costs, so exit and settlement prices are exact.
+    fn costless(mode: SimMode, exit_rules: ExitRules) -> PaperWallet {
+        let mut wallet = PaperWallet::new(mode);
+        wallet.fill_model = FillModel {
+            fees: FeeSchedule { maker_rate: 0.0, taker_rate: 0.0 },
+            half_spread_bps: 0.0,
+            slippage: SlippageModel::FixedBps(0.0),
+            latency: std::time::Duration::ZERO,
+        };
+        wallet.funding_rate = 0.0;
+        wallet.exit_rules = exit_rules;
+        wallet
+    }
+
+    fn rules(stop_loss_pct: f64, take_profit_pct: f64, trailing_pct: f64, max_hold_secs: u64) -> ExitRules {
+        ExitRules { stop_loss_pct, take_profit_pct, trailing_pct, max_hold_secs }
+    }
+
+    fn at(secs: i64, price: f64) -> TradeData {
+        let start: DateTime<Utc> = "2026-01-01T10:00:00Z".parse().unwrap();
+        TradeData { timestamp: start + Duration::seconds(secs), price, quantity: 0.01, is_buyer_maker: false }
+    }
+
+    /// Opens `direction` at 100, feeds `prices` one second apart and returns the first exit.
+    fn exit_after(exit_rules: ExitRules, direction: &str, prices: &[f64]) -> Option<(ExitReason, f64)> {
+        let mut wallet = costless(SimMode::Perpetual, exit_rules);
+        wallet.update(&at(0, 100.0));
+        wallet.open_trade(1, direction.to_string(), 100.0, 10.0);
+        for (i, &price) in prices.iter().enumerate() {
+            wallet.update(&at(i as i64 + 1, price));
+            if let Some(closed) = wallet.drain_closed().pop() {
+                return Some((closed.reason, closed.exit_price));
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn latency_fills_at_the_first_tick_after_the_delay() {
+        let mut wallet = costless(SimMode::Perpetual, rules(0.0, 0.0, 0.0, 0));
+        wallet.fill_model.latency = std::time::Duration::from_millis(1500);
+        wallet.update(&at(0, 100.0));
+        wallet.open_trade(1, "UP".to_string(), 100.0, 10.0);
+        assert_eq!((wallet.pending_orders.len(), wallet.position.is_none()), (1, true));
+
+        wallet.update(&at(1, 101.0));
+        assert!(wallet.position.is_none());
+        wallet.update(&at(2, 102.0));
+        let fills = wallet.drain_fills();
+        assert_eq!(fills.iter().map(|f| (f.trade_id, f.price)).collect::<Vec<_>>(), vec![(1, 102.0)]);
+        assert_eq!(wallet.position.map(|p| p.avg_entry), Some(102.0));
+    }
+
+    #[test]
+    fn stop_loss_fills_at_the_triggering_tick() {
+        assert_eq!(exit_after(rules(1.0, 0.0, 0.0, 0), "UP", &[99.5, 98.9]), Some((ExitReason::StopLoss, 98.9)));
+        assert_eq!(exit_after(rules(1.0, 0.0, 0.0, 0), "DOWN", &[100.5, 101.2]), Some((ExitReason::StopLoss, 101.2)));
+    }
+
+    #[test]
+    fn take_profit_fills_at_its_level() {
+        assert_eq!(exit_after(rules(0.0, 1.0, 0.0, 0), "UP", &[100.5, 101.5]), Some((ExitReason::TakeProfit, 101.0)));
+        assert_eq!(exit_after(rules(0.0, 1.0, 0.0, 0), "DOWN", &[99.5, 98.7]), Some((ExitReason::TakeProfit, 99.0)));
+    }
+
+    #[test]
+    fn trailing_stop_follows_the_best_price() {
+        // 1% under the 105 high is 103.95; under the 100 entry it would be 99
+        assert_eq!(exit_after(rules(0.0, 0.0, 1.0, 0), "UP", &[105.0, 104.0, 103.9]), Some((ExitReason::TrailingStop, 103.9)));
+        // 1% over the 95 low is 95.95
+        assert_eq!(exit_after(rules(0.0, 0.0, 1.0, 0), "DOWN", &[95.0, 95.9, 96.0]), Some((ExitReason::TrailingStop, 96.0)));
+    }
+
+    #[test]
+    fn time_stop_closes_after_max_hold() {
+        let flat = [100.0; 60];
+        assert_eq!(exit_after(rules(0.0, 0.0, 0.0, 60), "UP", &flat[..59]), None);
+        assert_eq!(exit_after(rules(0.0, 0.0, 0.0, 60), "UP", &flat), Some((ExitReason::TimeStop, 100.0)));
+        assert_eq!(exit_after(rules(0.0, 0.0, 0.0, 60), "DOWN", &flat), Some((ExitReason::TimeStop, 100.0)));
+    }
+
+    #[test]
+    fn scale_in_restarts_the_trailing_stop() {
+        let mut wallet = costless(SimMode::Perpetual, rules(0.0, 0.0, 10.0, 0));
+        wallet.update(&at(0, 100.0));
+        wallet.open_trade(1, "UP".to_string(), 100.0, 10.0);
+        wallet.update(&at(1, 110.0));
+        wallet.update(&at(2, 105.0));
+        wallet.open_trade(2, "UP".to_string(), 105.0, 10.0);
+
+        // 10% under the old 110 high (99) would have stopped out here; under the 105 fill it is 94.5
+        wallet.update(&at(3, 95.0));
+        assert!(wallet.drain_closed().is_empty());
+        wallet.update(&at(4, 94.0));
+        assert_eq!(wallet.drain_closed().pop().map(|c| c.reason), Some(ExitReason::TrailingStop));
+    }
+
+    #[test]
+    fn flatten_closes_at_the_mark_and_drops_queued_orders() {
+        let mut wallet = costless(SimMode::Perpetual, rules(0.0, 0.0, 0.0, 0));
+        wallet.update(&at(0, 100.0));
+        wallet.open_trade(1, "UP".to_string(), 100.0, 10.0);
+        wallet.update(&at(1, 102.0));
+        wallet.fill_model.latency = std::time::Duration::from_secs(5);
+        wallet.open_trade(2, "UP".to_string(), 102.0, 10.0);
+
+        wallet.flatten();
+        assert!(wallet.position.is_none() && wallet.pending_orders.is_empty());
+        let closed = wallet.drain_closed().pop().unwrap();
+        assert_eq!((closed.reason, closed.exit_price), (ExitReason::KillSwitch, 102.0));
+    }
+
+    /// A binary UP trade opened at 100 at t=0, expiring at t=60.
+    fn binary_up() -> PaperWallet {
+        let mut wallet = costless(SimMode::Binary, rules(0.0, 0.0, 0.0, 0));
+        wallet.settlement_policy = SettlementPolicy::LastTrade;
+        wallet.tie_rule = TieRule::Loss;
+        wallet.settlement_max_stale = Duration::seconds(10);
+        wallet.update(&at(0, 100.0));
+        wallet.open_trade(1, "UP".to_string(), 100.0, 10.0);
+        wallet
+    }
+
+    fn settle(mut wallet: PaperWallet, ticks: &[(i64, f64)]) -> ClosedTrade {
+        for &(secs, price) in ticks {
+            wallet.update(&at(secs, price));
         }
+        let mut closed = wallet.drain_closed();
+        assert_eq!(closed.len(), 1, "exactly one settlement");
+        closed.remove(0)
+    }
+
+    #[test]
+    fn settles_at_the_last_trade_at_or_before_expiry() {
+        let t = |secs| at(secs, 0.0).timestamp;
+        let closed = settle(binary_up(), &[(59, 99.0), (61, 105.0)]);
+        assert_eq!((closed.exit_price, closed.price_source), (99.0, PriceSource::LastTrade { at: t(59) }));
+        assert_eq!((closed.pnl, closed.close_time), (-1_000.0, t(60)));
+
+        let closed = settle(binary_up(), &[(59, 99.0), (60, 101.0), (61, 95.0)]);
+        assert_eq!((closed.exit_price, closed.pnl), (101.0, 850.0));
+    }
+
+    #[test]
+    fn interpolates_between_the_bracketing_trades() {
+        let t = |secs| at(secs, 0.0).timestamp;
+        let mut wallet = binary_up();
+        wallet.settlement_policy = SettlementPolicy::Interpolated;
+        let closed = settle(wallet, &[(58, 99.0), (62, 103.0)]);
+        assert_eq!(closed.exit_price, 101.0);
+        assert_eq!(closed.price_source, PriceSource::Interpolated { before: t(58), after: t(62) });
+    }
+
+    #[test]
+    fn tie_rule_decides_a_settlement_at_the_strike() {
+        let closed = settle(binary_up(), &[(60, 100.0)]);
+        assert_eq!(closed.pnl, -1_000.0);
+
+        let mut wallet = binary_up();
+        wallet.tie_rule = TieRule::Refund;
+        wallet.update(&at(60, 100.0));
+        assert_eq!(wallet.drain_closed()[0].pnl, 0.0);
+        assert_eq!((wallet.balance, wallet.wins, wallet.losses, wallet.refunds), (10_000.0, 0, 0, 1));
+    }
+
+    #[test]
+    fn stale_last_trade_falls_back_to_the_first_trade_after_expiry() {
+        let t = |secs| at(secs, 0.0).timestamp;
+        // The feed went quiet after the 100 entry tick; 45s old is too stale to settle on
+        let closed = settle(binary_up(), &[(15, 100.0), (75, 104.0)]);
+        assert_eq!((closed.exit_price, closed.price_source), (104.0, PriceSource::FirstAfter { at: t(75) }));
+    }
+
+    #[test]
+    fn overdue_trade_waits_for_a_fresh_price() {
+        let mut wallet = binary_up();
+        wallet.update(&at(30, 101.0));
+        // Down from t=30 to t=3600: the pre-shutdown 101 says nothing about the t=60 expiry
+        wallet.settle_overdue(at(3_600, 0.0).timestamp);
+        assert!(wallet.drain_closed().is_empty());
+        assert_eq!(wallet.active_trades.len(), 1);
+
+        let closed = settle(wallet, &[(3_601, 99.0)]);
+        assert_eq!((closed.exit_price, closed.pnl), (99.0, -1_000.0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn funding_is_paid_at_the_fixed_utc_times() {
+        let mut wallet = perpetual_opened_at("2026-01-01T07:59:00Z");
+        wallet.update(&tick("2026-01-01T07:59:59Z"));
+        assert_eq!(wallet.funding_paid, 0.0);
+        wallet.update(&tick("2026-01-01T08:00:00Z"));
+        assert!(wallet.funding_paid > 0.0, "a long held over 08:00 pays funding");
+        let paid = wallet.funding_paid;
+        wallet.update(&tick("2026-01-01T08:30:00Z"));
+        assert_eq!(wallet.funding_paid, paid);
+    }
+
+    #[test]
+    fn funding_does_not_follow_the_open_time() {
+        let mut wallet = perpetual_opened_at("2026-01-01T08:00:30Z");
+        wallet.update(&tick("2026-01-01T08:59:00Z"));
+        assert_eq!(wallet.funding_paid, 0.0);
+    }
+
+    #[test]
+    fn next_funding_time_is_on_the_schedule() {
+        let at = |ts: &str| ts.parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(next_funding_after(at("2026-01-01T07:59:59Z")), at("2026-01-01T08:00:00Z"));
+        assert_eq!(next_funding_after(at("2026-01-01T08:00:00Z")), at("2026-01-01T16:00:00Z"));
+        assert_eq!(next_funding_after(at("2026-01-01T23:00:00Z")), at("2026-01-02T00:00:00Z"));
+    }
+}
@@ -0,0 +1,438 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, TimeZone, Utc};
+use log::{error, info, warn};
+use rusqlite::{params, Connection};
+use crate::calibration::SignalOutcome;
+use crate::model::{QuantumSignal, TradeData};
+use crate::simulator::{ClosedTrade, PaperWallet};
+
+const QUEUE_CAPACITY: usize = 10_000;
+const MAX_BATCH: usize = 1_000;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Schema history. Each entry runs once, in order; `PRAGMA user_version` tracks progress.
+const MIGRATIONS: &[&str] = &[
+    // v1: initial schema
+    "CREATE TABLE ticks (
+        ts_ms INTEGER NOT NULL,
+        price REAL NOT NULL,
+        quantity REAL NOT NULL,
+        is_buyer_maker INTEGER NOT NULL
+    );
+    CREATE INDEX idx_ticks_ts ON ticks(ts_ms);
+    CREATE TABLE signals (
+        trade_id INTEGER PRIMARY KEY,
+        ts_ms INTEGER NOT NULL,
+        direction TEXT NOT NULL,
+        price REAL NOT NULL,
+        confidence REAL NOT NULL,
+        prob_up REAL NOT NULL,
+        ofi REAL NOT NULL,
+        volatility REAL NOT NULL,
+        whale_confirmed INTEGER NOT NULL
+    );
+    CREATE TABLE trades (
+        trade_id INTEGER NOT NULL,
+        direction TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        entry_price REAL NOT NULL,
+        exit_price REAL NOT NULL,
+        pnl REAL NOT NULL,
+        open_ms INTEGER NOT NULL,
+        close_ms INTEGER NOT NULL,
+        price_source TEXT NOT NULL
+    );
+    CREATE INDEX idx_trades_close ON trades(close_ms);
+    CREATE TABLE wallet_snapshots (
+        ts_ms INTEGER NOT NULL,
+        balance REAL NOT NULL,
+        equity REAL NOT NULL,
+        wins INTEGER NOT NULL,
+        losses INTEGER NOT NULL,
+        open_positions INTEGER NOT NULL
+    );",
+    // v2: indicator features on signals
+    "ALTER TABLE signals ADD COLUMN feature_confirmed INTEGER;
+    ALTER TABLE signals ADD COLUMN ema_fast REAL;
+    ALTER TABLE signals ADD COLUMN ema_slow REAL;
+    ALTER TABLE signals ADD COLUMN rsi REAL;
+    ALTER TABLE signals ADD COLUMN macd_hist REAL;
+    ALTER TABLE signals ADD COLUMN bb_upper REAL;
+    ALTER TABLE signals ADD COLUMN bb_lower REAL;
+    ALTER TABLE signals ADD COLUMN atr REAL;
+    ALTER TABLE signals ADD COLUMN vwap REAL;",
+    // v3: every evaluated signal scored at its horizon
+    "CREATE TABLE signal_outcomes (
+        ts_ms INTEGER NOT NULL,
+        direction TEXT NOT NULL,
+        price REAL NOT NULL,
+        confidence REAL NOT NULL,
+        prob_up REAL NOT NULL,
+        whale_confirmed INTEGER NOT NULL,
+        feature_confirmed INTEGER NOT NULL,
+        passed INTEGER NOT NULL,
+        horizon_secs INTEGER NOT NULL,
+        exit_price REAL NOT NULL
+    );
+    CREATE INDEX idx_signal_outcomes_ts ON signal_outcomes(ts_ms);",
+    // v4: one row per trade id; replays and double recording used to duplicate them
+    "CREATE TABLE trades_v4 (
+        trade_id INTEGER PRIMARY KEY,
+        direction TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        entry_price REAL NOT NULL,
+        exit_price REAL NOT NULL,
+        pnl REAL NOT NULL,
+        open_ms INTEGER NOT NULL,
+        close_ms INTEGER NOT NULL,
+        price_source TEXT NOT NULL
+    );
+    INSERT OR IGNORE INTO trades_v4 SELECT trade_id, direction, reason, entry_price, exit_price, pnl, open_ms, close_ms, price_source
+        FROM trades ORDER BY rowid;
+    DROP TABLE trades;
+    ALTER TABLE trades_v4 RENAME TO trades;
+    CREATE INDEX idx_trades_close ON trades(close_ms);",
+];
+
+/// Rows queued for the writer thread.
+enum Record {
+    Tick(TradeData),
+    Signal { trade_id: u64, ts: DateTime<Utc>, signal: QuantumSignal, price: f64, volatility: f64 },
+    Trade(ClosedTrade),
+    Outcome(SignalOutcome),
+    Snapshot { ts: DateTime<Utc>, balance: f64, equity: f64, wins: u32, losses: u32, open_positions: usize },
+}
+
+/// A settled trade read back from the store.
+#[derive(Debug, Clone)]
+pub struct StoredTrade {
+    pub pnl: f64,
+    pub close_time: DateTime<Utc>,
+}
+
+/// Handle to the SQLite writer. Every call only enqueues; a dedicated thread
+/// batches the rows into one transaction per flush, off the logic loop.
+/// With the queue full, signals and trades wait for room; ticks, outcomes and
+/// snapshots are dropped and counted in `dropped`.
+pub struct Storage {
+    tx: SyncSender<Record>,
+    writer: thread::JoinHandle<()>,
+    dropped: AtomicU64,
+}
+
+impl Storage {
+    pub fn spawn(path: &str) -> anyhow::Result<Self> {
+        let mut conn = open(path)?;
+        let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+
+        let writer = thread::Builder::new()
+            .name("sqlite-writer".to_string())
+            .spawn(move || writer_loop(&mut conn, rx))?;
+
+        info!("🗄️ SQLITE STORE READY: {}", path);
+        Ok(Self { tx, writer, dropped: AtomicU64::new(0) })
+    }
+
+    /// Stops accepting rows and waits for the writer to commit everything still queued.
+    pub fn close(self) {
+        let Self { tx, writer, .. } = self;
+        drop(tx);
+        if writer.join().is_err() {
+            error!("SQLITE WRITER PANICKED: Queued rows may be lost");
+        } else {
+            info!("🗄️ SQLITE STORE CLOSED");
+        }
+    }
+
+    /// Rows lost to a full queue since startup.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn tick(&self, tick: &TradeData) {
+        self.send(Record::Tick(*tick));
+    }
+
+    pub fn signal(&self, trade_id: u64, ts: DateTime<Utc>, signal: &QuantumSignal, price: f64, volatility: f64) {
+        self.send_critical(Record::Signal { trade_id, ts, signal: signal.clone(), price, volatility });
+    }
+
+    pub fn trade(&self, trade: &ClosedTrade) {
+        self.send_critical(Record::Trade(trade.clone()));
+    }
+
+    pub fn signal_outcome(&self, outcome: &SignalOutcome) {
+        self.send(Record::Outcome(outcome.clone()));
+    }
+
+    pub fn wallet_snapshot(&self, wallet: &PaperWallet) {
+        self.send(Record::Snapshot {
+            ts: wallet.clock,
+            balance: wallet.balance,
+            equity: wallet.equity(),
+            wins: wallet.wins,
+            losses: wallet.losses,
+            open_positions: wallet.open_positions(),
+        });
+    }
+
+    fn send(&self, record: Record) {
+        match self.tx.try_send(record) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                // Warn on the first drop and every thousandth after, not per tick
+                if self.dropped.fetch_add(1, Ordering::Relaxed).is_multiple_of(1_000) {
+                    warn!("⚠️ SQLITE QUEUE FULL: {} rows dropped", self.dropped());
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => error!("SQLITE WRITER DIED: Record dropped"),
+        }
+    }
+
+    /// Blocks until the writer makes room; these rows cannot be rebuilt later.
+    fn send_critical(&self, record: Record) {
+        let sent = match self.tx.try_send(record) {
+            Err(TrySendError::Full(record)) => {
+                warn!("⚠️ SQLITE QUEUE FULL: Waiting for the writer");
+                self.tx.send(record).is_ok()
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+            Ok(()) => true,
+        };
+        if !sent {
+            error!("SQLITE WRITER DIED: Record dropped");
+        }
+    }
+}
+
+fn open(path: &str) -> anyhow::Result<Connection> {
+    if let Some(dir) = Path::new(path).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut conn = Connection::open(path)?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    migrate(&mut conn)?;
+    Ok(conn)
+}
+
+fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+        info!("🗄️ SQLITE MIGRATED TO v{}", i + 1);
+    }
+    Ok(())
+}
+
+fn writer_loop(conn: &mut Connection, rx: Receiver<Record>) {
+    loop {
+        // Block for the first record, then gather more until the flush deadline
+        let first = match rx.recv() {
+            Ok(r) => r,
+            Err(_) => return, // All handles dropped
+        };
+        let mut batch = vec![first];
+        let deadline = Instant::now() + FLUSH_INTERVAL;
+        let mut disconnected = false;
+
+        while batch.len() < MAX_BATCH {
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(r) => batch.push(r),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    disconnected = true;
+                    break;
+                }
+            }
+        }
+
+        if let Err(e) = write_batch(conn, &batch) {
+            error!("SQLITE BATCH WRITE FAILED ({} rows): {}", batch.len(), e);
+        }
+        if disconnected { return; }
+    }
+}
+
+fn write_batch(conn: &mut Connection, batch: &[Record]) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    for record in batch {
+        match record {
+            Record::Tick(t) => {
+                tx.prepare_cached("INSERT INTO ticks (ts_ms, price, quantity, is_buyer_maker) VALUES (?1, ?2, ?3, ?4)")?
+                    .execute(params![t.timestamp.timestamp_millis(), t.price, t.quantity, t.is_buyer_maker])?;
+            }
+            Record::Signal { trade_id, ts, signal, price, volatility } => {
+                let f = &signal.features;
+                tx.prepare_cached(
+                    "INSERT OR REPLACE INTO signals (trade_id, ts_ms, direction, price, confidence, prob_up, ofi, volatility, whale_confirmed,
+                         feature_confirmed, ema_fast, ema_slow, rsi, macd_hist, bb_upper, bb_lower, atr, vwap)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)")?
+                    .execute(params![*trade_id as i64, ts.timestamp_millis(), signal.direction, price, signal.confidence,
+                        signal.prob_up, signal.ofi, volatility, signal.is_whale_confirmed, signal.is_feature_confirmed,
+                        f.ema_fast, f.ema_slow, f.rsi, f.macd_hist, f.bb_upper, f.bb_lower, f.atr, f.vwap])?;
+            }
+            Record::Trade(t) => {
+                tx.prepare_cached(
+                    "INSERT OR IGNORE INTO trades (trade_id, direction, reason, entry_price, exit_price, pnl, open_ms, close_ms, price_source)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)")?
+                    .execute(params![t.trade_id as i64, t.direction, t.reason.label(), t.entry_price, t.exit_price, t.pnl,
+                        t.open_time.timestamp_millis(), t.close_time.timestamp_millis(), t.price_source.label()])?;
+            }
+            Record::Outcome(o) => {
+                tx.prepare_cached(
+                    "INSERT INTO signal_outcomes (ts_ms, direction, price, confidence, prob_up, whale_confirmed, feature_confirmed,
+                         passed, horizon_secs, exit_price)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)")?
+                    .execute(params![o.time.timestamp_millis(), o.direction, o.price, o.confidence, o.prob_up, o.whale_confirmed,
+                        o.feature_confirmed, o.passed, o.horizon_secs, o.exit_price])?;
+            }
+            Record::Snapshot { ts, balance, equity, wins, losses, open_positions } => {
+                tx.prepare_cached(
+                    "INSERT INTO wallet_snapshots (ts_ms, balance, equity, wins, losses, open_positions) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?
+                    .execute(params![ts.timestamp_millis(), balance, equity, wins, losses, *open_positions as i64])?;
+            }
+        }
+    }
+    tx.commit()
+}
+
+fn from_millis(ms: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(ms).single().unwrap_or_default()
+}
+
+/// Every settled trade, oldest first. Opens its own read connection.
+pub fn load_trades(path: &str) -> anyhow::Result<Vec<StoredTrade>> {
+    let conn = open(path)?;
+    let mut stmt = conn.prepare("SELECT pnl, close_ms FROM trades ORDER BY close_ms")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(StoredTrade { pnl: row.get(0)?, close_time: from_millis(row.get(1)?) })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Every scored signal with the given horizon, oldest first.
+pub fn load_signal_outcomes(path: &str, horizon_secs: i64) -> anyhow::Result<Vec<SignalOutcome>> {
+    let conn = open(path)?;
+    let mut stmt = conn.prepare(
+        "SELECT ts_ms, direction, price, confidence, prob_up, whale_confirmed, feature_confirmed, passed, exit_price
+         FROM signal_outcomes WHERE horizon_secs = ?1 ORDER BY ts_ms")?;
+    let rows = stmt.query_map(params![horizon_secs], |row| {
+        Ok(SignalOutcome {
+            time: from_millis(row.get(0)?),
+            direction: row.get(1)?,
+            price: row.get(2)?,
+            confidence: row.get(3)?,
+            prob_up: row.get(4)?,
+            whale_confirmed: row.get(5)?,
+            feature_confirmed: row.get(6)?,
+            passed: row.get(7)?,
+            horizon_secs,
+            exit_price: row.get(8)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Recorded ticks in `[from, to)`, oldest first, ready to replay through the engine.
+pub fn load_ticks(path: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> anyhow::Result<Vec<TradeData>> {
+    let conn = open(path)?;
+    let mut stmt = conn.prepare(
+        "SELECT ts_ms, price, quantity, is_buyer_maker FROM ticks WHERE ts_ms >= ?1 AND ts_ms < ?2 ORDER BY ts_ms")?;
+    let rows = stmt.query_map(params![from.timestamp_millis(), to.timestamp_millis()], |row| {
+        Ok(TradeData {
+            timestamp: from_millis(row.get(0)?),
+            price: row.get(1)?,
+            quantity: row.get(2)?,
+            is_buyer_maker: row.get(3)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::{ExitReason, PriceSource};
+
+    fn db_path(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("storage-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("bot.db").to_string_lossy().into_owned()
+    }
+
+    fn trade(trade_id: u64, pnl: f64) -> ClosedTrade {
+        let t = Utc.timestamp_millis_opt(1_700_000_000_000).unwrap();
+        ClosedTrade {
+            trade_id,
+            direction: "UP".to_string(),
+            reason: ExitReason::Expiry,
+            entry_price: 100.0,
+            exit_price: 101.0,
+            price_source: PriceSource::Fill,
+            pnl,
+            open_time: t,
+            close_time: t + chrono::Duration::seconds(60),
+        }
+    }
+
+    #[test]
+    fn close_commits_queued_rows_and_ignores_duplicate_trades() {
+        let path = db_path("dup");
+        let db = Storage::spawn(&path).unwrap();
+        db.trade(&trade(1, 5.0));
+        db.trade(&trade(1, 5.0));
+        db.trade(&trade(2, -3.0));
+        db.close();
+
+        let pnls: Vec<f64> = load_trades(&path).unwrap().iter().map(|t| t.pnl).collect();
+        assert_eq!(pnls, vec![5.0, -3.0]);
+        let _ = std::fs::remove_dir_all(Path::new(&path).parent().unwrap());
+    }
+
+    #[test]
+    fn v4_migration_drops_duplicate_trades() {
+        let path = db_path("v4");
+        std::fs::create_dir_all(Path::new(&path).parent().unwrap()).unwrap();
+        let conn = Connection::open(&path).unwrap();
+        for sql in &MIGRATIONS[..3] { conn.execute_batch(sql).unwrap(); }
+        conn.pragma_update(None, "user_version", 3).unwrap();
+        for pnl in [5.0, 7.0] {
+            conn.execute("INSERT INTO trades VALUES (1, 'UP', 'EXPIRY', 100.0, 101.0, ?1, 0, 60000, 'FILL')", params![pnl]).unwrap();
+        }
+        drop(conn);
+
+        let pnls: Vec<f64> = load_trades(&path).unwrap().iter().map(|t| t.pnl).collect();
+        assert_eq!(pnls, vec![5.0]); // First recorded row wins
+        let _ = std::fs::remove_dir_all(Path::new(&path).parent().unwrap());
+    }
+
+    #[test]
+    fn full_queue_drops_ticks_but_waits_for_trades() {
+        let (tx, rx) = mpsc::sync_channel(1);
+        // A stalled writer that wakes up late and only reports what reached it
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            let trades = rx.iter().filter(|r| matches!(r, Record::Trade(_))).count();
+            assert_eq!(trades, 1);
+        });
+        let db = Storage { tx, writer, dropped: AtomicU64::new(0) };
+        let tick = TradeData { timestamp: Utc::now(), price: 100.0, quantity: 0.1, is_buyer_maker: false };
+        db.tick(&tick);
+        db.tick(&tick);
+        db.tick(&tick);
+        assert_eq!(db.dropped(), 2);
+
+        db.trade(&trade(1, 5.0)); // Blocks until the writer drains the tick
+        let Storage { tx, writer, .. } = db;
+        drop(tx);
+        writer.join().unwrap();
+    }
+}
@@ -0,0 +1,396 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::rngs::StdRng;
+use log::{info, warn};
+use crate::bars::Bar;
+use crate::config::env_or;
+use crate::executor::OrderRequest;
+use crate::model::{MarketMicrostructure, QuantumSignal, SignalParams, TradeData};
+use crate::performance::PerformanceTracker;
+use crate::pretrade::PreTradeChecks;
+use crate::risk::RiskManager;
+use crate::simulator::{FillRecord, PaperWallet, SimMode};
+
+/// A request to open (or flip) a position. Risk, pre-trade checks and execution stay
+/// with the engine.
+#[derive(Debug, Clone)]
+pub struct Intent {
+    pub direction: String, // UP / DOWN
+    pub stake_pct: f64,
+    pub price: f64,        // Price the decision was made at
+    pub volatility: f64,
+    pub signal: QuantumSignal, // Evidence behind the intent, for the journal and alerts
+}
+
+/// A signal the strategy evaluated, traded or not, for calibration.
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    pub time: DateTime<Utc>,
+    pub price: f64,
+    pub signal: QuantumSignal,
+    pub passed: bool, // Cleared every gate; an intent only outside the cooldown
+}
+
+/// Pluggable signal logic. A strategy sees every tick, every closed bar, a periodic timer
+/// and the fills of its own wallet, and answers with intents.
+pub trait Strategy: Send {
+    fn name(&self) -> &str;
+
+    fn on_tick(&mut self, tick: &TradeData, market: &MarketMicrostructure) -> Vec<Intent>;
+
+    /// Called after `on_tick` for each bar the tick closed (see `BARS`).
+    fn on_bar(&mut self, _bar: &Bar, _market: &MarketMicrostructure) -> Vec<Intent> {
+        Vec::new()
+    }
+
+    fn on_timer(&mut self, _now: DateTime<Utc>) -> Vec<Intent> {
+        Vec::new()
+    }
+
+    fn on_fill(&mut self, _fill: &FillRecord) -> Vec<Intent> {
+        Vec::new()
+    }
+
+    /// The latest signal evaluated since the last call, if the strategy has one.
+    fn take_evaluation(&mut self) -> Option<Evaluation> {
+        None
+    }
+}
+
+/// Reads `STRATEGIES` (comma-separated, default whale_mc). The first one trades the main
+/// wallet; the rest run as shadow books.
+pub fn from_env() -> Vec<Box<dyn Strategy>> {
+    let mut strategies: Vec<Box<dyn Strategy>> = env_or("STRATEGIES", "whale_mc".to_string())
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .filter_map(|name| by_name(name).or_else(|| {
+            warn!("⚠️ UNKNOWN STRATEGY {:?} skipped", name);
+            None
+        }))
+        .collect();
+    if strategies.is_empty() {
+        strategies.push(Box::new(WhaleMonteCarlo::from_env()));
+    }
+    strategies
+}
+
+/// Strategy registry. New implementations get an arm here.
+fn by_name(name: &str) -> Option<Box<dyn Strategy>> {
+    match name.to_lowercase().as_str() {
+        "whale_mc" | "whale" => Some(Box::new(WhaleMonteCarlo::from_env())),
+        "ema_cross" | "ema" => Some(Box::new(EmaCross::from_env())),
+        _ => None,
+    }
+}
+
+/// The original engine: Monte Carlo direction, confidence tiers by volatility, an
+/// OFI whale confirmation and any `FEATURE_FILTERS`. Emits no intent for `SIGNAL_COOLDOWN_SECS`
+/// (60) after each one, but keeps evaluating so calibration sees every signal.
+pub struct WhaleMonteCarlo {
+    params: SignalParams,
+    rng: StdRng, // Seeded from `params.seed`
+    cooldown: Duration,
+    quiet_until: Option<DateTime<Utc>>,
+    evaluated: Option<Evaluation>,
+}
+
+impl WhaleMonteCarlo {
+    pub fn new(params: SignalParams) -> Self {
+        Self { params, rng: params.rng(), cooldown: Duration::seconds(env_or("SIGNAL_COOLDOWN_SECS", 60)), quiet_until: None, evaluated: None }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(SignalParams::from_env())
+    }
+}
+
+impl Strategy for WhaleMonteCarlo {
+    fn name(&self) -> &str {
+        "whale_mc"
+    }
+
+    fn on_tick(&mut self, tick: &TradeData, market: &MarketMicrostructure) -> Vec<Intent> {
+        // Speed Hack: Start trading after just a few ticks
+        if market.prices.len() < self.params.warmup_ticks {
+            return Vec::new();
+        }
+        let current_price = tick.price;
+
+        // Calculate Volatility
+        let min = market.prices.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+        let max = market.prices.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+        let volatility = (max - min) / 2.0;
+
+        // Dynamic Threshold Logic
+        let required_conf = self.params.required_confidence(volatility);
+
+        let signal = QuantumSignal::analyze(market, current_price, volatility, &self.params, &mut self.rng);
+        let passed = signal.confidence > required_conf && signal.is_whale_confirmed && signal.is_feature_confirmed;
+        self.evaluated = Some(Evaluation { time: tick.timestamp, price: current_price, signal: signal.clone(), passed });
+        if !passed || self.quiet_until.is_some_and(|t| tick.timestamp < t) {
+            return Vec::new();
+        }
+
+        self.quiet_until = Some(tick.timestamp + self.cooldown);
+        let stake_pct = signal.calculate_stake();
+        vec![Intent { direction: signal.direction.clone(), stake_pct, price: current_price, volatility, signal }]
+    }
+
+    fn take_evaluation(&mut self) -> Option<Evaluation> {
+        self.evaluated.take()
+    }
+}
+
+/// Trend follower on the feature bars (`FEATURE_BAR`): UP when the fast EMA crosses above
+/// the slow one, DOWN on the cross below, if `FEATURE_FILTERS` agree. Stakes
+/// `EMA_CROSS_STAKE_PCT` (1.0) per cross.
+pub struct EmaCross {
+    stake_pct: f64,
+    above: Option<bool>, // Fast EMA above the slow one at the last feature bar
+}
+
+impl EmaCross {
+    pub fn new(stake_pct: f64) -> Self {
+        Self { stake_pct, above: None }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(env_or("EMA_CROSS_STAKE_PCT", 1.0))
+    }
+}
+
+impl Strategy for EmaCross {
+    fn name(&self) -> &str {
+        "ema_cross"
+    }
+
+    fn on_tick(&mut self, _tick: &TradeData, _market: &MarketMicrostructure) -> Vec<Intent> {
+        Vec::new()
+    }
+
+    fn on_bar(&mut self, bar: &Bar, market: &MarketMicrostructure) -> Vec<Intent> {
+        let features = &market.features;
+        if bar.kind != features.config.bar {
+            return Vec::new();
+        }
+        let snapshot = features.snapshot();
+        let (Some(fast), Some(slow)) = (snapshot.ema_fast, snapshot.ema_slow) else { return Vec::new() };
+        let above = fast > slow;
+        // The first warm bar only sets the side; a cross needs a previous one
+        let crossed = self.above.is_some_and(|was| was != above);
+        self.above = Some(above);
+
+        let direction = if above { "UP" } else { "DOWN" };
+        if !crossed || !features.confirms(direction, bar.close) {
+            return Vec::new();
+        }
+        let signal = QuantumSignal {
+            confidence: 0.0, // No probability model behind a crossover
+            direction: direction.to_string(),
+            is_whale_confirmed: false,
+            prob_up: 0.5,
+            ofi: market.calculate_ofi(),
+            features: snapshot,
+            is_feature_confirmed: true,
+        };
+        vec![Intent { direction: signal.direction.clone(), stake_pct: self.stake_pct, price: bar.close,
+            volatility: snapshot.atr.unwrap_or(0.0), signal }]
+    }
+}
+
+/// A secondary strategy paper trading its own wallet, isolated from the main book.
+/// Its intents pass the main book's risk limits and kill switch and its own copy of the
+/// pre-trade checks. Shadow books are not journaled, persisted or executed live.
+pub struct ShadowBook {
+    pub strategy: Box<dyn Strategy>,
+    pub wallet: PaperWallet,
+    pub performance: PerformanceTracker,
+    pretrade: PreTradeChecks,
+    next_id: u64,
+}
+
+impl ShadowBook {
+    pub fn new(strategy: Box<dyn Strategy>, mode: SimMode, pretrade: PreTradeChecks, now: DateTime<Utc>) -> Self {
+        let wallet = PaperWallet::new(mode);
+        let performance = PerformanceTracker::new(wallet.equity(), now);
+        Self { strategy, wallet, performance, pretrade, next_id: now.timestamp_millis() as u64 }
+    }
+
+    pub fn on_tick(&mut self, tick: &TradeData, bars: &[Bar], market: &MarketMicrostructure, risk: &RiskManager) {
+        self.wallet.update(tick);
+        let mut intents = self.strategy.on_tick(tick, market);
+        for bar in bars {
+            intents.extend(self.strategy.on_bar(bar, market));
+        }
+        self.execute(intents, risk);
+        self.settle(risk);
+    }
+
+    pub fn on_timer(&mut self, now: DateTime<Utc>, risk: &RiskManager) {
+        let intents = self.strategy.on_timer(now);
+        self.execute(intents, risk);
+    }
+
+    /// The main book's gate, minus the operator pause and the live mirror.
+    fn execute(&mut self, intents: Vec<Intent>, risk: &RiskManager) {
+        let name = self.strategy.name().to_string();
+        for intent in intents {
+            self.next_id += 1;
+            let wallet = &self.wallet;
+            let mut order = OrderRequest {
+                trade_id: self.next_id,
+                symbol: self.pretrade.rules.symbol.clone(),
+                direction: intent.direction.clone(),
+                price: intent.price,
+                stake_pct: intent.stake_pct,
+                notional: wallet.balance * intent.stake_pct / 100.0,
+                close_quantity: wallet.position.as_ref().filter(|p| p.direction != intent.direction).map_or(0.0, |p| p.quantity),
+                quantity: 0.0,
+            };
+            let reference = wallet.book.as_ref().and_then(|b| b.mid()).unwrap_or(wallet.mark_price);
+            let gate = match risk.check(wallet, intent.stake_pct) {
+                Err(r) => Err(r.to_string()),
+                Ok(()) => self.pretrade.check(&mut order, reference, wallet.clock).map_err(|r| format!("pre-trade: {}", r)),
+            };
+            if let Err(rejection) = gate {
+                info!("🧪 [{}] BLOCKED {} | {}", name, intent.direction, rejection);
+                continue;
+            }
+            info!("🧪 [{}] SIGNAL {} | Stake: {:.1}% | Conf: {:.1}%", name,
+                intent.direction, intent.stake_pct, intent.signal.confidence);
+            self.wallet.open_trade(order.trade_id, order.direction, order.price, order.stake_pct);
+        }
+    }
+
+    fn settle(&mut self, risk: &RiskManager) {
+        for fill in self.wallet.drain_fills() {
+            let intents = self.strategy.on_fill(&fill);
+            self.execute(intents, risk);
+        }
+        for closed in self.wallet.drain_closed() {
+            self.performance.record(&closed);
+            info!("🧪 [{}] CLOSED {} | {} | PnL: ${:+.2}", self.strategy.name(), closed.direction,
+                closed.reason.label(), closed.pnl);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+
+    /// prob_up of every evaluation over a fixed random walk.
+    fn evaluations(params: SignalParams) -> Vec<f64> {
+        let mut walk = StdRng::seed_from_u64(7);
+        let mut market = MarketMicrostructure::new();
+        let mut strategy = WhaleMonteCarlo::new(params);
+        let start = Utc::now();
+        let mut price = 50_000.0;
+        let mut seen = Vec::new();
+        for i in 0..60 {
+            price += walk.random_range(-5.0..5.0);
+            let tick = TradeData { timestamp: start + Duration::milliseconds(i * 250), price, quantity: 0.01, is_buyer_maker: i % 3 == 0 };
+            market.update(&tick);
+            strategy.on_tick(&tick, &market);
+            seen.extend(strategy.take_evaluation().map(|e| e.signal.prob_up));
+        }
+        seen
+    }
+
+    #[test]
+    fn seeded_monte_carlo_repeats() {
+        let mut params = SignalParams::from_env();
+        params.mc_paths = 300; // Not a multiple of the chunk size
+        params.seed = Some(42);
+        let first = evaluations(params);
+        assert!(!first.is_empty());
+        assert_eq!(first, evaluations(params));
+
+        params.seed = Some(43);
+        assert_ne!(first, evaluations(params));
+    }
+
+    #[test]
+    fn keeps_evaluating_during_the_cooldown() {
+        let mut params = SignalParams::from_env();
+        params.mc_paths = 100;
+        params.seed = Some(1);
+        let mut strategy = WhaleMonteCarlo::new(params);
+        let start = Utc::now();
+        strategy.quiet_until = Some(start + Duration::hours(1));
+
+        let mut market = MarketMicrostructure::new();
+        let mut evaluated = 0;
+        for i in 0..40 {
+            let tick = TradeData { timestamp: start + Duration::seconds(i), price: 50_000.0 + i as f64, quantity: 0.01, is_buyer_maker: false };
+            market.update(&tick);
+            assert!(strategy.on_tick(&tick, &market).is_empty());
+            evaluated += strategy.take_evaluation().is_some() as usize;
+        }
+        assert_eq!(evaluated, 40 - params.warmup_ticks + 1);
+    }
+
+    /// One tick per `FEATURE_BAR` (1m), so each tick closes the previous bar.
+    fn minute_ticks(prices: impl IntoIterator<Item = f64>) -> Vec<TradeData> {
+        let start: DateTime<Utc> = "2026-01-01T00:00:00Z".parse().unwrap();
+        prices.into_iter().enumerate()
+            .map(|(i, price)| TradeData { timestamp: start + Duration::minutes(i as i64), price, quantity: 0.01, is_buyer_maker: false })
+            .collect()
+    }
+
+    #[test]
+    fn ema_cross_trades_each_cross_once() {
+        let mut market = MarketMicrostructure::new();
+        let mut strategy = EmaCross::new(2.0);
+        // 40 falling bars warm both EMAs below each other, then a rally crosses them up
+        let prices = (0..40).map(|i| 50_000.0 - i as f64 * 10.0).chain((0..40).map(|i| 49_610.0 + i as f64 * 30.0));
+        let mut intents = Vec::new();
+        for tick in minute_ticks(prices) {
+            for bar in market.update(&tick) {
+                intents.extend(strategy.on_bar(&bar, &market).into_iter().map(|i| (tick.timestamp, i)));
+            }
+        }
+        assert_eq!(intents.len(), 1);
+        let (at, intent) = &intents[0];
+        assert_eq!((intent.direction.as_str(), intent.stake_pct), ("UP", 2.0));
+        assert!(*at > "2026-01-01T00:40:00Z".parse::<DateTime<Utc>>().unwrap());
+    }
+
+    #[test]
+    fn shadow_intents_pass_the_pre_trade_checks() {
+        use crate::pretrade::{PreTradeLimits, SymbolRules};
+        use crate::risk::RiskLimits;
+
+        /// Wants a 10% stake on every tick.
+        struct Eager;
+        impl Strategy for Eager {
+            fn name(&self) -> &str { "eager" }
+            fn on_tick(&mut self, tick: &TradeData, market: &MarketMicrostructure) -> Vec<Intent> {
+                let signal = QuantumSignal { confidence: 0.0, direction: "UP".to_string(), is_whale_confirmed: false, prob_up: 0.5,
+                    ofi: 0.0, features: market.features.snapshot(), is_feature_confirmed: true };
+                vec![Intent { direction: "UP".to_string(), stake_pct: 10.0, price: tick.price, volatility: 0.0, signal }]
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("shadow-risk-{}", std::process::id()));
+        let no_limits = RiskLimits { max_positions: 0, max_exposure_pct: 0.0, daily_loss_pct: 0.0, max_drawdown_pct: 0.0, max_consecutive_losses: 0 };
+        let risk = RiskManager::new(no_limits, &PaperWallet::new(SimMode::Perpetual), dir.to_str().unwrap());
+
+        // A 10% stake of the 10k wallet is $1000
+        for (max_notional, opens) in [(5000.0, true), (500.0, false)] {
+            let mut limits = PreTradeLimits::from_env();
+            limits.max_notional = max_notional;
+            let checks = PreTradeChecks::new(limits, SymbolRules::from_env("BTCUSDT"), SimMode::Perpetual);
+            let mut book = ShadowBook::new(Box::new(Eager), SimMode::Perpetual, checks, Utc::now());
+            book.wallet.fill_model.latency = std::time::Duration::ZERO;
+            let mut market = MarketMicrostructure::new();
+            for tick in minute_ticks([50_000.0]) {
+                market.update(&tick);
+                book.on_tick(&tick, &[], &market, &risk);
+            }
+            assert_eq!(book.wallet.position.is_some(), opens);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,47 +1,401 @@
-use reqwest::Client;
-use serde::Serialize;
-use log::{info, error};
+use std::collections::HashSet;
+use std::time::Duration;
+use reqwest::{multipart, Client};
+use serde::{Deserialize, Serialize};
+use log::{info, warn};
+use tokio::sync::mpsc;
+use crate::config::env_or;
+use crate::message::Format;
+use crate::notify::{DeliveryError, Notification, Notifier};
+
+const LONG_POLL_SECS: u64 = 30;
+const MAX_CAPTION_CHARS: usize = 1024;
+
+/// Backend name used for alert routing and command replies.
+pub const BACKEND: &str = "telegram";
 
 #[derive(Serialize)]
 struct TelegramMessage {
     chat_id: String,
     text: String,
-    parse_mode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parse_mode: Option<&'static str>,
+}
+
+#[derive(Deserialize, Default)]
+struct ApiError {
+    description: Option<String>,
+    parameters: Option<ResponseParameters>,
+}
+
+#[derive(Deserialize)]
+struct ResponseParameters {
+    retry_after: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct UpdatesResponse {
+    ok: bool,
+    #[serde(default)]
+    result: Vec<Update>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<IncomingMessage>,
+}
+
+#[derive(Deserialize)]
+struct IncomingMessage {
+    chat: Chat,
+    from: Option<User>,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+#[derive(Deserialize)]
+struct User {
+    id: i64,
+    username: Option<String>,
+}
+
+/// Operator commands accepted over Telegram.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Status,
+    Pause,
+    Resume,
+    Positions,
+    Pnl,
+    Config,
+    Calibration,
+    Halt,
+    Reset, // Clears a tripped kill switch
+    Help,
+}
+
+impl Command {
+    /// Parses "/status", "/status@MyBot" etc. Returns None for anything that is not a command.
+    pub fn parse(text: &str) -> Option<Self> {
+        let word = text.split_whitespace().next()?.strip_prefix('/')?;
+        let name = word.split('@').next().unwrap_or(word).to_lowercase();
+        Some(match name.as_str() {
+            "status" => Command::Status,
+            "pause" => Command::Pause,
+            "resume" => Command::Resume,
+            "positions" => Command::Positions,
+            "pnl" => Command::Pnl,
+            "config" => Command::Config,
+            "calibration" => Command::Calibration,
+            "halt" => Command::Halt,
+            "reset" => Command::Reset,
+            _ => Command::Help,
+        })
+    }
+
+    /// Commands that resume trading or change the kill switch. Only allow-listed user ids
+    /// may send them, even from the configured chat.
+    pub fn is_privileged(self) -> bool {
+        matches!(self, Command::Resume | Command::Halt | Command::Reset)
+    }
+}
+
+/// An authenticated command on its way to the logic task. Replies go back to `chat_id`.
+#[derive(Debug, Clone)]
+pub struct CommandRequest {
+    pub command: Command,
+    pub chat_id: String,
+    pub user: String,
 }
 
 #[derive(Clone)]
 pub struct TelegramBot {
     client: Client,
+    api_base: String, // TELEGRAM_API_URL, so a local mock server can stand in for Telegram
     token: String,
     chat_id: String,
+    allowed: HashSet<i64>, // User ids that may command from any chat and send privileged commands
+    format: Format,
 }
 
 impl TelegramBot {
+    /// Reads `TELEGRAM_API_URL` (https://api.telegram.org) and `TELEGRAM_ALLOWED_USERS`
+    /// (comma-separated numeric user ids, the only ones allowed /resume, /halt and /reset).
     pub fn new(token: String, chat_id: String) -> Self {
+        let api_base = env_or("TELEGRAM_API_URL", "https://api.telegram.org".to_string());
+        let mut bot = Self::with_api_base(&api_base, token, chat_id);
+        bot.allowed = allowed_users(&env_or("TELEGRAM_ALLOWED_USERS", String::new()));
+        bot
+    }
+
+    pub fn with_api_base(api_base: &str, token: String, chat_id: String) -> Self {
         Self {
             client: Client::new(),
+            api_base: api_base.trim_end_matches('/').to_string(),
             token,
             chat_id,
+            allowed: HashSet::new(),
+            format: Format::telegram_from_env(),
         }
     }
 
-    pub async fn send_signal(&self, msg: &str) {
-        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.token);
-        let payload = TelegramMessage {
-            chat_id: self.chat_id.clone(),
-            text: msg.to_string(),
-            parse_mode: "Markdown".to_string(),
-        };
+    fn method_url(&self, method: &str) -> String {
+        format!("{}/bot{}/{}", self.api_base, self.token, method)
+    }
+
+    /// Commands count from the configured chat, or from an allow-listed user id in any chat.
+    /// Privileged commands need the allow-listed id wherever they come from. Usernames can
+    /// be changed or reused by anyone, so they never authorise.
+    fn is_authorised(&self, command: Command, chat_id: &str, from: Option<&User>) -> bool {
+        let listed = from.is_some_and(|u| self.allowed.contains(&u.id));
+        listed || (chat_id == self.chat_id && !command.is_privileged())
+    }
+
+    /// Long-polls `getUpdates` and forwards authorised commands. Runs until the receiving
+    /// side is dropped. Commands sent while the bot was down are skipped, not replayed.
+    pub async fn poll_commands(self, tx: mpsc::Sender<CommandRequest>) {
+        let mut offset: Option<i64> = None;
+        info!("📨 TELEGRAM COMMANDS ACTIVE ({} users allowed privileged commands)", self.allowed.len());
+
+        loop {
+            // offset=-1 returns only the newest update and confirms everything before it
+            let Some(next) = offset else {
+                match self.get_updates(-1, 0).await {
+                    Ok(backlog) => {
+                        if let Some(last) = backlog.last() {
+                            info!("📨 SKIPPED TELEGRAM BACKLOG up to update {}", last.update_id);
+                        }
+                        offset = Some(backlog.last().map_or(0, |u| u.update_id + 1));
+                    }
+                    Err(e) => {
+                        warn!("⚠️ TELEGRAM POLL FAILED: {}", e);
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                }
+                continue;
+            };
+            let updates = match self.get_updates(next, LONG_POLL_SECS).await {
+                Ok(u) => u,
+                Err(e) => {
+                    warn!("⚠️ TELEGRAM POLL FAILED: {}", e);
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+
+            for update in updates {
+                offset = Some(next.max(update.update_id + 1));
+                let Some(msg) = update.message else { continue };
+                let Some(command) = msg.text.as_deref().and_then(Command::parse) else { continue };
+
+                let chat_id = msg.chat.id.to_string();
+                let user = msg.from.as_ref()
+                    .map(|u| u.username.clone().unwrap_or_else(|| u.id.to_string()))
+                    .unwrap_or_default();
+                if !self.is_authorised(command, &chat_id, msg.from.as_ref()) {
+                    warn!("🚫 UNAUTHORISED COMMAND {:?} from {} in chat {}", command, user, chat_id);
+                    continue;
+                }
+                info!("📨 COMMAND {:?} from {}", command, user);
+                if tx.send(CommandRequest { command, chat_id, user }).await.is_err() { return; }
+            }
+        }
+    }
+
+    async fn get_updates(&self, offset: i64, timeout_secs: u64) -> anyhow::Result<Vec<Update>> {
+        let resp: UpdatesResponse = self.client
+            .get(self.method_url("getUpdates"))
+            .query(&[("offset", offset.to_string()), ("timeout", timeout_secs.to_string())])
+            .timeout(Duration::from_secs(timeout_secs + 10))
+            .send()
+            .await?
+            .json()
+            .await?;
+        if !resp.ok {
+            anyhow::bail!(resp.description.unwrap_or_else(|| "getUpdates returned ok=false".to_string()));
+        }
+        Ok(resp.result)
+    }
+}
+
+/// Parses `TELEGRAM_ALLOWED_USERS`. Anything that is not a numeric user id is skipped.
+fn allowed_users(raw: &str) -> HashSet<i64> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| match s.parse() {
+            Ok(id) => Some(id),
+            Err(_) => {
+                warn!("⚠️ TELEGRAM_ALLOWED_USERS: ignoring {:?}, only numeric user ids authorise", s);
+                None
+            }
+        })
+        .collect()
+}
+
+impl Notifier for TelegramBot {
+    fn name(&self) -> &'static str {
+        BACKEND
+    }
 
-        match self.client.post(&url).json(&payload).send().await {
-            Ok(resp) => {
-                if !resp.status().is_success() {
-                    error!("Telegram API Error: {:?}", resp.text().await);
-                } else {
-                    info!("Alert dispatched.");
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    /// One `sendMessage` attempt, or `sendPhoto` with the text as caption when the message
+    /// carries an image. The target is a chat id, defaulting to `CHAT_ID`.
+    async fn deliver(&self, n: &Notification) -> Result<(), DeliveryError> {
+        let chat_id = n.target.clone().unwrap_or_else(|| self.chat_id.clone());
+        let text = n.message.render(self.format);
+
+        let request = match &n.message.image {
+            Some(png) if text.chars().count() <= MAX_CAPTION_CHARS => {
+                let photo = multipart::Part::bytes(png.clone())
+                    .file_name("chart.png")
+                    .mime_str("image/png")
+                    .map_err(|e| DeliveryError::Rejected(e.to_string()))?;
+                let mut form = multipart::Form::new()
+                    .text("chat_id", chat_id)
+                    .text("caption", text)
+                    .part("photo", photo);
+                if let Some(mode) = self.format.telegram_parse_mode() {
+                    form = form.text("parse_mode", mode);
                 }
-            },
-            Err(e) => error!("Network Error sending alert: {}", e),
+                self.client.post(self.method_url("sendPhoto")).multipart(form)
+            }
+            // Too long for a caption (or no image): plain text message
+            _ => self.client.post(self.method_url("sendMessage")).json(&TelegramMessage {
+                chat_id,
+                text,
+                parse_mode: self.format.telegram_parse_mode(),
+            }),
+        };
+
+        let resp = request
+            .timeout(Duration::from_secs(20))
+            .send()
+            .await
+            .map_err(|e| DeliveryError::Transient(e.to_string()))?;
+        let status = resp.status();
+        if status.is_success() { return Ok(()); }
+
+        let body: ApiError = resp.json().await.unwrap_or_default();
+        let description = body.description.unwrap_or_else(|| status.to_string());
+        match (status.as_u16(), body.parameters.and_then(|p| p.retry_after)) {
+            (429, retry_after) => Err(DeliveryError::RateLimited(Duration::from_secs(retry_after.unwrap_or(1)))),
+            (code, _) if code >= 500 => Err(DeliveryError::Transient(description)),
+            _ => Err(DeliveryError::Rejected(description)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::MessageBuilder;
+    use crate::mock_http::MockServer;
+    use crate::notify::EventKind;
+
+    fn bot(api_base: &str) -> TelegramBot {
+        let mut bot = TelegramBot::with_api_base(api_base, "TOKEN".to_string(), "100".to_string());
+        bot.allowed = allowed_users("42, @alice, bob,");
+        bot
+    }
+
+    fn user(id: i64, username: &str) -> User {
+        User { id, username: Some(username.to_string()) }
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(Command::parse("/status"), Some(Command::Status));
+        assert_eq!(Command::parse("/PnL@QuantumBot now"), Some(Command::Pnl));
+        assert_eq!(Command::parse("  /reset"), Some(Command::Reset));
+        assert_eq!(Command::parse("/whatever"), Some(Command::Help));
+        assert_eq!(Command::parse("status"), None);
+        assert_eq!(Command::parse(""), None);
+    }
+
+    #[test]
+    fn only_the_chat_or_numeric_ids_authorise() {
+        let bot = bot("http://127.0.0.1:9");
+        let status = Command::Status;
+        assert_eq!(bot.allowed, HashSet::from([42]));
+        assert!(bot.is_authorised(status, "100", None));
+        assert!(bot.is_authorised(status, "100", Some(&user(7, "mallory"))));
+        assert!(bot.is_authorised(status, "555", Some(&user(42, "renamed"))));
+        assert!(!bot.is_authorised(status, "555", Some(&user(7, "alice"))));
+        assert!(!bot.is_authorised(status, "555", Some(&user(7, "bob"))));
+        assert!(!bot.is_authorised(status, "555", None));
+    }
+
+    #[test]
+    fn privileged_commands_need_an_allow_listed_id() {
+        let bot = bot("http://127.0.0.1:9");
+        for command in [Command::Resume, Command::Halt, Command::Reset] {
+            assert!(!bot.is_authorised(command, "100", Some(&user(7, "mallory"))));
+            assert!(!bot.is_authorised(command, "100", None));
+            assert!(bot.is_authorised(command, "100", Some(&user(42, "ops"))));
+            assert!(bot.is_authorised(command, "555", Some(&user(42, "ops"))));
         }
+        assert!(bot.is_authorised(Command::Pause, "100", Some(&user(7, "mallory"))));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn polls_and_forwards_authorised_commands() {
+        // Sent while the bot was down
+        let backlog = r#"{"ok":true,"result":[
+            {"update_id":9,"message":{"chat":{"id":100},"from":{"id":42},"text":"/reset"}}
+        ]}"#;
+        let updates = r#"{"ok":true,"result":[
+            {"update_id":10,"message":{"chat":{"id":100},"from":{"id":7},"text":"/status"}},
+            {"update_id":11,"message":{"chat":{"id":555},"from":{"id":8,"username":"alice"},"text":"/halt"}},
+            {"update_id":12,"message":{"chat":{"id":555},"from":{"id":42},"text":"/pnl@QuantumBot"}},
+            {"update_id":13,"message":{"chat":{"id":100},"text":"hello"}}
+        ]}"#;
+        let server = MockServer::start(vec![(200, backlog.to_string()), (200, updates.to_string())]).await;
+        let (tx, mut rx) = mpsc::channel(8);
+        tokio::spawn(bot(&server.url).poll_commands(tx));
+
+        let first = rx.recv().await.unwrap();
+        assert_eq!((first.command, first.chat_id.as_str()), (Command::Status, "100"));
+        let second = rx.recv().await.unwrap();
+        assert_eq!((second.command, second.chat_id.as_str()), (Command::Pnl, "555"));
+
+        let requests = server.wait_for(3).await;
+        assert!(requests[0].path.starts_with("/botTOKEN/getUpdates?offset=-1"));
+        assert!(requests[1].path.starts_with("/botTOKEN/getUpdates?offset=10"));
+        assert!(requests[2].path.starts_with("/botTOKEN/getUpdates?offset=14"));
+        assert!(rx.try_recv().is_err()); // The queued /reset was skipped and alice's /halt dropped
+    }
+
+    #[tokio::test]
+    async fn maps_delivery_errors() {
+        let server = MockServer::start(vec![
+            (200, r#"{"ok":true}"#.to_string()),
+            (429, r#"{"ok":false,"description":"Too Many Requests","parameters":{"retry_after":7}}"#.to_string()),
+            (400, r#"{"ok":false,"description":"Bad Request: can't parse entities"}"#.to_string()),
+            (502, "".to_string()),
+        ]).await;
+        let bot = bot(&server.url);
+        let n = Notification::new(EventKind::System, None, MessageBuilder::new().line("hi").build(), false);
+
+        assert!(bot.deliver(&n).await.is_ok());
+        assert!(matches!(bot.deliver(&n).await, Err(DeliveryError::RateLimited(d)) if d == Duration::from_secs(7)));
+        assert!(matches!(bot.deliver(&n).await, Err(DeliveryError::Rejected(e)) if e.contains("can't parse")));
+        assert!(matches!(bot.deliver(&n).await, Err(DeliveryError::Transient(_))));
+
+        let request = &server.requests()[0];
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/botTOKEN/sendMessage"));
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        let sent: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(sent["chat_id"], "100");
+        assert_eq!(sent["text"], "hi");
+    }
+}
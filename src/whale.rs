@@ -0,0 +1,366 @@
+use std::collections::VecDeque;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use crate::config::env_or;
+use crate::executor::Side;
+use crate::model::TradeData;
+
+/// Thresholds for calling a trade, or a run of trades, a whale.
+#[derive(Debug, Clone)]
+pub struct WhaleConfig {
+    pub window: usize,         // Trades in the rolling size distribution
+    pub min_samples: usize,    // Below this only the notional threshold applies
+    pub percentile: f64,       // Size percentile a trade has to reach, 0-100
+    pub zscore: f64,           // Or its log-size z-score
+    pub min_notional: f64,     // USDT; any trade this big counts, and smaller ones must reach min_notional / 10
+    pub burst_gap: Duration,   // Max gap between same-side trades of one cluster
+    pub burst_notional: f64,   // USDT a cluster needs to count
+    pub iceberg_fills: u64,    // Fills at one price that make a cluster an iceberg
+    pub lookback: Duration,    // How long an event keeps confirming signals
+    pub ofi_min_volume: f64,   // BTC in the OFI window before OFI is trusted
+}
+
+impl WhaleConfig {
+    /// Reads `WHALE_WINDOW` (1000), `WHALE_MIN_SAMPLES` (100), `WHALE_PERCENTILE` (99),
+    /// `WHALE_ZSCORE` (3.0), `WHALE_MIN_NOTIONAL` (100000), `WHALE_BURST_MS` (500),
+    /// `WHALE_BURST_NOTIONAL` (250000), `WHALE_ICEBERG_FILLS` (5), `WHALE_LOOKBACK_SECS` (60)
+    /// and `OFI_MIN_VOLUME` (1.0).
+    pub fn from_env() -> Self {
+        Self {
+            window: env_or("WHALE_WINDOW", 1000usize).max(10),
+            min_samples: env_or("WHALE_MIN_SAMPLES", 100),
+            percentile: env_or("WHALE_PERCENTILE", 99.0f64).clamp(0.0, 100.0),
+            zscore: env_or("WHALE_ZSCORE", 3.0),
+            min_notional: env_or("WHALE_MIN_NOTIONAL", 100_000.0),
+            burst_gap: Duration::milliseconds(env_or("WHALE_BURST_MS", 500)),
+            burst_notional: env_or("WHALE_BURST_NOTIONAL", 250_000.0),
+            iceberg_fills: env_or("WHALE_ICEBERG_FILLS", 5),
+            lookback: Duration::seconds(env_or("WHALE_LOOKBACK_SECS", 60)),
+            ofi_min_volume: env_or("OFI_MIN_VOLUME", 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WhaleKind {
+    LargeTrade, // One aggTrade far out in the size distribution
+    Burst,      // Same-side aggTrades in quick succession
+    Iceberg,    // A burst filled at a single price
+}
+
+impl WhaleKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            WhaleKind::LargeTrade => "LARGE TRADE",
+            WhaleKind::Burst => "BURST",
+            WhaleKind::Iceberg => "ICEBERG",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WhaleEvent {
+    pub kind: WhaleKind,
+    pub side: Side, // Taker side
+    pub price: f64,
+    pub quantity: f64,
+    pub notional: f64,
+    pub fills: u64,
+    pub zscore: Option<f64>, // Large trades only, once the distribution is warm
+}
+
+/// Same-side run of trades being watched for a burst.
+struct Cluster {
+    side: Side,
+    last: DateTime<Utc>,
+    price: f64,
+    same_price: bool,
+    quantity: f64,
+    notional: f64,
+    fills: u64,
+    counted: f64, // Notional already in the whale flow
+    reported: bool,
+}
+
+const PERCENTILE_REFRESH: usize = 100; // Trades between percentile re-sorts
+
+/// Spots large takers from trade sizes: single prints far out in the rolling size
+/// distribution or over a notional threshold, and bursts of same-side trades.
+pub struct WhaleDetector {
+    pub config: WhaleConfig,
+    sizes: VecDeque<f64>,
+    log_sum: f64,
+    log_sum_sq: f64,
+    percentile_qty: Option<f64>,
+    since_refresh: usize,
+    sizeless: bool, // Every trade in the warm window had the same size
+    cluster: Option<Cluster>,
+    flow: VecDeque<(DateTime<Utc>, f64)>, // Signed notional of whale trades, each trade once
+    fresh: Vec<WhaleEvent>,
+}
+
+impl WhaleDetector {
+    pub fn new(config: WhaleConfig) -> Self {
+        Self {
+            config,
+            sizes: VecDeque::new(),
+            log_sum: 0.0,
+            log_sum_sq: 0.0,
+            percentile_qty: None,
+            since_refresh: 0,
+            sizeless: false,
+            cluster: None,
+            flow: VecDeque::new(),
+            fresh: Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self, tick: &TradeData) {
+        if tick.quantity <= 0.0 { return; }
+        // Buyer is the maker, so the seller crossed the spread
+        let side = if tick.is_buyer_maker { Side::Sell } else { Side::Buy };
+        let notional = tick.price * tick.quantity;
+
+        // Judge the trade against the distribution before it joins it
+        let zscore = self.zscore(tick.quantity);
+        let outlier = self.sizes.len() >= self.config.min_samples
+            && notional >= self.config.min_notional / 10.0
+            && (self.percentile_qty.is_some_and(|p| tick.quantity >= p) || zscore.is_some_and(|z| z >= self.config.zscore));
+        let large = notional >= self.config.min_notional || outlier;
+        if large {
+            self.fresh.push(WhaleEvent {
+                kind: WhaleKind::LargeTrade, side, price: tick.price,
+                quantity: tick.quantity, notional, fills: 1, zscore,
+            });
+            self.add_flow(tick.timestamp, side, notional);
+        }
+        self.add_size(tick.quantity);
+        self.update_cluster(tick, side, notional, large);
+
+        while self.flow.front().is_some_and(|(t, _)| tick.timestamp - *t > self.config.lookback) {
+            self.flow.pop_front();
+        }
+    }
+
+    /// Net taker notional of whale trades in the lookback before the last tick: positive is buying.
+    pub fn net_flow(&self) -> f64 {
+        self.flow.iter().map(|(_, n)| n).sum()
+    }
+
+    /// False once a full window of trades all had one size, as on a polled price feed
+    /// that fills in a fixed quantity. Whale flow means nothing there.
+    pub fn has_sizes(&self) -> bool {
+        !self.sizeless
+    }
+
+    /// UP needs whales net buying in the lookback, DOWN net selling.
+    pub fn confirms(&self, direction: &str) -> bool {
+        let flow = self.net_flow();
+        match direction {
+            "UP" => flow > 0.0,
+            "DOWN" => flow < 0.0,
+            _ => false,
+        }
+    }
+
+    /// Events detected since the last call, for logs and alerts.
+    pub fn drain_events(&mut self) -> Vec<WhaleEvent> {
+        std::mem::take(&mut self.fresh)
+    }
+
+    fn add_flow(&mut self, time: DateTime<Utc>, side: Side, notional: f64) {
+        if notional > 0.0 {
+            self.flow.push_back((time, if side == Side::Buy { notional } else { -notional }));
+        }
+    }
+
+    fn zscore(&self, quantity: f64) -> Option<f64> {
+        let n = self.sizes.len() as f64;
+        if self.sizes.len() < self.config.min_samples || n < 2.0 { return None; }
+        // Trade sizes are heavy-tailed; log sizes are close enough to normal
+        let mean = self.log_sum / n;
+        let var = (self.log_sum_sq / n - mean * mean).max(0.0);
+        (var > 0.0).then(|| (quantity.ln() - mean) / var.sqrt())
+    }
+
+    fn add_size(&mut self, quantity: f64) {
+        let ln = quantity.ln();
+        self.sizes.push_back(quantity);
+        self.log_sum += ln;
+        self.log_sum_sq += ln * ln;
+        if self.sizes.len() > self.config.window {
+            if let Some(old) = self.sizes.pop_front() {
+                let ln = old.ln();
+                self.log_sum -= ln;
+                self.log_sum_sq -= ln * ln;
+            }
+        }
+
+        self.since_refresh += 1;
+        if self.since_refresh >= PERCENTILE_REFRESH || self.percentile_qty.is_none() {
+            self.since_refresh = 0;
+            let mut sorted: Vec<f64> = self.sizes.iter().copied().collect();
+            sorted.sort_by(|a, b| a.total_cmp(b));
+            let idx = ((self.config.percentile / 100.0) * (sorted.len() - 1) as f64).round() as usize;
+            self.percentile_qty = sorted.get(idx).copied();
+            // Re-sum so the rolling sums do not drift
+            self.log_sum = sorted.iter().map(|q| q.ln()).sum();
+            self.log_sum_sq = sorted.iter().map(|q| q.ln().powi(2)).sum();
+
+            let sizeless = sorted.len() >= self.config.min_samples && sorted.first() == sorted.last();
+            if sizeless != self.sizeless {
+                self.sizeless = sizeless;
+                if sizeless {
+                    warn!("⚠️ WHALE GATE SKIPPED: every trade is {} BTC, the feed has no real sizes; confirming on OFI alone", sorted[0]);
+                } else {
+                    info!("🐋 WHALE GATE BACK ON: trade sizes vary again");
+                }
+            }
+        }
+    }
+
+    /// A reported cluster keeps adding its fills to the flow until it breaks up.
+    fn update_cluster(&mut self, tick: &TradeData, side: Side, notional: f64, large: bool) {
+        let gap = self.config.burst_gap;
+        let cluster = match self.cluster.as_mut() {
+            Some(c) if c.side == side && tick.timestamp - c.last <= gap => {
+                c.same_price &= c.price == tick.price;
+                c.last = tick.timestamp;
+                c.quantity += tick.quantity;
+                c.notional += notional;
+                c.fills += 1;
+                c
+            }
+            _ => self.cluster.insert(Cluster {
+                side, last: tick.timestamp, price: tick.price, same_price: true,
+                quantity: tick.quantity, notional, fills: 1, counted: 0.0, reported: false,
+            }),
+        };
+        if large {
+            cluster.counted += notional;
+        }
+        // One report per cluster, the first time it crosses the threshold
+        if cluster.reported || cluster.fills < 2 || cluster.notional < self.config.burst_notional {
+            let pending = if cluster.reported { cluster.notional - cluster.counted } else { 0.0 };
+            cluster.counted += pending;
+            self.add_flow(tick.timestamp, side, pending);
+            return;
+        }
+        cluster.reported = true;
+        let kind = if cluster.same_price && cluster.fills >= self.config.iceberg_fills { WhaleKind::Iceberg } else { WhaleKind::Burst };
+        let event = WhaleEvent {
+            kind, side, price: tick.price,
+            quantity: cluster.quantity, notional: cluster.notional, fills: cluster.fills, zscore: None,
+        };
+        let uncounted = cluster.notional - cluster.counted;
+        cluster.counted = cluster.notional;
+        self.fresh.push(event);
+        self.add_flow(tick.timestamp, side, uncounted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector() -> WhaleDetector {
+        WhaleDetector::new(WhaleConfig {
+            window: 200,
+            min_samples: 20,
+            percentile: 99.0,
+            zscore: 3.0,
+            min_notional: 100_000.0,
+            burst_gap: Duration::milliseconds(500),
+            burst_notional: 250_000.0,
+            iceberg_fills: 5,
+            lookback: Duration::seconds(60),
+            ofi_min_volume: 1.0,
+        })
+    }
+
+    fn trade(ms: i64, price: f64, quantity: f64, side: Side) -> TradeData {
+        let start: DateTime<Utc> = "2026-01-01T10:00:00Z".parse().unwrap();
+        TradeData { timestamp: start + Duration::milliseconds(ms), price, quantity, is_buyer_maker: side == Side::Sell }
+    }
+
+    /// Small trades a second apart, alternating sides so no cluster forms.
+    fn warm(whales: &mut WhaleDetector, n: i64) {
+        for i in 0..n {
+            let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
+            whales.update(&trade(i * 1000, 50_000.0, 0.01 + (i % 5) as f64 * 0.01, side));
+        }
+        whales.drain_events();
+    }
+
+    #[test]
+    fn a_large_print_confirms_its_side_for_the_lookback() {
+        let mut whales = detector();
+        whales.update(&trade(0, 50_000.0, 3.0, Side::Sell));
+        let events = whales.drain_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].kind, events[0].side, events[0].notional), (WhaleKind::LargeTrade, Side::Sell, 150_000.0));
+        assert!(whales.confirms("DOWN") && !whales.confirms("UP") && !whales.confirms("NEUTRAL"));
+
+        whales.update(&trade(61_000, 50_000.0, 0.01, Side::Buy));
+        assert_eq!(whales.net_flow(), 0.0);
+        assert!(!whales.confirms("DOWN"));
+    }
+
+    #[test]
+    fn a_size_outlier_counts_only_once_the_distribution_is_warm() {
+        let mut cold = detector();
+        warm(&mut cold, 5);
+        cold.update(&trade(10_000, 50_000.0, 0.3, Side::Buy)); // $15k: over min_notional / 10
+        assert!(cold.drain_events().is_empty());
+
+        let mut whales = detector();
+        warm(&mut whales, 30);
+        whales.update(&trade(40_000, 50_000.0, 0.3, Side::Buy));
+        let events = whales.drain_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, WhaleKind::LargeTrade);
+        assert!(events[0].zscore.is_some_and(|z| z > 3.0));
+        assert!(whales.confirms("UP"));
+    }
+
+    #[test]
+    fn same_side_fills_in_quick_succession_make_a_burst() {
+        let mut whales = detector();
+        // $50k each, none large alone; the fifth takes the run to $250k
+        for i in 0..5 {
+            whales.update(&trade(i * 100, 50_000.0 + i as f64, 1.0, Side::Buy));
+            assert_eq!(whales.drain_events().len(), usize::from(i == 4));
+        }
+        whales.update(&trade(500, 50_010.0, 1.0, Side::Buy));
+        assert!(whales.drain_events().is_empty()); // One report per cluster
+        assert_eq!(whales.net_flow(), 50_000.0 * 5.0 + 10.0 + 50_010.0);
+
+        // A gap over WHALE_BURST_MS starts a new cluster
+        whales.update(&trade(2_000, 50_000.0, 1.0, Side::Buy));
+        assert!(whales.drain_events().is_empty());
+    }
+
+    #[test]
+    fn a_burst_at_one_price_is_an_iceberg() {
+        let mut whales = detector();
+        for i in 0..5 {
+            whales.update(&trade(i * 100, 50_000.0, 1.0, Side::Sell));
+        }
+        let events = whales.drain_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].kind, events[0].fills, events[0].side), (WhaleKind::Iceberg, 5, Side::Sell));
+        assert!(whales.confirms("DOWN"));
+    }
+
+    #[test]
+    fn a_feed_with_one_fixed_size_turns_the_gate_off() {
+        let mut whales = detector();
+        for i in 0..150 {
+            whales.update(&trade(i * 1000, 50_000.0, 0.1, Side::Buy));
+        }
+        assert!(!whales.has_sizes());
+
+        warm(&mut whales, 150);
+        assert!(whales.has_sizes());
+    }
+}